@@ -1,11 +1,9 @@
 //! Ugly
 use clap::Parser;
-use grba_core::emulator::cartridge::header::CartridgeHeader;
-use grba_core::emulator::cartridge::Cartridge;
-use grba_core::emulator::{EmuOptions, GBAEmulator};
-use std::ops::{Deref, DerefMut};
+use grba_core::emulator::timing::SubsystemTimings;
+use grba_core::emulator::GBAEmulator;
 use std::path::{Path, PathBuf};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 #[derive(clap::Parser, Debug)]
 #[clap(version, about)]
@@ -17,55 +15,63 @@ pub struct Args {
     pub frames: u32,
     #[clap(short, default_value = "roms/gba_bios.bin")]
     pub bios: PathBuf,
+    /// Print a breakdown of the time spent in each subsystem at the end
+    #[clap(long)]
+    pub profile: bool,
 }
 
 fn main() {
     let args = Args::parse();
-    let (mut emulator, header) = get_emu(&args.rom_path, &args.bios);
+    let mut emulator = get_emu(&args.rom_path, &args.bios);
     println!("Running {:?} for {} frames", args.rom_path, args.frames);
 
+    if args.profile {
+        emulator.subsystem_timings = Some(SubsystemTimings::default());
+    }
+
     let start = Instant::now();
 
     for _ in 0..args.frames {
         emulator.run_to_vblank();
     }
 
+    let elapsed = start.elapsed();
+
     println!(
         "Executing took {:.2?} for a total of {:.2} frames per second",
-        start.elapsed(),
-        args.frames as f64 / start.elapsed().as_secs_f64()
+        elapsed,
+        args.frames as f64 / elapsed.as_secs_f64()
     );
-}
-
-pub fn get_emu(rom: impl AsRef<Path>, bios: impl AsRef<Path>) -> (GBAEmulator, CartridgeHeader) {
-    let rom = std::fs::read(rom).expect("Could not find the provided ROM");
-    let ram = Box::new(MemoryRam {
-        data: grba_core::box_array![0u8; grba_core::emulator::cartridge::CARTRIDGE_RAM_SIZE],
-    });
-    let bios = std::fs::read(bios).unwrap();
 
-    let cartridge = Cartridge::new(rom, ram);
-    let header = cartridge.header().clone();
-
-    let mut options = EmuOptions::default();
-    options.bios = Some(bios);
-    (GBAEmulator::new(cartridge, options), header)
+    if let Some(timings) = emulator.subsystem_timings {
+        print_breakdown(&timings, elapsed);
+    }
 }
 
-struct MemoryRam {
-    data: Box<[u8; grba_core::emulator::cartridge::CARTRIDGE_RAM_SIZE]>,
-}
+fn print_breakdown(timings: &SubsystemTimings, elapsed: Duration) {
+    let subsystems = [
+        ("CPU", elapsed.saturating_sub(timings.total())),
+        ("PPU", timings.ppu),
+        ("DMA", timings.dma),
+        ("Timers", timings.timers),
+        ("Interrupts", timings.interrupts),
+    ];
 
-impl Deref for MemoryRam {
-    type Target = [u8];
+    println!("Subsystem breakdown:");
 
-    fn deref(&self) -> &Self::Target {
-        &*self.data
+    for (name, time) in subsystems {
+        println!(
+            "  {:<10} {:>10.2?} ({:.1}%)",
+            name,
+            time,
+            time.as_secs_f64() / elapsed.as_secs_f64() * 100.0
+        );
     }
 }
 
-impl DerefMut for MemoryRam {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.data
-    }
+pub fn get_emu(rom: impl AsRef<Path>, bios: impl AsRef<Path>) -> GBAEmulator {
+    let rom = std::fs::read(rom).expect("Could not find the provided ROM");
+    let bios = std::fs::read(bios).unwrap();
+
+    grba_core::headless::headless_emulator(rom, Some(bios), Default::default()).expect("Invalid ROM or BIOS")
 }
@@ -21,31 +21,24 @@ impl GbaBios {
         }
     }
 
-    pub fn read_32(&mut self, addr: MemoryAddress, cpu: &CPU) -> u32 {
-        let pc_in_bios = Self::is_in_bios_region(cpu.registers.pc());
-        if pc_in_bios {
-            let addr = addr as usize;
-
-            let read_opcode = u32::from_le_bytes(self.data[addr..addr + 4].try_into().unwrap());
-            self.latest_read_instr = read_opcode;
-
-            read_opcode
-        } else {
-            self.latest_read_instr
-        }
-    }
-
+    /// Read a byte from the BIOS.
+    ///
+    /// The BIOS can only be read while the CPU is executing from it, in all other cases the most recently fetched BIOS
+    /// opcode is returned instead. Data reads within the BIOS don't affect this value, only opcode fetches do.
     pub fn read(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
-        let pc_in_bios = Self::is_in_bios_region(cpu.registers.pc());
+        let pc = cpu.registers.pc();
         let addr = addr as usize;
 
-        if pc_in_bios {
+        if Self::is_in_bios_region(pc) {
             let read_byte = self.data[addr];
-            // Reading from bios, so we should update the latest read opcode
-            let mut current_opcode: [u8; 4] = self.latest_read_instr.to_le_bytes();
-            current_opcode[addr % 4] = read_byte;
 
-            self.latest_read_instr = u32::from_le_bytes(current_opcode);
+            // Opcode fetches always happen at the current `PC`.
+            if addr & !3 == pc as usize & !3 {
+                let mut current_opcode: [u8; 4] = self.latest_read_instr.to_le_bytes();
+                current_opcode[addr % 4] = read_byte;
+
+                self.latest_read_instr = u32::from_le_bytes(current_opcode);
+            }
 
             read_byte
         } else {
@@ -57,3 +50,52 @@ impl GbaBios {
         addr <= BIOS_REGION_END
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::PC_REG;
+    use crate::emulator::EmuOptions;
+
+    #[test]
+    fn test_bios_read_protection() {
+        let bios: Vec<u32> = vec![
+            // MOV r1, #0x0800_0000
+            0xE3A0_1408,
+            // BX r1
+            0xE12F_FF11,
+            0x1111_1111,
+            // Fetched while executing the `BX`
+            0xDEAD_BEEF,
+            0,
+            0x1234_5678,
+        ];
+        let mut bios: Vec<u8> = bios.into_iter().flat_map(u32::to_le_bytes).collect();
+        bios.resize(0x4000, 0);
+        let rom = crate::emulator::cpu::test_utils::arm_rom(&[
+            // LDR r0, [r2]
+            0xE592_0000,
+            // LDRB r3, [r2, #1]
+            0xE5D2_3001,
+        ]);
+        let options = EmuOptions {
+            skip_bios: false,
+            ..Default::default()
+        };
+        let mut emu = crate::headless::headless_emulator(rom, Some(bios), options).unwrap();
+
+        for _ in 0..4 {
+            emu.step_instruction();
+        }
+
+        // Reading the BIOS from ROM code should only expose the last fetched opcode.
+        assert_eq!(emu.cpu.read_reg(0), 0xDEAD_BEEF);
+        assert_eq!(emu.cpu.read_reg(3), 0xBE);
+
+        // While executing in the BIOS everything is readable, without changing the last fetched opcode.
+        emu.cpu.registers.general_purpose[PC_REG] = 0x10;
+        assert_eq!(emu.bus.read_32(0x14, &emu.cpu), 0x1234_5678);
+
+        emu.cpu.registers.general_purpose[PC_REG] = 0x0800_0010;
+        assert_eq!(emu.bus.read_32(0x14, &emu.cpu), 0xDEAD_BEEF);
+    }
+}
@@ -1,17 +1,68 @@
 //! All debug related functionality for the [Bus] component.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::ops::RangeInclusive;
 
 use crate::emulator::bus::dma::{DMA_0_ADDR_START, DMA_3_ADDR_END};
+use crate::emulator::bus::interrupts::{IE_START, IME_START};
+use crate::emulator::bus::keypad::KEYINTERRUPT_START;
+use crate::emulator::bus::sound::SOUND_BIAS_START;
+use crate::emulator::bus::system_control::WAIT_CNT_START;
+use crate::emulator::bus::timers::{TIMER_IO_END, TIMER_IO_START};
 use crate::emulator::bus::{Bus, IO_START};
 use crate::emulator::cpu::CPU;
 use crate::emulator::ppu::LCD_IO_END;
 use crate::emulator::MemoryAddress;
 
+/// The halfwords restored by [Bus::reset_io_registers], as inclusive `(start, end)` ranges.
+///
+/// Registers with side effects beyond their own value, like acknowledging interrupts through `IF`, are left out.
+const RESETTABLE_IO: [(MemoryAddress, MemoryAddress); 8] = [
+    // LCD, the final halfword is unused.
+    (IO_START, LCD_IO_END - 2),
+    (SOUND_BIAS_START, SOUND_BIAS_START),
+    (DMA_0_ADDR_START, DMA_3_ADDR_END - 1),
+    (TIMER_IO_START, TIMER_IO_END - 1),
+    (KEYINTERRUPT_START, KEYINTERRUPT_START),
+    (IE_START, IE_START),
+    (WAIT_CNT_START, WAIT_CNT_START),
+    (IME_START, IME_START),
+];
+
+/// The resettable registers which don't power on as `0`, as `(address, value)` halfwords.
+const NON_ZERO_IO_DEFAULTS: [(MemoryAddress, u16); 6] = [
+    // `DISPCNT` starts out with forced blank enabled.
+    (IO_START, 0x0080),
+    // `BG2PA`, `BG2PD`, `BG3PA`, and `BG3PD` start out as the identity matrix.
+    (0x0400_0020, 0x0100),
+    (0x0400_0026, 0x0100),
+    (0x0400_0030, 0x0100),
+    (0x0400_0036, 0x0100),
+    (SOUND_BIAS_START, 0x0200),
+];
+
+/// The power-on value of the IO halfword at `addr`, or `None` if it can't be reset by [Bus::reset_io_registers].
+pub fn io_register_default(addr: MemoryAddress) -> Option<u16> {
+    let addr = addr & !1;
+
+    if !RESETTABLE_IO.iter().any(|&(start, end)| (start..=end).contains(&addr)) {
+        return None;
+    }
+
+    let default = NON_ZERO_IO_DEFAULTS
+        .iter()
+        .find(|&&(address, _)| address == addr)
+        .map_or(0, |&(_, value)| value);
+
+    Some(default)
+}
+
 impl Bus {
     #[inline]
     pub fn read_dbg(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
         match Self::get_mem_range(addr) {
             4 => self.read_io_dbg(addr, cpu),
-            _ => self.read(addr, cpu),
+            _ => self.read_byte(addr, cpu),
         }
     }
 
@@ -25,23 +76,131 @@ impl Bus {
                 let current_data = (self.ppu.read_oam(addr.wrapping_add(1)) as u16) << 8;
                 self.ppu.write_oam_16(addr, current_data | data as u16)
             }
-            _ => self.write(addr, data),
+            _ => self.write_byte(addr, data),
         }
     }
 
+    /// The wait states of a 16 bit EWRAM access, including any debug override.
+    pub fn ewram_wait_states(&self) -> u8 {
+        self.system_control.ewram_wait_states()
+    }
+
+    /// Override the EWRAM wait states set by the game through the internal memory control register.
+    ///
+    /// Passing `None` restores the game's configuration.
+    pub fn set_ewram_wait_states_override(&mut self, wait_states: Option<u8>) {
+        self.system_control.set_ewram_wait_states_override(wait_states);
+    }
+
+    /// Restore all IO registers within `range` to their power-on value, see [io_register_default].
+    ///
+    /// The registers are written like the CPU would, so read-only bits keep their current value.
+    pub fn reset_io_registers(&mut self, range: RangeInclusive<MemoryAddress>) {
+        for addr in ((range.start() & !1)..=*range.end()).step_by(2) {
+            if let Some(value) = io_register_default(addr) {
+                self.write_io_16(addr, value);
+            }
+        }
+    }
+
+    /// Create a report of all unimplemented IO registers the game has accessed so far, and how often.
+    ///
+    /// Returns `None` if no such access has taken place.
+    pub fn unhandled_io_report(&self) -> Option<String> {
+        self.unhandled_io.report()
+    }
+
     #[inline]
     fn read_io_dbg(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
         match addr {
             IO_START..=LCD_IO_END => self.ppu.read_io_dbg(addr),
             DMA_0_ADDR_START..=DMA_3_ADDR_END => self.dma.read_debug(addr),
-            _ => self.read_io(addr, cpu),
+            _ => {
+                // Reads from the debugger shouldn't end up in the unhandled IO statistics.
+                self.unhandled_io.paused = true;
+                let result = self.read_io(addr, cpu);
+                self.unhandled_io.paused = false;
+                result
+            }
         }
     }
 
     #[inline]
     fn write_io_dbg(&mut self, addr: MemoryAddress, data: u8) {
         match addr {
+            IO_START..=LCD_IO_END => self.ppu.write_io_dbg(addr, data),
             _ => self.write_io(addr, data),
         }
     }
 }
+
+/// Keeps count of all accesses to IO registers which aren't implemented, to help prioritise what to implement next.
+#[derive(Debug, Default, Clone)]
+pub struct UnhandledIoTracker {
+    pub reads: BTreeMap<MemoryAddress, u64>,
+    pub writes: BTreeMap<MemoryAddress, u64>,
+    /// If `true` no accesses will be recorded.
+    paused: bool,
+}
+
+impl UnhandledIoTracker {
+    #[inline]
+    pub fn record_read(&mut self, addr: MemoryAddress) {
+        if !self.paused {
+            *self.reads.entry(addr).or_default() += 1;
+        }
+    }
+
+    #[inline]
+    pub fn record_write(&mut self, addr: MemoryAddress) {
+        if !self.paused {
+            *self.writes.entry(addr).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self) -> Option<String> {
+        if self.reads.is_empty() && self.writes.is_empty() {
+            return None;
+        }
+
+        let mut result = String::from("Unhandled IO accesses:");
+
+        for (kind, accesses) in [("Read", &self.reads), ("Write", &self.writes)] {
+            for (addr, count) in accesses {
+                let _ = write!(result, "\n{kind:<5} {addr:#010X} - {count} time(s)");
+            }
+        }
+
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::debug::io_register_default;
+    use crate::emulator::bus::IO_START;
+    use crate::emulator::ppu::LCD_IO_END;
+    use crate::emulator::EmuOptions;
+
+    #[test]
+    fn test_reset_io_registers() {
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        // DISPCNT, BG0CNT, BG2PA, and SOUNDBIAS
+        emu.bus.write_16(0x0400_0000, 0x0403);
+        emu.bus.write_16(0x0400_0008, 0x1234);
+        emu.bus.write_16(0x0400_0020, 0x0200);
+        emu.bus.write_16(0x0400_0088, 0x0100);
+
+        emu.bus.reset_io_registers(IO_START..=LCD_IO_END);
+
+        assert_eq!(emu.bus.ppu.read_io_dbg(0x0400_0000), 0x80);
+        assert_eq!(emu.bus.ppu.read_io_dbg(0x0400_0008), 0);
+        assert_eq!(emu.bus.ppu.read_io_dbg(0x0400_0021), 0x01);
+        // Outside of the given range
+        assert_eq!(emu.bus.read_dbg(0x0400_0089, &emu.cpu), 0x01);
+
+        assert_eq!(io_register_default(0x0400_0089), Some(0x0200));
+        // IF has side effects when written
+        assert_eq!(io_register_default(0x0400_0202), None);
+    }
+}
@@ -52,9 +52,17 @@ impl Bus {
         // TODO: Make this not an instant transfer by ticking scheduler & checking for higher priority DMAs
         match channel.control.dma_transfer_type() {
             DmaTransferType::U16 => {
-                for _ in 0..transfer_state.length {
+                for unit in 0..transfer_state.length {
                     let value = self.read_16(transfer_state.source_address, cpu);
                     self.write_16(transfer_state.dest_address, value);
+                    self.check_dma_watchpoints(
+                        channel_idx,
+                        unit * 2,
+                        transfer_state.source_address,
+                        transfer_state.dest_address,
+                    );
+                    // Halfwords are driven on both halves of the data bus.
+                    self.dma_bus_latch = Some(value as u32 * 0x0001_0001);
                     // Two's complement allows us to just cast i32 to u32 for this
                     transfer_state.dest_address = transfer_state
                         .dest_address
@@ -65,9 +73,16 @@ impl Bus {
                 }
             }
             DmaTransferType::U32 => {
-                for _ in 0..transfer_state.length {
+                for unit in 0..transfer_state.length {
                     let value = self.read_32(transfer_state.source_address, cpu);
                     self.write_32(transfer_state.dest_address, value);
+                    self.check_dma_watchpoints(
+                        channel_idx,
+                        unit * 4,
+                        transfer_state.source_address,
+                        transfer_state.dest_address,
+                    );
+                    self.dma_bus_latch = Some(value);
                     // Two's complement allows us to just cast i32 to u32 for this
                     transfer_state.dest_address = transfer_state
                         .dest_address
@@ -105,6 +120,15 @@ impl Bus {
         }
     }
 
+    /// Record the first transfer which touches a range watched by the debugger, see
+    /// [crate::emulator::debug::DmaWatchpoints].
+    #[inline(always)]
+    #[cfg_attr(not(feature = "debug-functionality"), allow(unused_variables))]
+    fn check_dma_watchpoints(&mut self, channel_idx: usize, offset: u32, source: MemoryAddress, dest: MemoryAddress) {
+        #[cfg(feature = "debug-functionality")]
+        self.dma_watch.check(channel_idx, offset, source, dest);
+    }
+
     /// At the moment we'll just poll.
     ///
     /// This can be implemented more efficiently by keeping 2 sorted Vecs (HBLANK,VBLANK) with current channels.
@@ -0,0 +1,146 @@
+//! Per memory region access counters, to get an idea of where a game spends its bus bandwidth.
+use std::collections::VecDeque;
+
+use crate::emulator::bus::Bus;
+use crate::emulator::MemoryAddress;
+
+/// The amount of regions accesses are bucketed into, one for each value of the upper byte of the address.
+pub const REGION_COUNT: usize = 16;
+
+/// The name of each region, indexed by the upper byte of the address.
+pub const REGION_NAMES: [&str; REGION_COUNT] = [
+    "BIOS",
+    "Unmapped",
+    "EWRAM",
+    "IWRAM",
+    "IO",
+    "Palette",
+    "VRAM",
+    "OAM",
+    "ROM WS0",
+    "ROM WS0 (Mirror)",
+    "ROM WS1",
+    "ROM WS1 (Mirror)",
+    "ROM WS2",
+    "ROM WS2 (Mirror)",
+    "SRAM",
+    "SRAM (Mirror)",
+];
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RegionAccesses {
+    pub reads: [u64; REGION_COUNT],
+    pub writes: [u64; REGION_COUNT],
+}
+
+impl RegionAccesses {
+    fn add(&mut self, other: &RegionAccesses) {
+        for i in 0..REGION_COUNT {
+            self.reads[i] += other.reads[i];
+            self.writes[i] += other.writes[i];
+        }
+    }
+}
+
+/// Counts every CPU and DMA access to the [Bus], grouped by memory region.
+///
+/// Every access is counted once at the width it was issued with, so a 32 bit write is recorded as a single write.
+/// Debug reads and writes are never counted.
+#[derive(Debug, Clone, Default)]
+pub struct AccessHeatmap {
+    current_frame: RegionAccesses,
+    /// The accesses of the most recently completed frames, oldest first.
+    history: VecDeque<RegionAccesses>,
+    /// All accesses of completed frames since the last [Self::reset].
+    cumulative: RegionAccesses,
+}
+
+impl AccessHeatmap {
+    /// The amount of completed frames kept around for [Self::last_frames].
+    pub const MAX_HISTORY: usize = 300;
+
+    #[inline]
+    pub fn record_read(&mut self, addr: MemoryAddress) {
+        self.current_frame.reads[Self::region(addr)] += 1;
+    }
+
+    #[inline]
+    pub fn record_write(&mut self, addr: MemoryAddress) {
+        self.current_frame.writes[Self::region(addr)] += 1;
+    }
+
+    /// Should be called at the start of every VBlank to close off the current frame.
+    pub fn end_frame(&mut self) {
+        let frame = std::mem::take(&mut self.current_frame);
+
+        self.cumulative.add(&frame);
+
+        if self.history.len() == Self::MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(frame);
+    }
+
+    /// The summed accesses of the last `frames` completed frames, capped at [Self::MAX_HISTORY].
+    pub fn last_frames(&self, frames: usize) -> RegionAccesses {
+        self.history
+            .iter()
+            .rev()
+            .take(frames)
+            .fold(RegionAccesses::default(), |mut acc, frame| {
+                acc.add(frame);
+                acc
+            })
+    }
+
+    /// The summed accesses of all completed frames since the last [Self::reset].
+    pub fn cumulative(&self) -> RegionAccesses {
+        self.cumulative
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    #[inline(always)]
+    fn region(addr: MemoryAddress) -> usize {
+        let region = Bus::get_mem_range(addr) as usize;
+        // The upper four bits of the address bus are unused, and would just result in open bus reads.
+        if region < REGION_COUNT {
+            region
+        } else {
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::heatmap::AccessHeatmap;
+
+    #[test]
+    fn test_access_heatmap() {
+        let mut heatmap = AccessHeatmap::default();
+
+        heatmap.record_read(0x0300_0000);
+        heatmap.record_write(0x0600_0010);
+        heatmap.end_frame();
+
+        heatmap.record_read(0x0300_0004);
+        heatmap.record_read(0x1000_0000);
+        heatmap.end_frame();
+
+        let last = heatmap.last_frames(1);
+        assert_eq!(last.reads[3], 1);
+        assert_eq!(last.reads[1], 1);
+        assert_eq!(last.writes[6], 0);
+
+        let cumulative = heatmap.cumulative();
+        assert_eq!(cumulative.reads[3], 2);
+        assert_eq!(cumulative.writes[6], 1);
+        assert_eq!(heatmap.last_frames(AccessHeatmap::MAX_HISTORY), cumulative);
+
+        heatmap.reset();
+        assert_eq!(heatmap.cumulative(), Default::default());
+    }
+}
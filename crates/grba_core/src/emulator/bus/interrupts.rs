@@ -1,5 +1,6 @@
 use crate::emulator::MemoryAddress;
 use crate::scheduler::{EmuTime, EventTag, Scheduler};
+use crate::utils::BitOps;
 use modular_bitfield::prelude::*;
 
 pub const IE_START: MemoryAddress = 0x04000200;
@@ -38,12 +39,15 @@ impl InterruptManager {
     }
 
     pub fn write_ie(&mut self, address: MemoryAddress, value: u8) {
-        self.enable.update_byte_le((address % 2) as usize, value);
+        let byte = (address % 2) as usize;
+        let new_value = u16::from(self.enable).change_byte_le_masked(byte, value, InterruptEnable::WRITABLE_BITS);
+
+        self.enable = InterruptEnable::from(new_value);
     }
 
     pub fn write_if(&mut self, address: MemoryAddress, value: u8, scheduler: &mut Scheduler) {
         let current_value = self.read_if(address);
-        // By writing a `1` to a bit that was already set, you indicate the interrupt has been handled.
+        // Writing a `1` to a bit acknowledges (clears) the interrupt, writing a `0` leaves the flag untouched.
         let new_value = current_value & !value;
 
         self.flags.update_byte_le((address % 2) as usize, new_value);
@@ -55,7 +59,11 @@ impl InterruptManager {
     pub fn write_ime(&mut self, address: MemoryAddress, value: u8, scheduler: &mut Scheduler) {
         // As interrupts can be enabled here we need to schedule a check for awaiting interrupts
         self.schedule_interrupt(scheduler);
-        self.master_enable.update_byte_le((address % 4) as usize, value);
+        let byte = (address % 4) as usize;
+        let new_value =
+            u32::from(self.master_enable).change_byte_le_masked(byte, value, InterruptMasterEnable::WRITABLE_BITS);
+
+        self.master_enable = InterruptMasterEnable::from(new_value);
     }
 
     /// Schedule an interrupt to be checked by the CPU.
@@ -70,6 +78,10 @@ impl InterruptManager {
         self.schedule_interrupt(scheduler);
     }
 
+    /// Schedule a check for pending interrupts at the next instruction boundary.
+    ///
+    /// The event is scheduled in the past, which makes it the first event to be handled after the current instruction
+    /// (only a pending `Halt`, at time `0`, goes before it).
     pub fn schedule_interrupt(&mut self, scheduler: &mut Scheduler) {
         scheduler.schedule_event(EventTag::PollInterrupt, EmuTime(1));
     }
@@ -81,6 +93,9 @@ impl InterruptManager {
 #[derive(Debug, Copy, Clone, PartialOrd, PartialEq)]
 #[repr(u16)]
 pub enum Interrupts {
+    /// Raised through the cartridge's `IREQ` line by some accessories, or when the cartridge is removed.
+    ///
+    /// Never raised by the emulator on its own, see [crate::emulator::GBAEmulator::raise_game_pak_interrupt].
     GamePak = 1 << 13,
     Keypad = 1 << 12,
     DMA3 = 1 << 11,
@@ -122,6 +137,11 @@ pub struct InterruptEnable {
     unused: B2,
 }
 
+impl InterruptEnable {
+    /// Bits 14..=15 are unused.
+    pub const WRITABLE_BITS: u16 = 0x3FFF;
+}
+
 /// If a flag is `true` then request interrupt
 #[bitfield(bits = 16)]
 #[repr(u16)]
@@ -159,3 +179,8 @@ pub struct InterruptMasterEnable {
     #[skip]
     unused: B31,
 }
+
+impl InterruptMasterEnable {
+    /// Only bit 0 is used.
+    pub const WRITABLE_BITS: u32 = 0x1;
+}
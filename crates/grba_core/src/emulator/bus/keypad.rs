@@ -1,7 +1,7 @@
 use crate::emulator::bus::interrupts::{InterruptManager, Interrupts};
 use crate::emulator::MemoryAddress;
 use crate::scheduler::Scheduler;
-use crate::InputKeys;
+use crate::{InputKeys, InputState};
 
 pub const KEYSTATUS_START: MemoryAddress = 0x0400_0130;
 pub const KEYSTATUS_END: MemoryAddress = 0x0400_0131;
@@ -57,20 +57,72 @@ impl Keypad {
             }
         }
 
-        if self.interrupt_control.button_irq_enable() {
-            let irq_buttons = u16::from_le_bytes(self.interrupt_control.to_le_bytes()) & 0x3FF;
-            // We invert it to get it such that the bit is set if the button is pressed
-            let buttons = (!u16::from_le_bytes(self.status.to_le_bytes())) & 0x3FF;
-
-            if self.interrupt_control.button_irq_condition() {
-                // Logical and, interrupt requested if ALL of the desired buttons are pressed
-                if (buttons & irq_buttons) == irq_buttons {
-                    interrupt.request_interrupt(Interrupts::Keypad, scheduler);
-                }
-            } else if buttons & irq_buttons != 0 {
-                // Logical or, interrupt requested if ANY of the desired buttons is pressed
-                interrupt.request_interrupt(Interrupts::Keypad, scheduler);
-            }
+        self.check_interrupt(scheduler, interrupt);
+    }
+
+    /// Set the state of all buttons at once, only checking for a keypad interrupt after all buttons have been updated.
+    pub fn set_state(&mut self, state: InputState, scheduler: &mut Scheduler, interrupt: &mut InterruptManager) {
+        // Button bit: 1 == released, 0 == pressed
+        self.status = (!state.bits() & 0x3FF).into();
+
+        self.check_interrupt(scheduler, interrupt);
+    }
+
+    /// The buttons which are currently held.
+    pub fn state(&self) -> InputState {
+        InputState::from_bits_truncate(!u16::from_le_bytes(self.status.to_le_bytes()))
+    }
+
+    /// Write to the `KEYCNT` register.
+    ///
+    /// As the IRQ condition could be satisfied by the new value (for example, when games enable the IRQ just before
+    /// entering `STOP`/`HALT` while buttons are already held) this also checks for a new keypad interrupt.
+    pub fn write_interrupt_control(
+        &mut self,
+        address: MemoryAddress,
+        value: u8,
+        scheduler: &mut Scheduler,
+        interrupt: &mut InterruptManager,
+    ) {
+        let mut control = self.interrupt_control;
+        control.update_byte_le((address - KEYINTERRUPT_START) as usize, value);
+
+        self.write_interrupt_control_16(control.into(), scheduler, interrupt);
+    }
+
+    /// Write the full `KEYCNT` register at once, only checking for a keypad interrupt with the fully assembled value.
+    pub fn write_interrupt_control_16(
+        &mut self,
+        value: u16,
+        scheduler: &mut Scheduler,
+        interrupt: &mut InterruptManager,
+    ) {
+        self.interrupt_control = value.into();
+
+        self.check_interrupt(scheduler, interrupt);
+    }
+
+    /// Request a [Interrupts::Keypad] interrupt if the keypad IRQ is enabled and the currently pressed buttons satisfy
+    /// the selected IRQ condition.
+    fn check_interrupt(&self, scheduler: &mut Scheduler, interrupt: &mut InterruptManager) {
+        if !self.interrupt_control.button_irq_enable() {
+            return;
+        }
+
+        let irq_buttons = u16::from_le_bytes(self.interrupt_control.to_le_bytes()) & 0x3FF;
+        // We invert it to get it such that the bit is set if the button is pressed
+        let buttons = (!u16::from_le_bytes(self.status.to_le_bytes())) & 0x3FF;
+
+        let condition_met = if self.interrupt_control.button_irq_condition() {
+            // Logical and, interrupt requested if ALL of the desired buttons are pressed
+            (buttons & irq_buttons) == irq_buttons
+        } else {
+            // Logical or, interrupt requested if ANY of the desired buttons is pressed
+            buttons & irq_buttons != 0
+        };
+
+        if condition_met {
+            interrupt.request_interrupt(Interrupts::Keypad, scheduler);
         }
     }
 }
@@ -125,3 +177,103 @@ pub struct KeypadInterruptControl {
     /// In logical AND mode, an interrupt is requested when ALL of the selected buttons are pressed.
     button_irq_condition: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::interrupts::{InterruptManager, Interrupts};
+    use crate::emulator::bus::keypad::{Keypad, KEYINTERRUPT_START};
+    use crate::emulator::EmuOptions;
+    use crate::scheduler::Scheduler;
+    use crate::{InputKeys, InputState};
+
+    fn keypad_irq_requested(interrupts: &InterruptManager) -> bool {
+        u16::from(interrupts.flags) & Interrupts::Keypad as u16 != 0
+    }
+
+    #[test]
+    fn test_keypad_irq_or() {
+        let mut keypad = Keypad::default();
+        let mut scheduler = Scheduler::new();
+        let mut interrupts = InterruptManager::new();
+
+        // Enable the IRQ for A or B, with the logical OR condition.
+        keypad.write_interrupt_control(KEYINTERRUPT_START, 0b11, &mut scheduler, &mut interrupts);
+        keypad.write_interrupt_control(KEYINTERRUPT_START + 1, 0b0100_0000, &mut scheduler, &mut interrupts);
+
+        keypad.button_changed(InputKeys::Start, true, &mut scheduler, &mut interrupts);
+        assert!(!keypad_irq_requested(&interrupts));
+
+        keypad.button_changed(InputKeys::B, true, &mut scheduler, &mut interrupts);
+        assert!(keypad_irq_requested(&interrupts));
+    }
+
+    #[test]
+    fn test_keypad_irq_and() {
+        let mut keypad = Keypad::default();
+        let mut scheduler = Scheduler::new();
+        let mut interrupts = InterruptManager::new();
+
+        // Enable the IRQ for A and B, with the logical AND condition.
+        keypad.write_interrupt_control(KEYINTERRUPT_START, 0b11, &mut scheduler, &mut interrupts);
+        keypad.write_interrupt_control(KEYINTERRUPT_START + 1, 0b1100_0000, &mut scheduler, &mut interrupts);
+
+        keypad.button_changed(InputKeys::A, true, &mut scheduler, &mut interrupts);
+        assert!(!keypad_irq_requested(&interrupts));
+
+        keypad.button_changed(InputKeys::B, true, &mut scheduler, &mut interrupts);
+        assert!(keypad_irq_requested(&interrupts));
+    }
+
+    #[test]
+    fn test_keypad_irq_on_enable() {
+        let mut keypad = Keypad::default();
+        let mut scheduler = Scheduler::new();
+        let mut interrupts = InterruptManager::new();
+
+        keypad.button_changed(InputKeys::Start, true, &mut scheduler, &mut interrupts);
+        assert!(!keypad_irq_requested(&interrupts));
+
+        // Enabling the IRQ while the button is already held should immediately request the interrupt.
+        keypad.write_interrupt_control(KEYINTERRUPT_START, 0b1000, &mut scheduler, &mut interrupts);
+        keypad.write_interrupt_control(KEYINTERRUPT_START + 1, 0b0100_0000, &mut scheduler, &mut interrupts);
+        assert!(keypad_irq_requested(&interrupts));
+    }
+
+    #[test]
+    fn test_keypad_irq_16_bit_write() {
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        let bus = &mut emu.bus;
+
+        // Enable the IRQ for B, with the logical OR condition, while A is held.
+        bus.write_16(KEYINTERRUPT_START, 0x4002);
+        bus.keypad
+            .button_changed(InputKeys::A, true, &mut bus.scheduler, &mut bus.interrupts);
+        assert!(!keypad_irq_requested(&bus.interrupts));
+
+        // Selecting A while disabling the IRQ mustn't request an interrupt, even though the new lower byte
+        // combined with the old upper byte would.
+        bus.write_16(KEYINTERRUPT_START, 0x0001);
+        assert!(!keypad_irq_requested(&bus.interrupts));
+    }
+
+    #[test]
+    fn test_keypad_set_state() {
+        let mut keypad = Keypad::default();
+        let mut scheduler = Scheduler::new();
+        let mut interrupts = InterruptManager::new();
+
+        keypad.set_state(InputState::A | InputState::UP, &mut scheduler, &mut interrupts);
+        assert!(!keypad.status.button_a());
+        assert!(!keypad.status.up());
+        assert!(keypad.status.button_b());
+
+        // Releasing all other buttons shouldn't affect the IRQ, only the full state is considered.
+        keypad.write_interrupt_control(KEYINTERRUPT_START, 0b11, &mut scheduler, &mut interrupts);
+        keypad.write_interrupt_control(KEYINTERRUPT_START + 1, 0b1100_0000, &mut scheduler, &mut interrupts);
+        assert!(!keypad_irq_requested(&interrupts));
+
+        keypad.set_state(InputState::A | InputState::B, &mut scheduler, &mut interrupts);
+        assert!(keypad_irq_requested(&interrupts));
+        assert!(keypad.status.up());
+    }
+}
@@ -1,4 +1,4 @@
-pub use bios::BiosData;
+pub use bios::{BiosData, BIOS_SIZE};
 
 use crate::emulator::bus::bios::GbaBios;
 use crate::emulator::bus::dma::{
@@ -9,16 +9,18 @@ use crate::emulator::bus::dma::{
 use crate::emulator::bus::helpers::ReadType;
 use crate::emulator::bus::interrupts::{InterruptManager, IE_END, IE_START, IF_END, IF_START, IME_END, IME_START};
 use crate::emulator::bus::keypad::{Keypad, KEYINTERRUPT_END, KEYINTERRUPT_START, KEYSTATUS_END, KEYSTATUS_START};
+use crate::emulator::bus::sound::{SoundBias, SOUND_BIAS_END, SOUND_BIAS_START};
 use crate::emulator::bus::system_control::{
-    GbaSystemControl, HALT_CNT_ADDR, POST_BOOT_FLAG_ADDR, WAIT_CNT_END, WAIT_CNT_START,
+    GbaSystemControl, HALT_CNT_ADDR, INTERNAL_MEMORY_CONTROL_END, INTERNAL_MEMORY_CONTROL_START, POST_BOOT_FLAG_ADDR,
+    WAIT_CNT_END, WAIT_CNT_START,
 };
 use crate::emulator::bus::timers::Timers;
 use crate::emulator::cartridge::Cartridge;
+use crate::emulator::cpu::registers::State;
 use crate::emulator::cpu::CPU;
 use crate::emulator::ppu::{LCD_IO_END, PPU};
 use crate::emulator::MemoryAddress;
 use crate::scheduler::Scheduler;
-use crate::utils::BitOps;
 
 pub const IO_START: MemoryAddress = 0x0400_0000;
 
@@ -26,10 +28,13 @@ mod bios;
 #[cfg(feature = "debug-functionality")]
 mod debug;
 pub mod dma;
+#[cfg(feature = "access-heatmap")]
+pub mod heatmap;
 pub mod helpers;
 pub mod interrupts;
 pub mod keypad;
 mod ram;
+pub mod sound;
 mod system_control;
 pub mod timers;
 
@@ -44,7 +49,19 @@ pub struct Bus {
     pub system_control: GbaSystemControl,
     pub ppu: PPU,
     pub scheduler: Scheduler,
-    pub sound_bias_stub: u16,
+    pub sound_bias: SoundBias,
+    /// The last value a DMA transferred, if a DMA used the data bus after the CPU last fetched an opcode.
+    ///
+    /// Open bus reads return this instead of the prefetched opcode, see [Self::open_bus_read_32].
+    pub(crate) dma_bus_latch: Option<u32>,
+    /// The address directly after the most recent timed access, an access to it is sequential.
+    next_sequential_address: MemoryAddress,
+    #[cfg(feature = "debug-functionality")]
+    pub unhandled_io: debug::UnhandledIoTracker,
+    #[cfg(feature = "debug-functionality")]
+    pub dma_watch: crate::emulator::debug::DmaWatchpoints,
+    #[cfg(feature = "access-heatmap")]
+    pub access_heatmap: heatmap::AccessHeatmap,
 }
 
 impl Bus {
@@ -60,37 +77,85 @@ impl Bus {
             timers: Timers::new(),
             keypad: Keypad::default(),
             system_control: GbaSystemControl::new(),
-            sound_bias_stub: 0,
+            sound_bias: SoundBias::default(),
+            dma_bus_latch: None,
+            next_sequential_address: 0,
+            #[cfg(feature = "debug-functionality")]
+            unhandled_io: Default::default(),
+            #[cfg(feature = "debug-functionality")]
+            dma_watch: Default::default(),
+            #[cfg(feature = "access-heatmap")]
+            access_heatmap: Default::default(),
         };
 
         result.ppu.initial_startup(&mut result.scheduler);
+        result.load_elf_ram_segments();
 
         result
     }
 
+    /// Copy the work RAM segments of an ELF cartridge into memory, see [Cartridge::from_elf].
+    fn load_elf_ram_segments(&mut self) {
+        let segments = self.rom.elf().into_iter().flat_map(|elf| &elf.ram_segments);
+
+        for (address, contents) in segments {
+            for (i, &value) in contents.iter().enumerate() {
+                let addr = address.wrapping_add(i as MemoryAddress);
+
+                match Self::get_mem_range(addr) {
+                    2 => self.ram.write_board(addr, value),
+                    3 => self.ram.write_chip(addr, value),
+                    _ => unreachable!("ELF segments are only placed in work RAM"),
+                }
+            }
+        }
+    }
+
+    /// Set the I/O registers to the values the BIOS leaves behind before jumping to the cartridge.
+    ///
+    /// Only relevant when the BIOS is skipped.
+    pub fn initialise_post_bios_io(&mut self) {
+        self.sound_bias = SoundBias::from(SoundBias::POST_BIOS);
+    }
+
     #[inline]
     pub fn read_32(&mut self, addr: MemoryAddress, cpu: &CPU) -> u32 {
         let addr = u32::align_address(addr);
+        self.add_wait_states(addr, true);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_read(addr);
         // Temporary implementation for ease of writing.
         // In the future for performance sake we should implement an individual match for each variant, possibly.
 
         u32::from_le_bytes([
-            self.read(addr, cpu),
-            self.read(addr.wrapping_add(1), cpu),
-            self.read(addr.wrapping_add(2), cpu),
-            self.read(addr.wrapping_add(3), cpu),
+            self.read_byte(addr, cpu),
+            self.read_byte(addr.wrapping_add(1), cpu),
+            self.read_byte(addr.wrapping_add(2), cpu),
+            self.read_byte(addr.wrapping_add(3), cpu),
         ])
     }
 
     #[inline]
     pub fn read_16(&mut self, addr: MemoryAddress, cpu: &CPU) -> u16 {
         let addr = u16::align_address(addr);
+        self.add_wait_states(addr, false);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_read(addr);
 
-        u16::from_le_bytes([self.read(addr, cpu), self.read(addr.wrapping_add(1), cpu)])
+        u16::from_le_bytes([self.read_byte(addr, cpu), self.read_byte(addr.wrapping_add(1), cpu)])
     }
 
     #[inline]
     pub fn read(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
+        self.add_wait_states(addr, false);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_read(addr);
+        self.read_byte(addr, cpu)
+    }
+
+    /// Read a single byte, without any of the access timing of [Self::read].
+    #[inline]
+    fn read_byte(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
         crate::cpu_log!("bus-logging"; "Reading from {:#X}", addr);
         match Self::get_mem_range(addr) {
             0 if GbaBios::is_in_bios_region(addr) => self.bios.read(addr, cpu),
@@ -101,16 +166,17 @@ impl Bus {
             5 => self.ppu.read_palette(addr),
             6 => self.ppu.read_vram(addr),
             7 => self.ppu.read_oam(addr),
+            // The cost of these regions differs by their wait state, see `Bus::add_wait_states`
             8 | 9 => {
-                // Wait state 1
+                // Wait state 0
                 self.rom.read(addr)
             }
             0xA | 0xB => {
-                // Wait state 2
+                // Wait state 1
                 self.rom.read(addr)
             }
             0xC | 0xD => {
-                // Wait state 3
+                // Wait state 2
                 self.rom.read(addr)
             }
             0xE | 0xF => {
@@ -123,28 +189,48 @@ impl Bus {
 
     pub fn write_32(&mut self, addr: MemoryAddress, data: u32) {
         let addr = u32::align_address(addr);
+        self.add_wait_states(addr, true);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_write(addr);
         let data = data.to_le_bytes();
 
-        self.write_16(addr, u16::from_le_bytes([data[0], data[1]]));
-        self.write_16(addr.wrapping_add(2), u16::from_le_bytes([data[2], data[3]]));
+        self.write_halfword(addr, u16::from_le_bytes([data[0], data[1]]));
+        self.write_halfword(addr.wrapping_add(2), u16::from_le_bytes([data[2], data[3]]));
     }
 
     pub fn write_16(&mut self, addr: MemoryAddress, data: u16) {
         let addr = u16::align_address(addr);
+        self.add_wait_states(addr, false);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_write(addr);
+        self.write_halfword(addr, data)
+    }
 
+    /// Write a single halfword, without any of the access timing of [Self::write_16].
+    #[inline]
+    fn write_halfword(&mut self, addr: MemoryAddress, data: u16) {
         match Self::get_mem_range(addr) {
+            4 => self.write_io_16(addr, data),
             5 => self.ppu.write_palette_16(addr, data),
             6 => self.ppu.write_vram_16(addr, data),
             7 => self.ppu.write_oam_16(addr, data),
             _ => {
                 let data: [u8; 2] = data.to_le_bytes();
-                self.write(addr, data[0]);
-                self.write(addr.wrapping_add(1), data[1]);
+                self.write_byte(addr, data[0]);
+                self.write_byte(addr.wrapping_add(1), data[1]);
             }
         }
     }
 
     pub fn write(&mut self, addr: MemoryAddress, data: u8) {
+        self.add_wait_states(addr, false);
+        #[cfg(feature = "access-heatmap")]
+        self.access_heatmap.record_write(addr);
+        self.write_byte(addr, data)
+    }
+
+    /// Write a single byte, without any of the access timing of [Self::write].
+    fn write_byte(&mut self, addr: MemoryAddress, data: u8) {
         crate::cpu_log!("bus-logging"; "Writing to {:#X} - Value: {:#X}", addr, data);
         match Self::get_mem_range(addr) {
             0 => {
@@ -183,7 +269,7 @@ impl Bus {
     pub fn read_io(&mut self, addr: MemoryAddress, cpu: &CPU) -> u8 {
         match addr {
             IO_START..=LCD_IO_END => self.ppu.read_io(addr),
-            0x4000088..=0x4000089 => self.sound_bias_stub.to_le_bytes()[addr as usize - 0x4000088],
+            SOUND_BIAS_START..=SOUND_BIAS_END => self.sound_bias.read(addr),
             // In this special case we return 0 since it's a write only register, and overlaps with a 16 bit
             // value which *is* readable (CONTROL regs), thus returning 0
             DMA_0_WORD_COUNT | DMA_1_WORD_COUNT | DMA_2_WORD_COUNT | DMA_3_WORD_COUNT => 0,
@@ -209,8 +295,13 @@ impl Bus {
             WAIT_CNT_START..=WAIT_CNT_END => self.system_control.read_wait_cnt(addr),
             IME_START..=IME_END => self.interrupts.read_ime(addr),
             POST_BOOT_FLAG_ADDR => self.system_control.read_post_boot(),
+            INTERNAL_MEMORY_CONTROL_START..=INTERNAL_MEMORY_CONTROL_END => {
+                self.system_control.read_memory_control(addr)
+            }
             _ => {
                 crate::cpu_log!("bus-logging"; "Unhandled IO read from {:#X}", addr);
+                #[cfg(feature = "debug-functionality")]
+                self.unhandled_io.record_read(addr);
                 self.open_bus_read(addr, cpu)
             }
         }
@@ -220,7 +311,7 @@ impl Bus {
     pub fn write_io(&mut self, addr: MemoryAddress, data: u8) {
         match addr {
             IO_START..=LCD_IO_END => self.ppu.write_io(addr, data),
-            0x4000088..=0x4000089 => self.sound_bias_stub.set_byte_le(addr as usize - 0x4000088, data),
+            SOUND_BIAS_START..=SOUND_BIAS_END => self.sound_bias.write(addr, data),
             DMA_0_ADDR_START..=DMA_3_ADDR_END => self.dma.write_channel(addr, data, &mut self.scheduler),
             timers::TIMER_IO_START..=timers::TIMER_IO_END => {
                 self.timers.write_registers(addr, data, &mut self.scheduler)
@@ -228,10 +319,10 @@ impl Bus {
             KEYSTATUS_START..=KEYSTATUS_END => {
                 crate::cpu_log!("bus-logging"; "Ignored write to keypad status register: {}", data);
             }
-            KEYINTERRUPT_START..=KEYINTERRUPT_END => self
-                .keypad
-                .interrupt_control
-                .update_byte_le((addr - KEYINTERRUPT_START) as usize, data),
+            KEYINTERRUPT_START..=KEYINTERRUPT_END => {
+                self.keypad
+                    .write_interrupt_control(addr, data, &mut self.scheduler, &mut self.interrupts)
+            }
             IE_START..=IE_END => self.interrupts.write_ie(addr, data),
             IF_START..=IF_END => self.interrupts.write_if(addr, data, &mut self.scheduler),
             WAIT_CNT_START..=WAIT_CNT_END => self.system_control.write_wait_cnt(addr, data),
@@ -240,19 +331,101 @@ impl Bus {
             HALT_CNT_ADDR => {
                 self.system_control.write_halt_control(data, &mut self.scheduler);
             }
+            INTERNAL_MEMORY_CONTROL_START..=INTERNAL_MEMORY_CONTROL_END => {
+                self.system_control.write_memory_control(addr, data)
+            }
             _ => {
                 crate::cpu_log!("bus-logging"; "Unhandled IO write from {:#X} - {}({})", addr, data, data as char);
+                #[cfg(feature = "debug-functionality")]
+                self.unhandled_io.record_write(addr);
                 // todo!("IO Write {:#X}", addr)
             }
         }
     }
 
-    /// Unused memory regions return the latest pre-fetched opcode.
+    /// Write a 16 bit value to the IO registers.
+    ///
+    /// Registers which act on a write are updated at once, so they never observe a half-written value. All other
+    /// registers are written byte by byte through [Self::write_io].
+    #[inline]
+    pub fn write_io_16(&mut self, addr: MemoryAddress, data: u16) {
+        match addr {
+            timers::TIMER_IO_START..=timers::TIMER_IO_END => {
+                self.timers.write_registers_16(addr, data, &mut self.scheduler)
+            }
+            KEYINTERRUPT_START => {
+                self.keypad
+                    .write_interrupt_control_16(data, &mut self.scheduler, &mut self.interrupts)
+            }
+            _ => {
+                let data = data.to_le_bytes();
+                self.write_io(addr, data[0]);
+                self.write_io(addr.wrapping_add(1), data[1]);
+            }
+        }
+    }
+
+    /// Advance the scheduler by the wait states of an access to `addr`.
+    ///
+    /// Work RAM wait states, Game Pak ROM wait states, and PPU memory stalls are taken into account. The base cycle of
+    /// each access is still part of the flat per-instruction cost.
+    #[inline(always)]
+    fn add_wait_states(&mut self, addr: MemoryAddress, wide: bool) {
+        let mut wait_states = self.system_control.work_ram_wait_states(addr, wide);
+
+        match Self::get_mem_range(addr) {
+            0x5..=0x7 => wait_states += self.ppu.access_stall_cycles(),
+            0x8..=0xD => {
+                // Every 128KiB block of ROM starts with a non-sequential access.
+                let sequential = addr == self.next_sequential_address && addr & 0x1_FFFF != 0;
+                let cycles = self.system_control.game_pak_access_cycles(addr, sequential, wide);
+                // The 16 bit Game Pak bus splits a 32 bit access in two, each with its own base cycle.
+                let base_cycles = if wide { 2 } else { 1 };
+
+                wait_states += cycles - base_cycles;
+            }
+            _ => {}
+        }
+
+        self.next_sequential_address = addr.wrapping_add(if wide { 4 } else { 2 });
+        self.scheduler.add_time(wait_states as i32);
+    }
+
+    /// Make the next timed access non-sequential, even if it directly follows the previous one.
+    #[inline(always)]
+    pub(crate) fn break_sequential_access(&mut self) {
+        // Only Game Pak accesses can be sequential, which this address is never part of.
+        self.next_sequential_address = MemoryAddress::MAX;
+    }
+
+    /// Unused memory regions return the value which was last driven on the data bus.
+    ///
+    /// That's the last value transferred by a DMA if one ran after the latest opcode fetch, and the prefetched opcode
+    /// otherwise. In `THUMB` mode a fetch only drives half the bus, the other half depends on where the code runs from.
     #[inline(always)]
     fn open_bus_read_32(&self, cpu: &CPU) -> u32 {
-        // Open bus read, return prefetched Opcode
-        //TODO: Handle THUMB mode special cases
-        cpu.pipeline[2]
+        if let Some(value) = self.dma_bus_latch {
+            return value;
+        }
+
+        match cpu.registers.cpsr.state() {
+            State::Arm => cpu.pipeline[2],
+            State::Thumb => {
+                // PC points to `$+4`, where `$` is the opcode currently being executed.
+                let pc = cpu.registers.pc();
+                let (current_plus_2, current_plus_4) = (cpu.pipeline[1] & 0xFFFF, cpu.pipeline[2] & 0xFFFF);
+                let word_aligned = pc & 2 == 0;
+
+                match Self::get_mem_range(pc) {
+                    // BIOS and OAM should use `$+6` for the upper half, which isn't fetched yet, so `$+4` is the
+                    // closest we have.
+                    0 | 7 if word_aligned => current_plus_4 | (current_plus_4 << 16),
+                    3 if word_aligned => current_plus_4 | (current_plus_2 << 16),
+                    0 | 3 | 7 => current_plus_2 | (current_plus_4 << 16),
+                    _ => current_plus_4 | (current_plus_4 << 16),
+                }
+            }
+        }
     }
 
     #[inline(always)]
@@ -266,3 +439,49 @@ impl Bus {
         addr >> 24
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::{State, PC_REG};
+    use crate::emulator::EmuOptions;
+
+    #[test]
+    fn test_open_bus_after_dma() {
+        // `B .`
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        emu.bus.write_32(0x0300_0000, 0xDEAD_BEEF);
+        // DMA3 a single word from IWRAM to IWRAM
+        emu.bus.write_32(0x0400_00D4, 0x0300_0000);
+        emu.bus.write_32(0x0400_00D8, 0x0300_0010);
+        emu.bus.write_32(0x0400_00DC, 0x8400_0001);
+
+        emu.bus.on_dma_start(&emu.cpu, 3);
+
+        assert_eq!(emu.bus.read_32(0x0100_0000, &emu.cpu), 0xDEAD_BEEF);
+
+        // Once the CPU fetches its next opcode the bus is back to returning the prefetch.
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.bus.read_32(0x0100_0000, &emu.cpu), 0xEAFF_FFFE);
+    }
+
+    #[test]
+    fn test_open_bus_thumb_regions() {
+        let rom = vec![0; 0x200];
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        emu.cpu.registers.cpsr.set_state(State::Thumb);
+        // `$+2` and `$+4`
+        emu.cpu.pipeline = [0, 0x1111, 0x2222];
+
+        let mut open_bus_at = |pc: u32| {
+            emu.cpu.registers.general_purpose[PC_REG] = pc;
+            emu.bus.read_32(0x0100_0000, &emu.cpu)
+        };
+
+        assert_eq!(open_bus_at(0x0800_0104), 0x2222_2222);
+        assert_eq!(open_bus_at(0x0300_0104), 0x1111_2222);
+        assert_eq!(open_bus_at(0x0300_0106), 0x2222_1111);
+        assert_eq!(open_bus_at(0x0700_0106), 0x2222_1111);
+    }
+}
@@ -0,0 +1,108 @@
+use modular_bitfield::prelude::*;
+
+use crate::emulator::MemoryAddress;
+use crate::utils::BitOps;
+
+pub const SOUND_BIAS_START: MemoryAddress = 0x0400_0088;
+pub const SOUND_BIAS_END: MemoryAddress = 0x0400_0089;
+
+/// The `SOUNDBIAS` register, which controls how the mixed (signed) sound output is converted to the unsigned PWM output.
+///
+/// More Info: [Here](https://problemkaputt.de/gbatek.htm#gbasoundcontrolregisters)
+#[bitfield(bits = 16)]
+#[repr(u16)]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct SoundBias {
+    #[skip]
+    unused_0: bool,
+    /// (Bits 1..=9) Added to the mixed sample, the BIOS sets this to `0x100` (so `0x200` for the full register).
+    pub bias_level: B9,
+    #[skip]
+    unused_1: B4,
+    /// (Bits 14..=15) Trades amplitude resolution for a higher sampling rate.
+    pub amplitude_resolution: AmplitudeResolution,
+}
+
+#[derive(Debug, BitfieldSpecifier, PartialEq, Eq, Clone, Copy)]
+#[bits = 2]
+pub enum AmplitudeResolution {
+    /// 9 bit at 32.768 kHz, the default.
+    Bits9 = 0b00,
+    /// 8 bit at 65.536 kHz
+    Bits8 = 0b01,
+    /// 7 bit at 131.072 kHz
+    Bits7 = 0b10,
+    /// 6 bit at 262.144 kHz
+    Bits6 = 0b11,
+}
+
+impl AmplitudeResolution {
+    /// The amount of bits in each output sample.
+    pub const fn bit_depth(self) -> u8 {
+        9 - self as u8
+    }
+
+    /// The sample rate of the PWM output in Hz.
+    pub const fn sample_rate(self) -> u32 {
+        32_768 << self as u32
+    }
+}
+
+impl SoundBias {
+    /// Bit 0 and bits 10..=13 are unused.
+    pub const WRITABLE_BITS: u16 = 0xC3FE;
+
+    /// The value the BIOS leaves behind on boot.
+    pub const POST_BIOS: u16 = 0x200;
+
+    pub fn write(&mut self, address: MemoryAddress, value: u8) {
+        let byte = (address - SOUND_BIAS_START) as usize;
+
+        *self = u16::from(*self)
+            .change_byte_le_masked(byte, value, Self::WRITABLE_BITS)
+            .into();
+    }
+
+    pub fn read(&self, address: MemoryAddress) -> u8 {
+        self.to_le_bytes()[(address - SOUND_BIAS_START) as usize]
+    }
+
+    /// Convert a mixed, signed, 10 bit `sample` to the final unsigned output sample.
+    ///
+    /// The bias is added, after which the result is clamped to `0..=0x3FF` and reduced to the current
+    /// [AmplitudeResolution].
+    pub fn apply(&self, sample: i16) -> u16 {
+        let biased = (sample as i32 + ((self.bias_level() as i32) << 1)).clamp(0, 0x3FF) as u16;
+
+        biased >> (10 - self.amplitude_resolution().bit_depth())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::sound::{AmplitudeResolution, SoundBias, SOUND_BIAS_END, SOUND_BIAS_START};
+
+    #[test]
+    fn test_sound_bias_output() {
+        let mut bias = SoundBias::from(SoundBias::POST_BIOS);
+
+        assert_eq!(bias.amplitude_resolution().sample_rate(), 32_768);
+        // Silence ends up in the middle of the output range.
+        assert_eq!(bias.apply(0), 0x100);
+        assert_eq!(bias.apply(-0x200), 0);
+        assert_eq!(bias.apply(0x1FF), 0x1FF);
+        // Clipped
+        assert_eq!(bias.apply(-0x300), 0);
+        assert_eq!(bias.apply(0x300), 0x1FF);
+
+        // 6 bit resolution at 262.144 kHz, unused bits should be ignored.
+        bias.write(SOUND_BIAS_END, 0xFF);
+        bias.write(SOUND_BIAS_START, 0xFF);
+
+        assert_eq!(bias.amplitude_resolution(), AmplitudeResolution::Bits6);
+        assert_eq!(bias.amplitude_resolution().sample_rate(), 262_144);
+        assert_eq!(u16::from(bias), 0xC3FE);
+        assert_eq!(bias.apply(-0x3FE), 0);
+        assert_eq!(bias.apply(-0x3FE + 0x10), 1);
+    }
+}
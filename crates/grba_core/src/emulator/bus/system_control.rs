@@ -1,17 +1,25 @@
 use crate::emulator::MemoryAddress;
 use crate::scheduler::{EmuTime, EventTag, Scheduler};
+use crate::utils::BitOps;
 use modular_bitfield::bitfield;
-use modular_bitfield::prelude::{B1, B2, B7};
+use modular_bitfield::prelude::{B1, B18, B2, B3, B4, B7};
 
 pub const WAIT_CNT_START: MemoryAddress = 0x0400_0204;
 pub const WAIT_CNT_END: MemoryAddress = 0x0400_0207;
 pub const POST_BOOT_FLAG_ADDR: MemoryAddress = 0x0400_0300;
 pub const HALT_CNT_ADDR: MemoryAddress = 0x0400_0301;
+pub const INTERNAL_MEMORY_CONTROL_START: MemoryAddress = 0x0400_0800;
+pub const INTERNAL_MEMORY_CONTROL_END: MemoryAddress = 0x0400_0803;
 
 pub struct GbaSystemControl {
     wait_control: WaitstateControl,
+    /// Cached from `wait_control` on every write, as it'll be queried on every Game Pak access.
+    game_pak_timings: GamePakTimings,
     post_boot: PostBootFlag,
     halt_control: HaltControl,
+    memory_control: InternalMemoryControl,
+    /// Debug override for the EWRAM wait states configured in `memory_control`.
+    ewram_wait_states_override: Option<u8>,
 
     pub is_halted: bool,
 }
@@ -20,8 +28,11 @@ impl GbaSystemControl {
     pub fn new() -> Self {
         GbaSystemControl {
             wait_control: WaitstateControl::new(),
+            game_pak_timings: GamePakTimings::from(WaitstateControl::new()),
             post_boot: PostBootFlag::new(),
             halt_control: HaltControl::new(),
+            memory_control: InternalMemoryControl::from(InternalMemoryControl::INITIAL_VALUE),
+            ewram_wait_states_override: None,
             is_halted: false,
         }
     }
@@ -40,11 +51,83 @@ impl GbaSystemControl {
     pub fn write_wait_cnt(&mut self, address: MemoryAddress, value: u8) {
         let addr = (address - WAIT_CNT_START) as usize;
         self.wait_control.update_byte_le(addr, value);
+        self.game_pak_timings = GamePakTimings::from(self.wait_control);
     }
 
+    /// Returns the amount of cycles an access to the Game Pak ROM at `address` takes, taking into account the
+    /// wait states configured in `WAITCNT`.
+    ///
+    /// # Arguments
+    ///
+    /// * `sequential` - Whether this access directly follows an access to the previous address.
+    /// * `wide` - Whether this is a 32 bit access, which the 16 bit Game Pak bus splits into two 16 bit accesses.
+    #[inline]
+    pub fn game_pak_access_cycles(&self, address: MemoryAddress, sequential: bool, wide: bool) -> u8 {
+        let timings = &self.game_pak_timings;
+
+        match address >> 24 {
+            0x8..=0xD => {
+                let region = ((address >> 24) - 0x8) as usize / 2;
+                let first = if sequential { timings.sequential[region] } else { timings.non_sequential[region] };
+
+                if wide {
+                    first + timings.sequential[region]
+                } else {
+                    first
+                }
+            }
+            // SRAM only has an 8 bit bus, and no sequential accesses
+            _ => timings.sram,
+        }
+    }
+
+    #[inline(always)]
+    pub fn read_memory_control(&self, address: MemoryAddress) -> u8 {
+        self.memory_control.to_le_bytes()[(address - INTERNAL_MEMORY_CONTROL_START) as usize]
+    }
+
+    #[inline]
+    pub fn write_memory_control(&mut self, address: MemoryAddress, value: u8) {
+        let byte = (address - INTERNAL_MEMORY_CONTROL_START) as usize;
+
+        self.memory_control = u32::from(self.memory_control)
+            .change_byte_le_masked(byte, value, InternalMemoryControl::WRITABLE_BITS)
+            .into();
+    }
+
+    /// The amount of wait states for a single 16 bit access to EWRAM, as configured in the internal memory control
+    /// register, or the debug override if one is set.
+    #[inline]
+    pub fn ewram_wait_states(&self) -> u8 {
+        self.ewram_wait_states_override
+            .unwrap_or_else(|| self.memory_control.ewram_wait_states())
+    }
+
+    /// Override the EWRAM wait states configured by the game, `None` restores the game's configuration.
+    pub fn set_ewram_wait_states_override(&mut self, wait_states: Option<u8>) {
+        self.ewram_wait_states_override = wait_states;
+    }
+
+    /// Returns the amount of wait states an access to work RAM at `address` takes.
+    ///
+    /// IWRAM has a 32 bit bus without wait states, whereas EWRAM is only 16 bits wide.
+    ///
+    /// # Arguments
+    ///
+    /// * `wide` - Whether this is a 32 bit access, which the 16 bit EWRAM bus splits into two 16 bit accesses.
+    #[inline]
+    pub fn work_ram_wait_states(&self, address: MemoryAddress, wide: bool) -> u8 {
+        match address >> 24 {
+            0x2 if wide => 2 * self.ewram_wait_states(),
+            0x2 => self.ewram_wait_states(),
+            _ => 0,
+        }
+    }
+
+    /// Only bit 0 of `POSTFLG` is writable.
     #[inline]
     pub fn write_post_flag(&mut self, value: u8) {
-        self.post_boot = value.into();
+        self.post_boot = (value & PostBootFlag::WRITABLE_BITS).into();
     }
 
     /// Upon writes to `Halt Control` the CPU is either stopped or halted.
@@ -94,6 +177,72 @@ pub struct WaitstateControl {
     unused: u16,
 }
 
+/// The amount of cycles (including the base cycle) a 16 bit access to a Game Pak region takes.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct GamePakTimings {
+    /// Non-sequential access cost for wait state 0, 1, and 2 respectively.
+    pub non_sequential: [u8; 3],
+    /// Sequential access cost for wait state 0, 1, and 2 respectively.
+    pub sequential: [u8; 3],
+    pub sram: u8,
+}
+
+impl From<WaitstateControl> for GamePakTimings {
+    fn from(control: WaitstateControl) -> Self {
+        const FIRST_ACCESS: [u8; 4] = [4, 3, 2, 8];
+
+        GamePakTimings {
+            non_sequential: [
+                1 + FIRST_ACCESS[control.wait_0_first_access() as usize],
+                1 + FIRST_ACCESS[control.wait_1_first_access() as usize],
+                1 + FIRST_ACCESS[control.wait_2_first_access() as usize],
+            ],
+            sequential: [
+                1 + if control.wait_0_second_access() { 1 } else { 2 },
+                1 + if control.wait_1_second_access() { 1 } else { 4 },
+                1 + if control.wait_2_second_access() { 1 } else { 8 },
+            ],
+            sram: 1 + FIRST_ACCESS[control.sram_wait_control() as usize],
+        }
+    }
+}
+
+/// Undocumented register which controls the work RAM, mirrored across the entire I/O region every 64KB.
+///
+/// More Info: [Here](https://problemkaputt.de/gbatek.htm#gbasystemcontrol)
+#[bitfield(bits = 32)]
+#[repr(u32)]
+#[allow(dead_code)]
+#[derive(Debug, Copy, Clone)]
+pub struct InternalMemoryControl {
+    /// (0=Normal, 1=Disable) Disables both IWRAM and EWRAM, currently ignored.
+    pub disable_work_ram: bool,
+    unknown_0: B3,
+    #[skip]
+    unused_0: bool,
+    /// (0=Disable, 1=Enable) Disabled EWRAM mirrors IWRAM, currently ignored.
+    pub ewram_enabled: bool,
+    #[skip]
+    unused_1: B18,
+    /// 0..=14 correspond to 15..=1 wait states, 15 locks up the GBA.
+    pub ewram_wait_control: B4,
+    unknown_1: B4,
+}
+
+impl InternalMemoryControl {
+    /// Bit 4 and bits 6..=23 are unused.
+    pub const WRITABLE_BITS: u32 = 0xFF00_002F;
+    /// Value on startup, results in 2 wait states for EWRAM accesses.
+    pub const INITIAL_VALUE: u32 = 0x0D00_0020;
+
+    /// The amount of wait states for each 16 bit EWRAM access.
+    ///
+    /// The lockup caused by a wait control of `15` isn't emulated, instead we just don't add any wait states.
+    pub fn ewram_wait_states(&self) -> u8 {
+        15 - self.ewram_wait_control()
+    }
+}
+
 /// After initial reset, the GBA BIOS initializes the register to `0x1`,
 /// and any further execution of the Reset vector (`0x00000000`) will pass control to the Debug vector (`0x0000001C`)
 /// when sensing the register to be still set to `0x1`.
@@ -108,6 +257,10 @@ pub struct PostBootFlag {
     unused: B7,
 }
 
+impl PostBootFlag {
+    pub const WRITABLE_BITS: u8 = 0x01;
+}
+
 #[bitfield(bits = 8)]
 #[repr(u8)]
 #[allow(dead_code)]
@@ -118,3 +271,124 @@ pub struct HaltControl {
     /// (0=Halt, 1=Stop)
     pub power_down_mode: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::system_control::{
+        GbaSystemControl, INTERNAL_MEMORY_CONTROL_END, POST_BOOT_FLAG_ADDR, WAIT_CNT_START,
+    };
+    use crate::emulator::bus::Bus;
+    use crate::emulator::{BiosSkipState, EmuOptions};
+    use crate::scheduler::EmuTime;
+
+    #[test]
+    fn test_default_game_pak_timings() {
+        let control = GbaSystemControl::new();
+
+        assert_eq!(control.game_pak_access_cycles(0x0800_0000, false, false), 5);
+        assert_eq!(control.game_pak_access_cycles(0x0800_0000, true, false), 3);
+        assert_eq!(control.game_pak_access_cycles(0x0A00_0000, true, false), 5);
+        assert_eq!(control.game_pak_access_cycles(0x0C00_0000, true, false), 9);
+        assert_eq!(control.game_pak_access_cycles(0x0800_0000, false, true), 8);
+        assert_eq!(control.game_pak_access_cycles(0x0E00_0000, false, false), 5);
+    }
+
+    #[test]
+    fn test_waitcnt_game_pak_timings() {
+        let mut control = GbaSystemControl::new();
+        // The value most commercial games write, SRAM 8, WS0 3/1, WS1 4/4, WS2 8/8, prefetch enabled.
+        control.write_wait_cnt(WAIT_CNT_START, 0x17);
+        control.write_wait_cnt(WAIT_CNT_START + 1, 0x43);
+
+        assert_eq!(control.game_pak_access_cycles(0x0900_0000, false, false), 4);
+        assert_eq!(control.game_pak_access_cycles(0x0900_0000, true, false), 2);
+        assert_eq!(control.game_pak_access_cycles(0x0900_0000, false, true), 6);
+        assert_eq!(control.game_pak_access_cycles(0x0B00_0000, false, false), 5);
+        assert_eq!(control.game_pak_access_cycles(0x0B00_0000, true, false), 5);
+        assert_eq!(control.game_pak_access_cycles(0x0D00_0000, false, false), 9);
+        assert_eq!(control.game_pak_access_cycles(0x0D00_0000, true, false), 9);
+        assert_eq!(control.game_pak_access_cycles(0x0E00_0000, false, false), 9);
+    }
+
+    #[test]
+    fn test_ewram_wait_states() {
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        let (bus, cpu) = (&mut emu.bus, &emu.cpu);
+
+        let access_cost = |bus: &mut Bus, addr, wide| {
+            let start = bus.scheduler.current_time;
+
+            if wide {
+                bus.read_32(addr, cpu);
+            } else {
+                bus.read_16(addr, cpu);
+            }
+
+            bus.scheduler.current_time - start
+        };
+
+        // 2 wait states by default, doubled for 32 bit accesses.
+        assert_eq!(access_cost(bus, 0x0200_0000, false), EmuTime(2));
+        assert_eq!(access_cost(bus, 0x0200_0000, true), EmuTime(4));
+        assert_eq!(access_cost(bus, 0x0300_0000, true), EmuTime(0));
+
+        // 1 wait state, as used by some homebrew.
+        bus.write(INTERNAL_MEMORY_CONTROL_END, 0x0E);
+
+        assert_eq!(bus.read(INTERNAL_MEMORY_CONTROL_END, cpu), 0x0E);
+        assert_eq!(access_cost(bus, 0x0200_0000, false), EmuTime(1));
+        assert_eq!(access_cost(bus, 0x0200_0000, true), EmuTime(2));
+
+        bus.system_control.set_ewram_wait_states_override(Some(0));
+
+        assert_eq!(access_cost(bus, 0x0200_0000, true), EmuTime(0));
+    }
+
+    #[test]
+    fn test_game_pak_wait_states() {
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        let (bus, cpu) = (&mut emu.bus, &emu.cpu);
+
+        let access_cost = |bus: &mut Bus, addr, wide| {
+            let start = bus.scheduler.current_time;
+
+            if wide {
+                bus.read_32(addr, cpu);
+            } else {
+                bus.read_16(addr, cpu);
+            }
+
+            bus.scheduler.current_time - start
+        };
+
+        // Wait state 0 defaults to `N = 5` and `S = 3`, minus the base cycle of each 16 bit access.
+        assert_eq!(access_cost(bus, 0x0800_0100, false), EmuTime(4));
+        assert_eq!(access_cost(bus, 0x0800_0102, false), EmuTime(2));
+        assert_eq!(access_cost(bus, 0x0800_0104, true), EmuTime(4));
+        // Wait state 1 defaults to `N = 5` and `S = 5`.
+        assert_eq!(access_cost(bus, 0x0A00_0000, true), EmuTime(8));
+        // Crossing into the next 128KiB block is always non-sequential.
+        assert_eq!(access_cost(bus, 0x0801_FFFE, false), EmuTime(4));
+        assert_eq!(access_cost(bus, 0x0802_0000, false), EmuTime(4));
+    }
+
+    #[test]
+    fn test_post_boot_flag() {
+        assert_eq!(GbaSystemControl::new().read_post_boot(), 0);
+
+        let options = EmuOptions {
+            skip_bios_state: BiosSkipState::Minimal,
+            ..Default::default()
+        };
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, options).unwrap();
+
+        // Skipping the BIOS should act as if it already booted once.
+        assert_eq!(emu.bus.read(POST_BOOT_FLAG_ADDR, &emu.cpu), 1);
+
+        emu.bus.write(POST_BOOT_FLAG_ADDR, 0xFE);
+        assert_eq!(emu.bus.read(POST_BOOT_FLAG_ADDR, &emu.cpu), 0);
+
+        emu.bus.write(POST_BOOT_FLAG_ADDR, 0xFF);
+        assert_eq!(emu.bus.read(POST_BOOT_FLAG_ADDR, &emu.cpu), 1);
+    }
+}
@@ -7,6 +7,8 @@ use crate::utils::BitOps;
 
 pub const TIMER_IO_START: MemoryAddress = 0x0400_0100;
 pub const TIMER_IO_END: MemoryAddress = 0x0400_010F;
+/// The delay between a timer being enabled and it starting to count.
+pub const TIMER_START_LATENCY: EmuTime = EmuTime(2);
 
 pub struct Timers {
     timers: [Timer; 4],
@@ -64,37 +66,63 @@ impl Timers {
                 timer.load_value = timer.load_value.change_byte_le(timer_addr, value);
             }
             2..=3 => {
-                let old_cnt = timer.control;
-                // Update the current value since we're going to reschedule due to potential clock tick rate changes.
-                if old_cnt.enabled() && !old_cnt.cascade_mode() {
-                    timer.value = timer.calculate_current_value(scheduler.current_time);
-                    scheduler.remove_event(Self::TIMER_EVENTS[timer_idx]);
-                }
+                let mut control = timer.control;
+                control.update_byte_le(timer_addr - 2, value);
 
-                timer.control.update_byte_le(timer_addr - 2, value);
-
-                if timer.control.enabled() {
-                    if !old_cnt.enabled() {
-                        timer.value = timer.load_value;
-                    }
-
-                    // Schedule an overflow if not cascading
-                    if !timer.control.cascade_mode() {
-                        timer.starting_timestamp = scheduler.current_time;
-                        let overflow_time = timer.calculate_overflow_time();
-                        scheduler.schedule_relative(Self::TIMER_EVENTS[timer_idx], overflow_time);
-                    }
-                } else if old_cnt.enabled() {
-                    // Freeze the current time value as the new timer is disabled.
-                    if !timer.control.cascade_mode() {
-                        timer.value = timer.calculate_current_value(scheduler.current_time);
-                    }
-                }
+                self.write_control(timer_idx, control, scheduler);
             }
             _ => unreachable!(),
         }
     }
 
+    /// Write a full 16 bit timer register at once.
+    ///
+    /// Ensures a write to the control register only (re)starts the timer once, instead of once per byte.
+    pub fn write_registers_16(&mut self, addr: AlignedAddress, value: u16, scheduler: &mut Scheduler) {
+        let timer_idx = Self::addr_to_timer_idx(addr);
+
+        match addr as usize % 4 {
+            0 => self.timers[timer_idx].load_value = value,
+            2 => self.write_control(timer_idx, value.into(), scheduler),
+            _ => unreachable!(),
+        }
+    }
+
+    fn write_control(&mut self, timer_idx: usize, control: TimerControl, scheduler: &mut Scheduler) {
+        let timer = &mut self.timers[timer_idx];
+        let old_cnt = timer.control;
+        // Update the current value since we're going to reschedule due to potential clock tick rate changes.
+        if old_cnt.enabled() && !old_cnt.cascade_mode() {
+            timer.value = timer.calculate_current_value(scheduler.current_time);
+            scheduler.remove_event(Self::TIMER_EVENTS[timer_idx]);
+        }
+
+        timer.control = control;
+
+        if timer.control.enabled() {
+            // A freshly enabled timer only starts counting after a short delay, whereas a reconfigured timer continues
+            // right away (unless it was still within that delay).
+            let starting_timestamp = if !old_cnt.enabled() {
+                timer.value = timer.load_value;
+                scheduler.current_time + TIMER_START_LATENCY
+            } else {
+                scheduler.current_time.max(timer.starting_timestamp)
+            };
+
+            // Schedule an overflow if not cascading
+            if !timer.control.cascade_mode() {
+                timer.starting_timestamp = starting_timestamp;
+                let overflow_time = timer.calculate_overflow_time();
+                scheduler.schedule_event(Self::TIMER_EVENTS[timer_idx], starting_timestamp + overflow_time);
+            }
+        } else if old_cnt.enabled() {
+            // Freeze the current time value as the new timer is disabled.
+            if !timer.control.cascade_mode() {
+                timer.value = timer.calculate_current_value(scheduler.current_time);
+            }
+        }
+    }
+
     pub fn timer_overflowed(
         &mut self,
         timer_idx: usize,
@@ -150,14 +178,18 @@ struct Timer {
 }
 
 impl Timer {
+    /// Calculate the live counter value, as the timer isn't ticked by the scheduler in between overflows.
     #[inline]
     pub fn calculate_current_value(&self, current_timestamp: EmuTime) -> u16 {
-        let ticks_passed =
-            ((current_timestamp - self.starting_timestamp).0 / self.control.timer_frequency().to_ticks()) as u16;
+        // The `starting_timestamp` lies in the future while the timer is still waiting out its start latency.
+        let cycles_passed = current_timestamp.0.saturating_sub(self.starting_timestamp.0);
+        let ticks_passed = (cycles_passed / self.control.timer_frequency().to_ticks()) as u16;
 
         self.value.wrapping_add(ticks_passed)
     }
 
+    /// The time between the timer starting to count from its current value and overflowing,
+    /// `(0x10000 - value) * prescaler` cycles.
     #[inline]
     pub fn calculate_overflow_time(&self) -> EmuTime {
         // u16::MAX + 1, as it technically only overflows at that point.
@@ -211,3 +243,64 @@ impl TimerFrequency {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::timers::{Timers, TIMER_IO_START, TIMER_START_LATENCY};
+    use crate::scheduler::{EmuTime, EventTag, Scheduler};
+
+    /// Enable timer 0 with the given `prescaler` bits, returning the scheduled overflow time.
+    fn start_timer(timers: &mut Timers, scheduler: &mut Scheduler, reload: u16, prescaler: u16) -> EmuTime {
+        timers.write_registers_16(TIMER_IO_START, reload, scheduler);
+        timers.write_registers_16(TIMER_IO_START + 2, 0x80 | prescaler, scheduler);
+
+        scheduler
+            .pending_events()
+            .find(|ev| ev.tag == EventTag::Timer0Irq)
+            .unwrap()
+            .timestamp
+    }
+
+    #[test]
+    fn test_timer_prescalers() {
+        for (prescaler, cycles) in [1, 64, 256, 1024].into_iter().enumerate() {
+            let mut timers = Timers::new();
+            let mut scheduler = Scheduler::new();
+            scheduler.add_time(100);
+
+            let overflow = start_timer(&mut timers, &mut scheduler, 0xFF00, prescaler as u16);
+
+            assert_eq!(
+                overflow,
+                EmuTime(100) + TIMER_START_LATENCY + EmuTime(0x100 * cycles),
+                "Prescaler: {}",
+                cycles
+            );
+        }
+    }
+
+    #[test]
+    fn test_timer_mid_count_read() {
+        let mut timers = Timers::new();
+        let mut scheduler = Scheduler::new();
+        // Prescaler of 64
+        start_timer(&mut timers, &mut scheduler, 0xFF00, 0b01);
+
+        let read_counter = |timers: &mut Timers, scheduler: &mut Scheduler| {
+            u16::from_le_bytes([
+                timers.read_registers(TIMER_IO_START, scheduler),
+                timers.read_registers(TIMER_IO_START + 1, scheduler),
+            ])
+        };
+
+        // Still waiting out the start latency.
+        scheduler.add_time(1);
+        assert_eq!(read_counter(&mut timers, &mut scheduler), 0xFF00);
+
+        scheduler.current_time = TIMER_START_LATENCY + EmuTime(64 * 10 + 63);
+        assert_eq!(read_counter(&mut timers, &mut scheduler), 0xFF0A);
+
+        scheduler.add_time(1);
+        assert_eq!(read_counter(&mut timers, &mut scheduler), 0xFF0B);
+    }
+}
@@ -0,0 +1,182 @@
+//! Loading of the `.elf` files homebrew toolchains produce, which unlike a raw `.gba` also carry debug symbols.
+use std::collections::BTreeMap;
+
+use object::read::elf::{ElfFile32, ProgramHeader};
+use object::{Object, ObjectSymbol, SymbolKind};
+
+use crate::emulator::cartridge::header::HEADER_SIZE;
+use crate::emulator::cartridge::{CARTRIDGE_ROM_START, MAX_ROM_SIZE};
+use crate::emulator::MemoryAddress;
+use crate::error::CoreError;
+
+/// Everything an ELF file provides beyond the ROM image itself.
+#[derive(Debug, Clone, Default)]
+pub struct ElfInfo {
+    /// Where execution starts when the BIOS is skipped, the CPU is assumed to start in ARM state.
+    pub entry_point: MemoryAddress,
+    /// Segments which are loaded straight into EWRAM/IWRAM as `(address, contents)`, such as multiboot programs.
+    pub ram_segments: Vec<(MemoryAddress, Vec<u8>)>,
+    /// All function and object symbols, by address.
+    pub symbols: BTreeMap<MemoryAddress, String>,
+}
+
+/// Split the ELF file `data` into a ROM image and the remaining [ElfInfo].
+///
+/// # Errors
+///
+/// If `data` isn't a valid 32 bit ELF file, or if it contains segments which can't be placed in ROM or work RAM.
+pub fn parse_elf(data: &[u8]) -> Result<(Vec<u8>, ElfInfo), CoreError> {
+    let elf = ElfFile32::<object::Endianness>::parse(data).map_err(|e| CoreError::InvalidElf(e.to_string()))?;
+    let endian = elf.endian();
+    let mut rom = Vec::new();
+    let mut ram_segments = Vec::new();
+
+    for segment in elf.raw_segments() {
+        if segment.p_type(endian) != object::elf::PT_LOAD {
+            continue;
+        }
+
+        // The physical address is where the segment is stored, `.data` for example lives in ROM until it's copied to
+        // IWRAM by the startup code.
+        let address = segment.p_paddr(endian);
+        let contents = segment
+            .data(endian, data)
+            .map_err(|_| CoreError::InvalidElf(format!("Segment at {:#010X} lies outside the file", address)))?;
+
+        if contents.is_empty() {
+            continue;
+        }
+
+        let offset = address.wrapping_sub(CARTRIDGE_ROM_START) as usize;
+
+        match address >> 24 {
+            0x2 | 0x3 => ram_segments.push((address, contents.to_vec())),
+            0x8 | 0x9 if offset + contents.len() <= MAX_ROM_SIZE => {
+                if rom.len() < offset + contents.len() {
+                    rom.resize(offset + contents.len(), 0);
+                }
+
+                rom[offset..offset + contents.len()].copy_from_slice(contents);
+            }
+            _ => {
+                return Err(CoreError::InvalidElf(format!(
+                    "Segment at {:#010X} can't be loaded into ROM or work RAM",
+                    address
+                )))
+            }
+        }
+    }
+
+    // Programs which run entirely from work RAM still need a (blank) cartridge.
+    if rom.len() < HEADER_SIZE {
+        rom.resize(HEADER_SIZE, 0);
+    }
+
+    let symbols = elf
+        .symbols()
+        .filter(|symbol| matches!(symbol.kind(), SymbolKind::Text | SymbolKind::Data) && symbol.address() != 0)
+        .filter_map(|symbol| {
+            let name = symbol.name().ok().filter(|name| !name.is_empty())?;
+            let address = symbol.address() as MemoryAddress;
+            // THUMB functions have their lowest bit set.
+            let address = if symbol.kind() == SymbolKind::Text { address & !1 } else { address };
+
+            Some((address, name.to_string()))
+        })
+        .collect();
+
+    let info = ElfInfo {
+        entry_point: elf.entry() as MemoryAddress & !1,
+        ram_segments,
+        symbols,
+    };
+
+    Ok((rom, info))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cartridge::elf::parse_elf;
+    use crate::emulator::cartridge::header::HEADER_SIZE;
+    use crate::emulator::cartridge::Cartridge;
+    use crate::emulator::{EmuOptions, GBAEmulator};
+    use crate::error::CoreError;
+    use crate::headless::MemoryRam;
+
+    /// Create a minimal ELF file with the given `(physical address, contents)` segments, and no sections.
+    fn create_elf(entry: u32, segments: &[(u32, &[u8])]) -> Vec<u8> {
+        const HEADER_LEN: u32 = 52;
+        const PROGRAM_HEADER_LEN: u32 = 32;
+
+        let mut elf = vec![0x7F, b'E', b'L', b'F', 1, 1, 1];
+        elf.resize(16, 0);
+
+        fn push(elf: &mut Vec<u8>, values: &[u32], size: usize) {
+            for value in values {
+                elf.extend_from_slice(&value.to_le_bytes()[..size]);
+            }
+        }
+
+        // Type (Executable) and machine (ARM)
+        push(&mut elf, &[2, 40], 2);
+        // Version, entry, program header offset, section header offset, flags
+        push(&mut elf, &[1, entry, HEADER_LEN, 0, 0x0500_0000], 4);
+        // Header size, program header size and count, section header size and count, string table index
+        push(
+            &mut elf,
+            &[HEADER_LEN, PROGRAM_HEADER_LEN, segments.len() as u32, 40, 0, 0],
+            2,
+        );
+
+        let mut data_offset = HEADER_LEN + PROGRAM_HEADER_LEN * segments.len() as u32;
+
+        for (address, contents) in segments {
+            let size = contents.len() as u32;
+            // Type (Load), offset, virtual address, physical address, file size, memory size, flags, alignment
+            push(&mut elf, &[1, data_offset, *address, *address, size, size, 0b111, 4], 4);
+            data_offset += size;
+        }
+
+        for (_, contents) in segments {
+            elf.extend_from_slice(contents);
+        }
+
+        elf
+    }
+
+    #[test]
+    fn test_parse_elf_segments() {
+        let elf = create_elf(0x0800_00C1, &[(0x0800_0100, &[1, 2, 3, 4]), (0x0300_0000, &[5, 6])]);
+
+        let (rom, info) = parse_elf(&elf).unwrap();
+
+        assert_eq!(rom.len(), 0x104);
+        assert_eq!(rom[0x100..], [1, 2, 3, 4]);
+        assert_eq!(info.ram_segments, vec![(0x0300_0000, vec![5, 6])]);
+        // The THUMB bit should be masked off.
+        assert_eq!(info.entry_point, 0x0800_00C0);
+
+        let (rom, _) = parse_elf(&create_elf(0x0200_0000, &[(0x0200_0000, &[1])])).unwrap();
+        assert_eq!(rom.len(), HEADER_SIZE);
+
+        assert!(matches!(
+            parse_elf(&create_elf(0, &[(0x0600_0000, &[1])])),
+            Err(CoreError::InvalidElf(_))
+        ));
+        assert!(matches!(parse_elf(&[0; 16]), Err(CoreError::InvalidElf(_))));
+    }
+
+    #[test]
+    fn test_elf_emulator_setup() {
+        let elf = create_elf(
+            0x0300_0000,
+            &[(0x0800_0000, &[0; HEADER_SIZE]), (0x0300_0000, &[0xAA, 0xBB])],
+        );
+        let cartridge = Cartridge::from_elf(&elf, Box::new(MemoryRam::default())).unwrap();
+
+        let emu = GBAEmulator::new(cartridge, EmuOptions::default()).unwrap();
+
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0300_0000);
+        assert_eq!(emu.bus.ram.read_chip::<u8>(0x0300_0001), 0xBB);
+    }
+}
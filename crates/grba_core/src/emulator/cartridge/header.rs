@@ -1,5 +1,8 @@
 use parsing::*;
 
+/// The header occupies the first `0xC0` bytes of every ROM.
+pub const HEADER_SIZE: usize = 0xC0;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Region {
     Japan,
@@ -11,7 +14,7 @@ pub enum Region {
     Italian,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, enum_iterator::Sequence, serde::Serialize, serde::Deserialize)]
 pub enum CartBackupId {
     /// Either 512 or 8 KB of EEPROM
     EEProm,
@@ -40,7 +43,12 @@ pub struct CartridgeHeader {
     pub software_version: u8,
     /// Header checksum (We'll probably just ignore this one)
     pub complement_checksum: u8,
-    /// The backup id of this particular cartridge
+    /// The backup id of this particular cartridge, as detected from the marker string in the ROM.
+    ///
+    /// Defaults to [CartBackupId::Flash64] if no marker could be found, see [Cartridge::set_backup_override] for games
+    /// where this is wrong.
+    ///
+    /// [Cartridge::set_backup_override]: crate::emulator::cartridge::Cartridge::set_backup_override
     pub backup_id: CartBackupId,
 }
 
@@ -1,16 +1,25 @@
 use crate::emulator::bus::helpers::ReadType;
-use crate::emulator::cartridge::header::CartridgeHeader;
+use crate::emulator::cartridge::elf::ElfInfo;
+use crate::emulator::cartridge::header::{CartBackupId, CartridgeHeader, HEADER_SIZE};
 use crate::emulator::{AlignedAddress, MemoryAddress};
-use std::ops::{Deref, DerefMut};
+use crate::error::CoreError;
+use std::ops::{Deref, DerefMut, Range};
 
+pub mod elf;
 pub mod header;
 
 pub const MAX_ROM_SIZE: usize = 1024 * 1024 * 32;
-/// Maximum of `64KB` of additional SRAM
-pub const CARTRIDGE_RAM_SIZE: usize = 1024 * 64;
+/// Maximum of `128KB` of backup storage, for `FLASH1M` carts.
+pub const CARTRIDGE_RAM_SIZE: usize = 1024 * 128;
+/// The size of the window through which backup storage is accessed, anything beyond is mirrored.
+pub const CARTRIDGE_SRAM_WINDOW_SIZE: usize = 1024 * 64;
 
 pub const CARTRIDGE_ROM_START: MemoryAddress = 0x0800_0000;
 pub const CARTRIDGE_SRAM_START: MemoryAddress = 0x0E00_0000;
+/// Multiboot images are loaded at, and start executing from, the start of EWRAM.
+pub const MULTIBOOT_START: MemoryAddress = 0x0200_0000;
+/// Multiboot images have to fit in the `256KB` of EWRAM.
+pub const MAX_MULTIBOOT_SIZE: usize = 1024 * 256;
 
 pub struct Cartridge {
     header: CartridgeHeader,
@@ -21,12 +30,72 @@ pub struct Cartridge {
     /// For now we take a [Box] here to avoid needing to specify lifetimes everywhere (as we want to be able to take
     /// a MMAP, or any byte array really). If performance turns out to be significantly worse we can always change it.
     ///
-    /// TODO: Implement different sizes based on the backup ID, currently we just assume Flash.
-    saved_ram: Box<dyn std::ops::DerefMut<Target = [u8]> + Send>,
+    /// Should be at least [CARTRIDGE_RAM_SIZE] bytes, how much of it is used depends on the [CartBackupId].
+    saved_ram: Box<dyn SaveStorage>,
+    /// The bytes of [Self::saved_ram] written since the last [Cartridge::flush_save], if any.
+    dirty_ram: Option<Range<usize>>,
+    /// The backup type in use, either the one detected in the [CartridgeHeader] or the user's override.
+    backup_id: CartBackupId,
+    /// The currently selected 64KB bank, only ever non-zero for [CartBackupId::Flash128].
+    flash_bank: usize,
+    flash_command: FlashCommandState,
+    /// Only present if the cartridge was loaded from an ELF file or multiboot image, see [Cartridge::from_elf] and
+    /// [Cartridge::from_multiboot].
+    elf: Option<ElfInfo>,
+}
+
+/// The backing storage of a [Cartridge]'s save, which can be anything that derefs to a byte slice.
+pub trait SaveStorage: DerefMut<Target = [u8]> + Send {
+    /// Persist the bytes in `range` to the underlying storage.
+    ///
+    /// Storage which only lives in memory has nothing to persist, and can rely on the default no-op.
+    fn flush_range(&mut self, _range: Range<usize>) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SaveStorage for Vec<u8> {}
+
+impl SaveStorage for memmap2::MmapMut {
+    fn flush_range(&mut self, range: Range<usize>) -> std::io::Result<()> {
+        memmap2::MmapMut::flush_range(self, range.start, range.len())
+    }
+}
+
+/// Progress through the Flash command sequence (`0x5555=AA`, `0x2AAA=55`, `0x5555=cmd`).
+///
+/// Commands for the chip identification mode are consumed like any other, but don't change what is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlashCommandState {
+    Ready,
+    FirstCycle,
+    SecondCycle,
+    /// The next write to `0x0000` selects the bank.
+    BankSelect,
+    /// The next write is stored.
+    ByteProgram,
+    /// An erase command (`0x80`) was given, which has to be followed by another command sequence to pick what to erase.
+    EraseReady,
+    EraseFirstCycle,
+    EraseSecondCycle,
 }
 
 impl Cartridge {
-    pub fn new(mut rom: Vec<u8>, ram: Box<dyn std::ops::DerefMut<Target = [u8]> + Send>) -> Self {
+    /// Create a new cartridge from the full `rom` contents, with `ram` as the backing save storage.
+    ///
+    /// # Errors
+    ///
+    /// If the `rom` can't contain a header, is larger than [MAX_ROM_SIZE], or if `ram` is smaller than
+    /// [CARTRIDGE_RAM_SIZE].
+    pub fn new(mut rom: Vec<u8>, ram: Box<dyn SaveStorage>) -> Result<Self, CoreError> {
+        if !(HEADER_SIZE..=MAX_ROM_SIZE).contains(&rom.len()) {
+            return Err(CoreError::InvalidRomSize(rom.len()));
+        }
+
+        if ram.len() < CARTRIDGE_RAM_SIZE {
+            return Err(CoreError::InvalidSaveSize(ram.len()));
+        }
+
         let header = CartridgeHeader::new(&rom);
 
         // Since games like to do out of bound reads we need to pre-emptively fill the data
@@ -34,17 +103,93 @@ impl Cartridge {
             fill_rom_out_of_bounds(&mut rom);
         }
 
-        Self {
+        Ok(Self {
+            backup_id: header.backup_id,
             header,
             rom,
             saved_ram: ram,
+            dirty_ram: None,
+            flash_bank: 0,
+            flash_command: FlashCommandState::Ready,
+            elf: None,
+        })
+    }
+
+    /// Create a new cartridge from the contents of an ELF file, with `ram` as the backing save storage.
+    ///
+    /// Segments in ROM make up the cartridge, while segments in work RAM are loaded when the [Bus] is created.
+    ///
+    /// # Errors
+    ///
+    /// If the ELF file is invalid, see [elf::parse_elf], or if the resulting ROM is invalid, see [Cartridge::new].
+    ///
+    /// [Bus]: crate::emulator::bus::Bus
+    pub fn from_elf(data: &[u8], ram: Box<dyn SaveStorage>) -> Result<Self, CoreError> {
+        let (rom, info) = elf::parse_elf(data)?;
+        let mut cartridge = Self::new(rom, ram)?;
+        cartridge.elf = Some(info);
+
+        Ok(cartridge)
+    }
+
+    /// Create a new cartridge for a multiboot image, which runs entirely from EWRAM, with `ram` as the backing save
+    /// storage.
+    ///
+    /// The image is loaded at [MULTIBOOT_START] when the [Bus] is created, and execution starts there as well.
+    /// Multiboot images share their header with regular cartridges, so the ROM consists of just a copy of that header.
+    ///
+    /// # Errors
+    ///
+    /// If the image is larger than [MAX_MULTIBOOT_SIZE], or if `ram` is smaller than [CARTRIDGE_RAM_SIZE].
+    ///
+    /// [Bus]: crate::emulator::bus::Bus
+    pub fn from_multiboot(data: &[u8], ram: Box<dyn SaveStorage>) -> Result<Self, CoreError> {
+        if data.len() > MAX_MULTIBOOT_SIZE {
+            return Err(CoreError::InvalidMultibootSize(data.len()));
         }
+
+        let mut rom = data[..data.len().min(HEADER_SIZE)].to_vec();
+        rom.resize(HEADER_SIZE, 0);
+
+        let mut cartridge = Self::new(rom, ram)?;
+        cartridge.elf = Some(ElfInfo {
+            entry_point: MULTIBOOT_START,
+            ram_segments: vec![(MULTIBOOT_START, data.to_vec())],
+            symbols: Default::default(),
+        });
+
+        Ok(cartridge)
     }
 
     pub fn header(&self) -> &CartridgeHeader {
         &self.header
     }
 
+    /// The backup type which is currently in use.
+    pub fn backup_id(&self) -> CartBackupId {
+        self.backup_id
+    }
+
+    /// Force the given backup type instead of the one detected in the [CartridgeHeader], `None` restores the detected
+    /// type.
+    ///
+    /// Should only be changed before the game starts running, as the backing storage isn't converted.
+    pub fn set_backup_override(&mut self, backup_id: Option<CartBackupId>) {
+        self.backup_id = backup_id.unwrap_or(self.header.backup_id);
+        self.flash_bank = 0;
+        self.flash_command = FlashCommandState::Ready;
+    }
+
+    /// The additional information of an ELF file, if this cartridge was loaded from one.
+    pub fn elf(&self) -> Option<&ElfInfo> {
+        self.elf.as_ref()
+    }
+
+    /// The address execution starts at when the BIOS is skipped.
+    pub fn entry_point(&self) -> MemoryAddress {
+        self.elf.as_ref().map_or(CARTRIDGE_ROM_START, |elf| elf.entry_point)
+    }
+
     pub fn rom(&self) -> &[u8] {
         &self.rom
     }
@@ -57,12 +202,104 @@ impl Cartridge {
     ///
     /// Note that the ROM only has an 8-bit bus, so this should only ever return a [u8]
     pub fn read_sram(&self, addr: MemoryAddress) -> u8 {
-        self.saved_ram[Self::cartridge_sram_addr_to_index(addr)]
+        self.saved_ram[self.cartridge_sram_addr_to_index(addr)]
     }
 
     /// Write the given `value` to the given `addr` in SRAM.
     pub fn write_sram(&mut self, addr: MemoryAddress, value: u8) {
-        self.saved_ram[Self::cartridge_sram_addr_to_index(addr)] = value;
+        let is_flash = matches!(self.backup_id, CartBackupId::Flash64 | CartBackupId::Flash128);
+
+        if is_flash && !self.handle_flash_command(addr, value) {
+            return;
+        }
+
+        let index = self.cartridge_sram_addr_to_index(addr);
+        self.saved_ram[index] = value;
+        self.mark_save_dirty(index..index + 1);
+    }
+
+    fn mark_save_dirty(&mut self, range: Range<usize>) {
+        self.dirty_ram = Some(match self.dirty_ram.take() {
+            Some(dirty) => dirty.start.min(range.start)..dirty.end.max(range.end),
+            None => range,
+        });
+    }
+
+    /// Whether the save has been written to since the last [Cartridge::flush_save].
+    pub fn is_save_dirty(&self) -> bool {
+        self.dirty_ram.is_some()
+    }
+
+    /// Persist all save writes since the last flush to the backing storage, see [SaveStorage::flush_range].
+    ///
+    /// Does nothing if the save hasn't been written to in the meantime.
+    pub fn flush_save(&mut self) -> std::io::Result<()> {
+        if let Some(dirty) = self.dirty_ram.take() {
+            if let Err(e) = self.saved_ram.flush_range(dirty.clone()) {
+                // Try again on the next flush.
+                self.dirty_ram = Some(dirty);
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Track the Flash command sequence, handling erases, and bank switches for 128KB Flash.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the write programs a byte, and should therefore be stored. All command writes are consumed.
+    fn handle_flash_command(&mut self, addr: MemoryAddress, value: u8) -> bool {
+        /// The size of the smallest erasable unit.
+        const SECTOR_SIZE: usize = 4 * 1024;
+
+        let offset = addr as usize % CARTRIDGE_SRAM_WINDOW_SIZE;
+
+        match self.flash_command {
+            FlashCommandState::ByteProgram => {
+                self.flash_command = FlashCommandState::Ready;
+                return true;
+            }
+            FlashCommandState::BankSelect if offset == 0x0000 && self.backup_id == CartBackupId::Flash128 => {
+                self.flash_bank = (value & 1) as usize;
+                self.flash_command = FlashCommandState::Ready;
+                return false;
+            }
+            _ => {}
+        }
+
+        self.flash_command = match (self.flash_command, offset, value) {
+            (FlashCommandState::Ready, 0x5555, 0xAA) => FlashCommandState::FirstCycle,
+            (FlashCommandState::FirstCycle, 0x2AAA, 0x55) => FlashCommandState::SecondCycle,
+            (FlashCommandState::SecondCycle, 0x5555, 0xA0) => FlashCommandState::ByteProgram,
+            (FlashCommandState::SecondCycle, 0x5555, 0xB0) => FlashCommandState::BankSelect,
+            (FlashCommandState::SecondCycle, 0x5555, 0x80) => FlashCommandState::EraseReady,
+            (FlashCommandState::EraseReady, 0x5555, 0xAA) => FlashCommandState::EraseFirstCycle,
+            (FlashCommandState::EraseFirstCycle, 0x2AAA, 0x55) => FlashCommandState::EraseSecondCycle,
+            (FlashCommandState::EraseSecondCycle, 0x5555, 0x10) => {
+                let banks = if self.backup_id == CartBackupId::Flash128 { 2 } else { 1 };
+                self.erase_save(0..banks * CARTRIDGE_SRAM_WINDOW_SIZE);
+                FlashCommandState::Ready
+            }
+            (FlashCommandState::EraseSecondCycle, _, 0x30) => {
+                let start = self.cartridge_sram_addr_to_index(addr) & !(SECTOR_SIZE - 1);
+                self.erase_save(start..start + SECTOR_SIZE);
+                FlashCommandState::Ready
+            }
+            _ => FlashCommandState::Ready,
+        };
+
+        false
+    }
+
+    /// Erased Flash reads as `0xFF`.
+    fn erase_save(&mut self, range: Range<usize>) {
+        for index in range.clone() {
+            self.saved_ram[index] = 0xFF;
+        }
+
+        self.mark_save_dirty(range);
     }
 
     #[inline]
@@ -81,8 +318,16 @@ impl Cartridge {
     }
 
     #[inline(always)]
-    const fn cartridge_sram_addr_to_index(addr: MemoryAddress) -> usize {
-        addr as usize % CARTRIDGE_RAM_SIZE
+    fn cartridge_sram_addr_to_index(&self, addr: MemoryAddress) -> usize {
+        match self.backup_id {
+            // 32KB, mirrored within the 64KB window.
+            CartBackupId::Sram => addr as usize % (32 * 1024),
+            CartBackupId::Flash128 => {
+                self.flash_bank * CARTRIDGE_SRAM_WINDOW_SIZE + addr as usize % CARTRIDGE_SRAM_WINDOW_SIZE
+            }
+            // EEPROM isn't accessed through this window, so it's treated the same as 64KB Flash.
+            CartBackupId::Flash64 | CartBackupId::EEProm => addr as usize % CARTRIDGE_SRAM_WINDOW_SIZE,
+        }
     }
 
     #[inline(always)]
@@ -95,10 +340,17 @@ impl Cartridge {
 /// Purely used for debugging.
 impl Default for Cartridge {
     fn default() -> Self {
+        let header = CartridgeHeader::new(&[0; 2000]);
+
         Cartridge {
-            header: CartridgeHeader::new(&[0; 2000]),
+            backup_id: header.backup_id,
+            header,
             rom: Vec::new(),
             saved_ram: Box::new(FakeRam),
+            dirty_ram: None,
+            flash_bank: 0,
+            flash_command: FlashCommandState::Ready,
+            elf: None,
         }
     }
 }
@@ -115,18 +367,223 @@ pub fn fill_rom_out_of_bounds(rom: &mut Vec<u8>) {
     rom.resize(MAX_ROM_SIZE, 0xFF);
 }
 
+/// Empty save storage for the [Cartridge::default] placeholder, which should never be accessed.
 struct FakeRam;
 
 impl Deref for FakeRam {
     type Target = [u8];
 
     fn deref(&self) -> &Self::Target {
-        todo!()
+        debug_assert!(false, "Accessed the save storage of a placeholder cartridge");
+        &[]
     }
 }
 
 impl DerefMut for FakeRam {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        todo!()
+        debug_assert!(false, "Accessed the save storage of a placeholder cartridge");
+        &mut []
+    }
+}
+
+impl SaveStorage for FakeRam {}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cartridge::header::CartBackupId;
+    use crate::emulator::cartridge::{
+        Cartridge, CARTRIDGE_RAM_SIZE, CARTRIDGE_SRAM_START, MAX_MULTIBOOT_SIZE, MULTIBOOT_START,
+    };
+    use crate::emulator::{EmuOptions, GBAEmulator};
+    use crate::error::CoreError;
+    use std::io::{Read, Seek, SeekFrom};
+
+    fn cartridge_with_backup(backup_id: &str) -> Cartridge {
+        let mut rom = vec![0; 0x200];
+        rom[0x100..0x100 + backup_id.len()].copy_from_slice(backup_id.as_bytes());
+
+        Cartridge::new(rom, Box::new(vec![0; CARTRIDGE_RAM_SIZE])).unwrap()
+    }
+
+    #[test]
+    fn test_multiboot_image() {
+        // `B .`, followed by the rest of the header.
+        let mut image = 0xEAFF_FFFEu32.to_le_bytes().to_vec();
+        image.resize(0x1000, 0xAB);
+        let cartridge = Cartridge::from_multiboot(&image, Box::new(vec![0; CARTRIDGE_RAM_SIZE])).unwrap();
+
+        let mut emu = GBAEmulator::new(cartridge, EmuOptions::default()).unwrap();
+
+        assert_eq!(emu.cpu.registers.next_pc(), MULTIBOOT_START);
+        assert_eq!(emu.bus.ram.read_board::<u8>(MULTIBOOT_START + 0xFFF), 0xAB);
+
+        emu.step_instruction();
+        assert_eq!(emu.cpu.registers.next_pc(), MULTIBOOT_START);
+
+        image.resize(MAX_MULTIBOOT_SIZE + 1, 0);
+        let result = Cartridge::from_multiboot(&image, Box::new(vec![0; CARTRIDGE_RAM_SIZE]));
+        assert_eq!(
+            result.err(),
+            Some(CoreError::InvalidMultibootSize(MAX_MULTIBOOT_SIZE + 1))
+        );
+    }
+
+    #[test]
+    fn test_sram_32kb_mirror() {
+        let mut cart = cartridge_with_backup("SRAM_V113");
+        assert_eq!(cart.header().backup_id, CartBackupId::Sram);
+
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x7FFF, 0x12);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x8000, 0x34);
+
+        // The second write wraps around to the start of the 32KB SRAM.
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START), 0x34);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x7FFF), 0x12);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0xFFFF), 0x12);
+        // Mirrored across the entire SRAM region as well.
+        assert_eq!(cart.read_sram(0x0F00_8000), 0x34);
+    }
+
+    #[test]
+    fn test_flash_128kb_bank_switch() {
+        let mut cart = cartridge_with_backup("FLASH1M_V103");
+        assert_eq!(cart.header().backup_id, CartBackupId::Flash128);
+
+        program_flash(&mut cart, 0x100, 0x12);
+        switch_flash_bank(&mut cart, 1);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0);
+
+        program_flash(&mut cart, 0x100, 0x34);
+        assert_eq!(cart.ram()[0x1_0100], 0x34);
+
+        switch_flash_bank(&mut cart, 0);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0x12);
+        // None of the command writes should have ended up in the backing storage.
+        for index in [0x0000, 0x5555, 0x2AAA, 0x1_0000, 0x1_5555, 0x1_2AAA] {
+            assert_eq!(cart.ram()[index], 0, "Index: {:#X}", index);
+        }
+
+        // Writes outside of a byte program command are ignored.
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x100, 0x56);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0x12);
+    }
+
+    #[test]
+    fn test_flash_erase() {
+        let mut cart = cartridge_with_backup("FLASH1M_V103");
+        program_flash(&mut cart, 0x100, 0x12);
+        program_flash(&mut cart, 0x1000, 0x34);
+
+        // Erase the first 4KB sector
+        flash_command(&mut cart, 0x80);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x5555, 0xAA);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x2AAA, 0x55);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x0FFF, 0x30);
+
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0xFF);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x1000), 0x34);
+
+        // Erase the entire chip
+        flash_command(&mut cart, 0x80);
+        flash_command(&mut cart, 0x10);
+
+        assert!(cart.ram().iter().all(|&byte| byte == 0xFF));
+    }
+
+    #[test]
+    fn test_flash_64kb_commands() {
+        let mut cart = cartridge_with_backup("FLASH512_V131");
+        assert_eq!(cart.header().backup_id, CartBackupId::Flash64);
+
+        // Enter and exit the chip identification mode
+        flash_command(&mut cart, 0x90);
+        flash_command(&mut cart, 0xF0);
+        program_flash(&mut cart, 0x100, 0x12);
+
+        // None of the command writes should end up in the save.
+        assert_eq!(cart.ram()[0x5555], 0);
+        assert_eq!(cart.ram()[0x2AAA], 0);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0x12);
+
+        // There is only a single bank, so the bank write is consumed without effect.
+        switch_flash_bank(&mut cart, 1);
+        assert_eq!(cart.ram()[0], 0);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START + 0x100), 0x12);
+
+        // Erasing the chip only affects the 64KB which exist.
+        flash_command(&mut cart, 0x80);
+        flash_command(&mut cart, 0x10);
+
+        assert!(cart.ram()[..0x1_0000].iter().all(|&byte| byte == 0xFF));
+        assert!(cart.ram()[0x1_0000..].iter().all(|&byte| byte == 0));
+    }
+
+    /// Write the `0x5555=AA`, `0x2AAA=55`, `0x5555=command` sequence.
+    fn flash_command(cart: &mut Cartridge, command: u8) {
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x5555, 0xAA);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x2AAA, 0x55);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x5555, command);
+    }
+
+    fn program_flash(cart: &mut Cartridge, offset: u32, value: u8) {
+        flash_command(cart, 0xA0);
+        cart.write_sram(CARTRIDGE_SRAM_START + offset, value);
+    }
+
+    fn switch_flash_bank(cart: &mut Cartridge, bank: u8) {
+        flash_command(cart, 0xB0);
+        cart.write_sram(CARTRIDGE_SRAM_START, bank);
+    }
+
+    #[test]
+    fn test_backup_override() {
+        let mut cart = cartridge_with_backup("FLASH1M_V103");
+
+        cart.set_backup_override(Some(CartBackupId::Sram));
+        assert_eq!(cart.backup_id(), CartBackupId::Sram);
+        // The header should still report what was detected.
+        assert_eq!(cart.header().backup_id, CartBackupId::Flash128);
+
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x8000, 0x12);
+        assert_eq!(cart.read_sram(CARTRIDGE_SRAM_START), 0x12);
+
+        cart.set_backup_override(None);
+        assert_eq!(cart.backup_id(), CartBackupId::Flash128);
+    }
+
+    #[test]
+    fn test_flush_save_to_file() {
+        let path = std::env::temp_dir().join(format!("grba_flush_save_{}.bin", std::process::id()));
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        file.set_len(CARTRIDGE_RAM_SIZE as u64).unwrap();
+
+        let map = unsafe { memmap2::MmapMut::map_mut(&file).unwrap() };
+        let mut rom = vec![0; 0x200];
+        rom[0x100..0x109].copy_from_slice(b"SRAM_V113");
+        let mut cart = Cartridge::new(rom, Box::new(map)).unwrap();
+
+        assert!(!cart.is_save_dirty());
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x10, 0x12);
+        cart.write_sram(CARTRIDGE_SRAM_START + 0x4, 0x34);
+        assert!(cart.is_save_dirty());
+
+        cart.flush_save().unwrap();
+        assert!(!cart.is_save_dirty());
+
+        let mut contents = [0; 0x11];
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_exact(&mut contents).unwrap();
+
+        assert_eq!(contents[0x4], 0x34);
+        assert_eq!(contents[0x10], 0x12);
+
+        drop(cart);
+        let _ = std::fs::remove_file(path);
     }
 }
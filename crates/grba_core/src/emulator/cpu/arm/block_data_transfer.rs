@@ -16,10 +16,7 @@ impl ArmV4 {
         instruction: ArmInstruction,
         bus: &mut Bus,
     ) {
-        // For the duration of this instruction PC will be 12 ahead instead of just 8.
-        cpu.registers.general_purpose[PC_REG] += 4;
         Self::block_data_transfer::<PSR_OR_USER, false, PRE_INDEXED, IS_UP, HAS_WRITEBACK>(cpu, instruction, bus);
-        cpu.registers.general_purpose[PC_REG] -= 4;
     }
 
     // Normal bit locations:
@@ -108,9 +105,10 @@ impl ArmV4 {
             if is_load {
                 // In ARMv4 writeback gets disabled, note that this is different from ARMv5
                 has_writeback = false;
-            } else {
+            } else if has_writeback {
                 // In ARMv4 we store the old base if the register is *first* in the list, otherwise store new base
                 // In ARMv5 we always store the new base
+                // Without writeback the base never changes, so the old base is stored regardless.
                 let registers = register_list << (15 - reg_base);
 
                 // Is not first in the list
@@ -156,9 +154,10 @@ impl ArmV4 {
             if is_load {
                 // In ARMv4 writeback gets disabled, note that this is different from ARMv5
                 has_writeback = false;
-            } else {
+            } else if has_writeback {
                 // In ARMv4 we store the old base if the register is *first* in the list, otherwise store new base
                 // In ARMv5 we always store the new base
+                // Without writeback the base never changes, so the old base is stored regardless.
                 let registers = register_list << (15 - reg_base);
                 // Is not first in the list
                 if registers.count_ones() != 1 {
@@ -168,26 +167,25 @@ impl ArmV4 {
         }
 
         // ** S bit handling **
-        let old_mode = cpu.registers.cpsr.mode();
-        let mut swapped_banks = false;
-
-        // This function has the s-bit set, so we need to handle it.
         if register_list.check_bit(15) && is_load {
             // LDM with R15 in transfer list and S bit set (Mode changes)
             cpu.registers.write_cpsr(cpu.registers.spsr, bus);
+
+            Self::iterate_registers(cpu, bus, is_load, register_list, address);
         } else {
-            // For STM instructions data will be taken from the User bank, so we need to switch to that.
-            swapped_banks = cpu.registers.swap_register_banks(old_mode, Mode::User, false);
-        }
+            // Without R15 in the list (or for any STM) the transfer uses the User bank, regardless of the current mode.
+            let old_mode = cpu.registers.cpsr.mode();
+            let swapped_banks = cpu.registers.swap_register_banks(old_mode, Mode::User, false);
 
-        // Handle all registers
-        Self::iterate_registers(cpu, bus, is_load, register_list, address);
+            Self::iterate_registers(cpu, bus, is_load, register_list, address);
 
-        if swapped_banks {
-            // In theory has_writeback should be false, but can't hurt to pre-emptively swap back.
-            cpu.registers.swap_register_banks(Mode::User, old_mode, false);
+            if swapped_banks {
+                cpu.registers.swap_register_banks(Mode::User, old_mode, false);
+            }
         }
 
+        // Writeback with the S bit set is unpredictable, so games shouldn't rely on it.
+        // We simply write to the base register of whichever mode we end up in.
         if has_writeback {
             cpu.write_reg(reg_base, writeback_address, bus);
         }
@@ -209,14 +207,17 @@ impl ArmV4 {
                 (final_address, start_address)
             } else {
                 // Handle edge case where register list is empty. If we're pre-indexed we do a branchless initial add.
-                (start_address + 0x40, start_address + (4 * is_preindexed as u32))
+                (
+                    start_address.wrapping_add(0x40),
+                    start_address.wrapping_add(4 * is_preindexed as u32),
+                )
             }
         } else {
             let final_address = if register_count != 0 {
                 start_address.wrapping_sub(4 * register_count)
             } else {
                 // Handle edge case where register list is empty (Note: Probably not worth keeping for future optimisation)
-                start_address - 0x40
+                start_address.wrapping_sub(0x40)
             };
 
             let start_address = if is_preindexed {
@@ -245,7 +246,8 @@ impl ArmV4 {
         } else {
             for i in 0..16 {
                 if register_list.check_bit(i) {
-                    let value = cpu.read_reg(i as usize);
+                    // Stored values are read after the first address cycle, so PC will be 12 ahead instead of 8.
+                    let value = cpu.read_reg_late(i as usize);
                     bus.write_32(address, value);
 
                     address = address.wrapping_add(4)
@@ -254,3 +256,172 @@ impl ArmV4 {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::Mode;
+    use crate::emulator::GBAEmulator;
+
+    const BASE: u32 = 0x0300_0100;
+
+    #[derive(Debug, Clone, Copy)]
+    enum AddressingMode {
+        IncrementAfter,
+        IncrementBefore,
+        DecrementAfter,
+        DecrementBefore,
+    }
+
+    impl AddressingMode {
+        /// The `(P, U)` bits of the instruction encoding.
+        fn encoding(self) -> u32 {
+            match self {
+                AddressingMode::IncrementAfter => 0b01,
+                AddressingMode::IncrementBefore => 0b11,
+                AddressingMode::DecrementAfter => 0b00,
+                AddressingMode::DecrementBefore => 0b10,
+            }
+        }
+
+        /// The address R15 should be transferred to/from for an empty register list.
+        fn expected_transfer_address(self) -> u32 {
+            match self {
+                AddressingMode::IncrementAfter => BASE,
+                AddressingMode::IncrementBefore => BASE + 4,
+                AddressingMode::DecrementAfter => BASE - 0x3C,
+                AddressingMode::DecrementBefore => BASE - 0x40,
+            }
+        }
+
+        /// The base after writeback, an empty list always adjusts by 16 words.
+        fn expected_writeback(self) -> u32 {
+            match self {
+                AddressingMode::IncrementAfter | AddressingMode::IncrementBefore => BASE + 0x40,
+                AddressingMode::DecrementAfter | AddressingMode::DecrementBefore => BASE - 0x40,
+            }
+        }
+    }
+
+    const MODES: [AddressingMode; 4] = [
+        AddressingMode::IncrementAfter,
+        AddressingMode::IncrementBefore,
+        AddressingMode::DecrementAfter,
+        AddressingMode::DecrementBefore,
+    ];
+
+    /// Create an `LDM`/`STM` instruction with `r0` as the base and an empty register list.
+    fn empty_list_instruction(mode: AddressingMode, writeback: bool, load: bool) -> u32 {
+        0xE800_0000 | (mode.encoding() << 23) | ((writeback as u32) << 21) | ((load as u32) << 20)
+    }
+
+    fn emu_with_instruction(instruction: u32) -> GBAEmulator {
+        let mut emu = crate::emulator::cpu::test_utils::emu_with_arm(&[instruction]);
+        emu.cpu.registers.general_purpose[0] = BASE;
+        emu
+    }
+
+    #[test]
+    fn test_stm_empty_list() {
+        for mode in MODES {
+            for writeback in [false, true] {
+                let mut emu = emu_with_instruction(empty_list_instruction(mode, writeback, false));
+
+                emu.cpu.step_instruction(&mut emu.bus);
+
+                let expected_base = if writeback { mode.expected_writeback() } else { BASE };
+                assert_eq!(
+                    emu.cpu.registers.general_purpose[0], expected_base,
+                    "Base mismatch for {mode:?}, writeback: {writeback}"
+                );
+
+                // Only R15 (PC + 12) should have been stored, and nothing else in the 16 word window around the base.
+                for address in (BASE - 0x40..=BASE + 0x40).step_by(4) {
+                    let expected = if address == mode.expected_transfer_address() { 0x0800_000C } else { 0 };
+
+                    assert_eq!(
+                        emu.bus.read_32(address, &emu.cpu),
+                        expected,
+                        "Memory mismatch at {address:#010X} for {mode:?}, writeback: {writeback}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_ldm_empty_list() {
+        for mode in MODES {
+            for writeback in [false, true] {
+                let mut emu = emu_with_instruction(empty_list_instruction(mode, writeback, true));
+                emu.bus.write_32(mode.expected_transfer_address(), 0x0800_0100);
+
+                emu.cpu.step_instruction(&mut emu.bus);
+
+                let expected_base = if writeback { mode.expected_writeback() } else { BASE };
+                assert_eq!(
+                    emu.cpu.registers.general_purpose[0], expected_base,
+                    "Base mismatch for {mode:?}, writeback: {writeback}"
+                );
+                assert_eq!(
+                    emu.cpu.registers.next_pc(),
+                    0x0800_0100,
+                    "PC mismatch for {mode:?}, writeback: {writeback}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_stm_base_in_list() {
+        let stm = |instruction: u32| {
+            let mut emu = emu_with_instruction(instruction);
+            emu.cpu.registers.general_purpose[0..3].copy_from_slice(&[0xA0, BASE, 0xA2]);
+
+            emu.cpu.step_instruction(&mut emu.bus);
+
+            let stored = [0, 4, 8].map(|offset| emu.bus.read_32(BASE + offset, &emu.cpu));
+            (stored, emu.cpu.registers.general_purpose[1])
+        };
+
+        // STMIA r1!, {r0-r2}, with the base in the middle the new base is stored.
+        assert_eq!(stm(0xE8A1_0007), ([0xA0, BASE + 12, 0xA2], BASE + 12));
+        // STMIA r1!, {r1, r2}, with the base first the old base is stored.
+        assert_eq!(stm(0xE8A1_0006), ([BASE, 0xA2, 0], BASE + 8));
+        // STMIA r1, {r0-r2}, without writeback the base is left untouched.
+        assert_eq!(stm(0xE881_0007), ([0xA0, BASE, 0xA2], BASE));
+    }
+
+    #[test]
+    fn test_user_bank_transfer() {
+        // LDMIA r0, {r13, r14}^
+        let mut emu = emu_with_instruction(0xE8D0_6000);
+        emu.cpu.switch_mode(Mode::IRQ, &mut emu.bus);
+        emu.cpu.registers.general_purpose[13] = 0x1D;
+        emu.cpu.registers.general_purpose[14] = 0x1E;
+        emu.bus.write_32(BASE, 0x0300_7F00);
+        emu.bus.write_32(BASE + 4, 0x0800_0200);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(emu.cpu.registers.general_purpose[13..15], [0x1D, 0x1E]);
+        assert_eq!(
+            emu.cpu.registers.banked_registers(Mode::User)[5..],
+            [0x0300_7F00, 0x0800_0200]
+        );
+
+        // STMIA r0, {r13, r14}^
+        let mut emu = emu_with_instruction(0xE8C0_6000);
+        emu.cpu.registers.general_purpose[13] = 0x0300_7F00;
+        emu.cpu.registers.general_purpose[14] = 0x0800_0200;
+        emu.cpu.switch_mode(Mode::IRQ, &mut emu.bus);
+        emu.cpu.registers.general_purpose[13] = 0x1D;
+        emu.cpu.registers.general_purpose[14] = 0x1E;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.bus.read_32(BASE, &emu.cpu), 0x0300_7F00);
+        assert_eq!(emu.bus.read_32(BASE + 4, &emu.cpu), 0x0800_0200);
+        assert_eq!(emu.cpu.registers.general_purpose[13..15], [0x1D, 0x1E]);
+    }
+}
@@ -20,11 +20,51 @@ impl ArmV4 {
         let pc = cpu.read_reg(PC_REG);
 
         if IS_LINK {
-            // Write the old PC value to the link register.
-            // Subtract an  adjustment to account for our pre-fetching (where we're 2 instructions ahead).
-            cpu.write_reg(LINK_REG, pc.wrapping_sub(4), bus);
+            // The link register should point to the instruction after this one.
+            cpu.write_reg(LINK_REG, cpu.registers.next_pc(), bus);
         }
 
         cpu.write_reg(PC_REG, pc.wrapping_add(offset as u32), bus);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::LINK_REG;
+    use crate::emulator::cpu::test_utils::emu_with_arm;
+
+    #[test]
+    fn test_branch_and_link_return() {
+        // `MOV PC, LR` and `BX LR`
+        for return_instruction in [0xE1A0_F00Eu32, 0xE12F_FF1E] {
+            let program = [
+                // BL 0x0800_0010
+                0xEB00_0002u32,
+                // MOV r0, #1
+                0xE3A0_0001,
+                // B .
+                0xEAFF_FFFE,
+                0,
+                // MOV r1, #2
+                0xE3A0_1002,
+                return_instruction,
+            ];
+            let mut emu = emu_with_arm(&program);
+
+            emu.step_instruction();
+
+            assert_eq!(emu.cpu.registers.general_purpose[LINK_REG], 0x0800_0004);
+            assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0010);
+
+            emu.step_instruction();
+            emu.step_instruction();
+
+            assert_eq!(emu.cpu.registers.general_purpose[1], 2);
+            assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0004);
+
+            emu.step_instruction();
+
+            assert_eq!(emu.cpu.registers.general_purpose[0], 1);
+        }
+    }
+}
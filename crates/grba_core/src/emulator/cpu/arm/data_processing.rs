@@ -1,7 +1,7 @@
 use crate::emulator::bus::Bus;
 use crate::emulator::cpu::arm::{ArmInstruction, ArmV4};
 use crate::emulator::cpu::common::{common_behaviour, ShiftType};
-use crate::emulator::cpu::registers::{Mode, PC_REG};
+use crate::emulator::cpu::registers::Mode;
 use crate::emulator::cpu::CPU;
 use crate::utils::BitOps;
 use num_traits::FromPrimitive;
@@ -54,9 +54,6 @@ impl ArmV4 {
     }
 
     pub fn data_processing_register_register_shift(cpu: &mut CPU, instruction: ArmInstruction, bus: &mut Bus) {
-        //we'll need to increment PC by 4 for the duration of this function, refer to section 4.5.5 of the instruction manual.
-        cpu.registers.general_purpose[PC_REG] += 4;
-
         let opcode = OpCode::from_u32(instruction.get_bits(21, 24)).unwrap();
         let set_condition_code = instruction.check_bit(20);
         let r_d = instruction.get_bits(12, 15) as usize;
@@ -66,24 +63,20 @@ impl ArmV4 {
 
         let (op2_value, carry) = {
             // Register Shift
+            // The operands are read after the shift amount, so PC will be 12 ahead instead of 8,
+            // refer to section 4.5.5 of the instruction manual.
             let shift_register = instruction.get_bits(8, 11) as usize;
-            // Only the lower byte matters, can just directly cast to a u8
-            let shift_amount = cpu.read_reg(shift_register) as u8;
+            // Only the lower byte matters, can just directly cast to a u8.
+            // Should the shift register be PC, it's also read as 12 ahead, matching hardware.
+            let shift_amount = cpu.read_reg_late(shift_register) as u8;
 
-            if shift_amount == 0 {
-                (cpu.read_reg(r_op2), cpu.registers.cpsr.carry())
-            } else {
-                shift_type.perform_shift(cpu.read_reg(r_op2), shift_amount, cpu.registers.cpsr.carry())
-            }
+            shift_type.perform_register_shift(cpu.read_reg_late(r_op2), shift_amount, cpu.registers.cpsr.carry())
         };
 
         let r_op1 = instruction.get_bits(16, 19) as usize;
-        let op1_value = cpu.read_reg(r_op1);
+        let op1_value = cpu.read_reg_late(r_op1);
 
         ArmV4::perform_data_operation(cpu, bus, opcode, op1_value, op2_value, r_d, set_condition_code, carry);
-
-        // Undo our increment from before
-        cpu.registers.general_purpose[PC_REG] -= 4;
     }
 
     #[inline(always)]
@@ -178,24 +171,27 @@ impl ArmV4 {
             }
         };
 
-        // If `r_d` is R15 and the S flag is set then the SPSR of the current mode is moved into the CPSR.
-        // Primarily used for `MOVS` when returning from software interrupts.
-        // Important to do this before the data operations due to force-alignment of PC on write
-        //TODO: This r_d == 15 && set_flags check *may* have to be handled differently due to the fact that in arm.gba
-        // we're currently failing the log diff on instruction 561 (cycle 1122), where the `CMP pc pc` sign flag is visible
-        // in the other emu's log, but not in ours due to the CPSR being overwritten.
-        // This test specifically: https://github.com/jsmolka/gba-tests/blob/a6447c5404c8fc2898ddc51f438271f832083b7e/arm/data_processing.asm#L498
-        // Consider https://discord.com/channels/465585922579103744/465586361731121162/913580452395229186
-        if r_d == 15 && set_flags {
-            // Only overwrite if we actually change mode, otherwise we're erasing status flags for nothing.
-            if cpu.registers.cpsr.mode() != cpu.registers.spsr.mode() {
-                cpu.registers.write_cpsr(cpu.registers.spsr, bus);
+        match opcode {
+            OpCode::Teq | OpCode::Tst | OpCode::Cmn | OpCode::Cmp => {
+                // The result of the comparisons is always discarded, but the `P` variants (`TEQP` etc., with `r_d` being
+                // R15) still move the SPSR of the current mode into the CPSR, overwriting the flags we just set.
+                if r_d == 15 && cpu.registers.cpsr.mode().has_spsr() {
+                    cpu.registers.write_cpsr(cpu.registers.spsr, bus);
+                }
             }
-        }
+            _ => {
+                // If `r_d` is R15 and the S flag is set then the SPSR of the current mode is moved into the CPSR.
+                // Primarily used for `MOVS` when returning from software interrupts.
+                // Important to do this before the data operations due to force-alignment of PC on write
+                if r_d == 15 && set_flags {
+                    // Only overwrite if we actually change mode, otherwise we're erasing status flags for nothing.
+                    if cpu.registers.cpsr.mode() != cpu.registers.spsr.mode() {
+                        cpu.registers.write_cpsr(cpu.registers.spsr, bus);
+                    }
+                }
 
-        match opcode {
-            OpCode::Teq | OpCode::Tst | OpCode::Cmn | OpCode::Cmp => {}
-            _ => cpu.write_reg(r_d, result, bus),
+                cpu.write_reg(r_d, result, bus);
+            }
         }
     }
 }
@@ -219,3 +215,172 @@ enum OpCode {
     Bic = 0b1110,
     Mvn = 0b1111,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::{Mode, PSR};
+    use crate::emulator::cpu::test_utils::emu_with_arm;
+
+    #[test]
+    fn test_pc_operand_immediate_shift() {
+        // MOV r0, pc
+        // ADD r1, pc, r2, LSL #0
+        let mut emu = emu_with_arm(&[0xE1A0_000F, 0xE08F_1002]);
+        emu.cpu.registers.general_purpose[2] = 0;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0x0800_0008);
+        assert_eq!(emu.cpu.registers.general_purpose[1], 0x0800_000C);
+    }
+
+    #[test]
+    fn test_pc_operand_register_shift() {
+        // MOV r0, pc, LSL r1
+        // ADD r3, pc, r2, LSL r1
+        let mut emu = emu_with_arm(&[0xE1A0_011F, 0xE08F_3112]);
+        emu.cpu.registers.general_purpose[1] = 0;
+        emu.cpu.registers.general_purpose[2] = 0;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0x0800_000C);
+        assert_eq!(emu.cpu.registers.general_purpose[3], 0x0800_0010);
+        // PC itself shouldn't have been affected by the reads.
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0008);
+    }
+
+    #[test]
+    fn test_pc_as_shift_register() {
+        // MOV r0, r1, LSL pc
+        // MOV r2, r1, LSL pc
+        let mut emu = emu_with_arm(&[0xE1A0_0F11, 0xE1A0_2F11]);
+        emu.cpu.registers.general_purpose[1] = 1;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // Only the bottom byte of PC + 12 should be used as the shift amount.
+        assert_eq!(emu.cpu.registers.general_purpose[0], 1 << 0xC);
+        assert_eq!(emu.cpu.registers.general_purpose[2], 1 << 0x10);
+    }
+
+    #[test]
+    fn test_pc_destination_register_shift() {
+        // ADD pc, pc, r2, LSL r1
+        let mut emu = emu_with_arm(&[0xE08F_F112]);
+        emu.cpu.registers.general_purpose[1] = 0;
+        emu.cpu.registers.general_purpose[2] = 0;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_000C);
+    }
+
+    #[test]
+    fn test_rotated_immediate_carry() {
+        // MOVS r0, #0x8000_0000 (0x02 ROR 2)
+        // ANDS r1, r2, #0x3F0 (0x3F ROR 28)
+        // MOVS r3, #0
+        let mut emu = emu_with_arm(&[0xE3B0_0102, 0xE212_1E3F, 0xE3B0_3000]);
+        emu.cpu.registers.cpsr.set_carry(false);
+        emu.cpu.registers.general_purpose[2] = 0xFFFF_FFFF;
+
+        // A non-zero rotate sets the carry to bit 31 of the result
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0x8000_0000);
+        assert!(emu.cpu.registers.cpsr.carry());
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[1], 0x3F0);
+        assert!(!emu.cpu.registers.cpsr.carry());
+
+        // A zero rotate leaves the carry untouched
+        emu.cpu.registers.cpsr.set_carry(true);
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[3], 0);
+        assert!(emu.cpu.registers.cpsr.carry());
+        assert!(emu.cpu.registers.cpsr.zero());
+    }
+
+    #[test]
+    fn test_rrx_and_register_ror_by_zero() {
+        // MOVS r0, r1, RRX (ROR #0)
+        // MOVS r2, r1, ROR r3
+        let mut emu = emu_with_arm(&[0xE1B0_0061, 0xE1B0_2371]);
+        emu.cpu.registers.cpsr.set_carry(true);
+        emu.cpu.registers.general_purpose[1] = 0b10;
+        // Only the lower byte of the shift register is used.
+        emu.cpu.registers.general_purpose[3] = 0x100;
+
+        // The old carry is shifted in at the top, bit 0 becomes the new carry.
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0x8000_0001);
+        assert!(!emu.cpu.registers.cpsr.carry());
+
+        // A register shift by 0 leaves both the value and carry untouched.
+        emu.cpu.registers.cpsr.set_carry(true);
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[2], 0b10);
+        assert!(emu.cpu.registers.cpsr.carry());
+    }
+
+    #[test]
+    fn test_register_lsl_by_32_and_more() {
+        // MOVS r0, r1, LSL r2
+        // MOVS r3, r1, LSL r4
+        let mut emu = emu_with_arm(&[0xE1B0_0211, 0xE1B0_3411]);
+        emu.cpu.registers.general_purpose[0] = 0xFFFF_FFFF;
+        emu.cpu.registers.general_purpose[1] = 0x8000_0001;
+        emu.cpu.registers.general_purpose[2] = 32;
+        emu.cpu.registers.general_purpose[3] = 0xFFFF_FFFF;
+        emu.cpu.registers.general_purpose[4] = 33;
+
+        // LSL by 32 shifts everything out, with bit 0 ending up in the carry.
+        emu.cpu.registers.cpsr.set_carry(false);
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0);
+        assert!(emu.cpu.registers.cpsr.carry());
+        assert!(emu.cpu.registers.cpsr.zero());
+
+        // LSL by more than 32 clears the carry as well.
+        emu.cpu.step_instruction(&mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[3], 0);
+        assert!(!emu.cpu.registers.cpsr.carry());
+        assert!(emu.cpu.registers.cpsr.zero());
+    }
+
+    #[test]
+    fn test_comparison_p_variant_restores_spsr() {
+        // TEQP r0, r0
+        let mut emu = emu_with_arm(&[0xE130_F000]);
+        emu.cpu.switch_mode(Mode::Supervisor, &mut emu.bus);
+        // System mode, only the carry flag set.
+        emu.cpu.registers.spsr = PSR::from_raw(0x2000_001F);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // The zero flag set by the comparison should be overwritten by the SPSR.
+        assert_eq!(emu.cpu.registers.cpsr.as_raw(), 0x2000_001F);
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::System);
+        // R15 is never written
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0004);
+    }
+
+    #[test]
+    fn test_comparison_p_variant_without_spsr() {
+        // CMPP r0, #1
+        let mut emu = emu_with_arm(&[0xE350_F001]);
+        emu.cpu.registers.general_purpose[0] = 0;
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // System mode has no SPSR, so only the flags of the comparison remain.
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::System);
+        assert!(emu.cpu.registers.cpsr.sign());
+        assert!(!emu.cpu.registers.cpsr.carry());
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0004);
+    }
+}
@@ -1,8 +1,7 @@
 use crate::emulator::bus::Bus;
 use crate::emulator::cpu::arm::{ArmInstruction, ArmV4};
 use crate::emulator::cpu::common::ShiftType;
-use crate::emulator::cpu::registers::PC_REG;
-use crate::emulator::cpu::CPU;
+use crate::emulator::cpu::{UnimplementedOpcode, CPU};
 use crate::utils::BitOps;
 use num_traits::FromPrimitive;
 
@@ -17,6 +16,9 @@ impl ArmV4 {
         let is_byte_transfer = instruction.check_bit(22);
         let is_up = instruction.check_bit(23);
         let is_preindexed = instruction.check_bit(24);
+        // For post-indexed transfers the W bit instead selects `LDRT`/`STRT`, which always write back and force a
+        // user mode access. The GBA has no memory protection, so the access itself behaves exactly like a privileged one.
+        // Note that the registers of the current mode are still used, only the memory access is downgraded.
         let has_writeback = instruction.check_bit(21);
         let is_immediate = !instruction.check_bit(25);
 
@@ -56,23 +58,20 @@ impl ArmV4 {
                 let value = bus.read_32(address, cpu);
                 // The byte at the address will always be at bits 0..=7, if unaligned access then the rest will be shifted.
                 let final_val = value.rotate_right(8 * (address.get_bits(0, 1)));
+                // On ARMv4 a load into PC never switches to THUMB based on bit 0 (unlike ARMv5),
+                // `write_reg` just force aligns the value to the current state.
                 cpu.write_reg(reg_dest, final_val, bus);
             }
         } else {
             // For store instructions, when R15 is specified in r_d it should be 3 words ahead of the current instruction.
-            // Usually it's +2, thus we need to temporarily add 4 bytes to the address
-            cpu.registers.general_purpose[PC_REG] += 4;
-
             if is_byte_transfer {
-                let data = cpu.read_reg(reg_dest) as u8;
+                let data = cpu.read_reg_late(reg_dest) as u8;
                 bus.write(address, data);
             } else {
-                let data = cpu.read_reg(reg_dest);
+                let data = cpu.read_reg_late(reg_dest);
                 // Force align the address
                 bus.write_32(address, data);
             }
-
-            cpu.registers.general_purpose[PC_REG] -= 4;
         }
 
         // No writeback occurs if the base and destination register are the same AND it's a load instruction.
@@ -186,7 +185,7 @@ impl ArmV4 {
         address: u32,
     ) {
         match sh {
-            SwapType::Swp => todo!("Swap instruction in halfword and signed register?: {:#X?}", instruction),
+            SwapType::Swp => cpu.unimplemented_instruction(bus, UnimplementedOpcode::Arm(instruction)),
             SwapType::UnsignedU16 => {
                 if is_load {
                     let value = bus.read_16(address, cpu) as u32;
@@ -194,7 +193,7 @@ impl ArmV4 {
                     let final_val = value.rotate_right(8 * (address.check_bit(0) as u32));
                     cpu.write_reg(reg_dest, final_val, bus);
                 } else {
-                    let value = cpu.read_reg(reg_dest) as u16;
+                    let value = cpu.read_reg_late(reg_dest) as u16;
                     bus.write_16(address, value);
                 }
             }
@@ -242,3 +241,69 @@ enum SwapType {
     Signedi8 = 0b10,
     Signedi16 = 0b11,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::{Mode, State};
+    use crate::emulator::cpu::test_utils::emu_with_arm;
+
+    const BASE: u32 = 0x0300_0100;
+
+    #[test]
+    fn test_translated_transfer_from_privileged_mode() {
+        // LDRT r0, [r8], #4
+        // STRT r1, [r8], #4
+        let mut emu = emu_with_arm(&[0xE4B8_0004, 0xE4A8_1004]);
+        emu.cpu.registers.general_purpose[8] = 0xDEAD_BEEF;
+        emu.cpu.switch_mode(Mode::FIQ, &mut emu.bus);
+        emu.cpu.registers.general_purpose[8] = BASE;
+        emu.cpu.registers.general_purpose[1] = 0x1234_5678;
+        emu.bus.write_32(BASE, 0xCAFE_BABE);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // The banked FIQ register should be used as the base, and always be written back.
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0xCAFE_BABE);
+        assert_eq!(emu.cpu.registers.general_purpose[8], BASE + 8);
+        assert_eq!(emu.bus.read_32(BASE + 4, &emu.cpu), 0x1234_5678);
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::FIQ);
+
+        // The user mode registers should be left untouched.
+        emu.cpu.switch_mode(Mode::System, &mut emu.bus);
+        assert_eq!(emu.cpu.registers.general_purpose[8], 0xDEAD_BEEF);
+    }
+
+    #[test]
+    fn test_store_pc_is_twelve_ahead() {
+        // STR pc, [r1]
+        // STRB pc, [r1, #4]
+        // STMIA r2, {pc}
+        let mut emu = emu_with_arm(&[0xE581_F000, 0xE5C1_F004, 0xE882_8000]);
+        emu.cpu.registers.general_purpose[1] = BASE;
+        emu.cpu.registers.general_purpose[2] = BASE + 8;
+
+        for _ in 0..3 {
+            emu.cpu.step_instruction(&mut emu.bus);
+        }
+
+        // Both single and block transfers should store the address of the instruction + 12.
+        assert_eq!(emu.bus.read_32(BASE, &emu.cpu), 0x0800_000C);
+        assert_eq!(emu.bus.read_32(BASE + 4, &emu.cpu), 0x10);
+        assert_eq!(emu.bus.read_32(BASE + 8, &emu.cpu), 0x0800_0014);
+    }
+
+    #[test]
+    fn test_load_pc_stays_in_arm() {
+        // LDR pc, [r1]
+        let mut emu = emu_with_arm(&[0xE591_F000]);
+        emu.cpu.registers.general_purpose[1] = BASE;
+        emu.bus.write_32(BASE, 0x0800_0103);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // Bit 0 shouldn't cause a switch to THUMB, and the target is aligned to 4.
+        assert_eq!(emu.cpu.state(), State::Arm);
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0100);
+    }
+}
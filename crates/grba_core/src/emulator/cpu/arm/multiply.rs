@@ -12,6 +12,8 @@ impl ArmV4 {
         let r_add = instruction.get_bits(12, 15) as usize;
         let r_1 = instruction.get_bits(8, 11) as usize;
         let r_2 = instruction.get_bits(0, 3) as usize;
+        // Need to read it before we potentially overwrite it with the result.
+        let cycles = multiply_internal_cycles(cpu.read_reg(r_1), true) + accumulate as i32;
 
         // Check if the accumulate flag is set by casting it to u32, and then adding.
         // Doing this elides a branch (Sadly, compiler doesn't do it for us according to GodBolt :( )
@@ -22,12 +24,15 @@ impl ArmV4 {
         cpu.write_reg(r_d, result, bus);
 
         if should_set_condition {
+            // The carry flag is set to a meaningless value on ARMv4, we just leave it unchanged.
             cpu.set_zero_and_sign(result);
-            // Carry flag set to meaningless value?
         }
+
+        bus.scheduler.add_time(cycles);
     }
 
-    pub fn multiply_long(cpu: &mut CPU, instruction: ArmInstruction, _bus: &mut Bus) {
+    /// Implements the `UMULL`, `UMLAL`, `SMULL`, and `SMLAL` instructions.
+    pub fn multiply_long(cpu: &mut CPU, instruction: ArmInstruction, bus: &mut Bus) {
         let signed = instruction.check_bit(22);
         let accumulate = instruction.check_bit(21);
         let should_set_condition = instruction.check_bit(20);
@@ -35,12 +40,16 @@ impl ArmV4 {
         let r_low = instruction.get_bits(12, 15) as usize;
         let r_1 = instruction.get_bits(8, 11) as usize;
         let r_2 = instruction.get_bits(0, 3) as usize;
+        // Need to read it before we potentially overwrite it with the result.
+        let cycles = multiply_internal_cycles(cpu.read_reg(r_1), signed) + 1 + accumulate as i32;
 
         if signed {
             ArmV4::multiply_long_signed(cpu, accumulate, should_set_condition, r_high, r_low, r_1, r_2);
         } else {
             ArmV4::multiply_long_unsigned(cpu, accumulate, should_set_condition, r_high, r_low, r_1, r_2);
         }
+
+        bus.scheduler.add_time(cycles);
     }
 
     fn multiply_long_unsigned(
@@ -67,7 +76,7 @@ impl ArmV4 {
         if should_set_condition {
             cpu.registers.cpsr.set_sign(result.check_bit(63));
             cpu.registers.cpsr.set_zero(result == 0);
-            // Carry and overflow flags set to meaningless value?
+            // Carry and overflow flags are set to meaningless values on ARMv4, we just leave them unchanged.
         }
     }
 
@@ -97,7 +106,156 @@ impl ArmV4 {
         if should_set_condition {
             cpu.registers.cpsr.set_sign(result.check_bit(63));
             cpu.registers.cpsr.set_zero(result == 0);
-            // Carry and overflow flags set to meaningless value?
+            // Carry and overflow flags are set to meaningless values on ARMv4, we just leave them unchanged.
         }
     }
 }
+
+/// Returns the amount of internal cycles (`m`) a multiply takes, based on the multiplier operand (`Rs`).
+///
+/// The multiplier terminates early if the upper bytes of the operand are all zeroes, or, for signed multiplies,
+/// all ones.
+#[inline]
+pub(crate) fn multiply_internal_cycles(multiplier: u32, signed: bool) -> i32 {
+    let mask_matches = |mask: u32| {
+        let upper = multiplier & mask;
+        upper == 0 || (signed && upper == mask)
+    };
+
+    if mask_matches(0xFFFF_FF00) {
+        1
+    } else if mask_matches(0xFFFF_0000) {
+        2
+    } else if mask_matches(0xFF00_0000) {
+        3
+    } else {
+        4
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::arm::multiply::multiply_internal_cycles;
+    use crate::emulator::cpu::arm::ArmV4;
+    use crate::emulator::{EmuOptions, GBAEmulator};
+
+    const UMULL: u32 = 0xE080_0090;
+    const UMLAL: u32 = 0xE0A0_0090;
+    const SMULL: u32 = 0xE0C0_0090;
+    const SMLAL: u32 = 0xE0E0_0090;
+    const SET_CONDITION: u32 = 1 << 20;
+
+    fn emu() -> GBAEmulator {
+        crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap()
+    }
+
+    /// Encodes `<op> r0, r1, r2, r3`, where `r0` is `RdLo`, `r1` is `RdHi`, `r2` is `Rm`, and `r3` is `Rs`.
+    fn encode_long(op: u32) -> u32 {
+        op | (1 << 16) | (3 << 8) | 2
+    }
+
+    fn run_long(emu: &mut GBAEmulator, op: u32, lo: u32, hi: u32, rm: u32, rs: u32) -> (u32, u32) {
+        let regs = &mut emu.cpu.registers.general_purpose;
+        regs[0] = lo;
+        regs[1] = hi;
+        regs[2] = rm;
+        regs[3] = rs;
+
+        ArmV4::multiply_long(&mut emu.cpu, encode_long(op), &mut emu.bus);
+
+        let regs = &emu.cpu.registers.general_purpose;
+        (regs[0], regs[1])
+    }
+
+    #[test]
+    fn test_signed_vs_unsigned_long_multiply() {
+        let mut emu = emu();
+
+        assert_eq!(run_long(&mut emu, UMULL, 0, 0, 0xFFFF_FFFF, 2), (0xFFFF_FFFE, 0x1));
+        assert_eq!(
+            run_long(&mut emu, SMULL, 0, 0, 0xFFFF_FFFF, 2),
+            (0xFFFF_FFFE, 0xFFFF_FFFF)
+        );
+        assert_eq!(
+            run_long(&mut emu, SMULL, 0, 0, 0x8000_0000, 0x8000_0000),
+            (0x0, 0x4000_0000)
+        );
+    }
+
+    #[test]
+    fn test_accumulate_long_multiply() {
+        let mut emu = emu();
+
+        assert_eq!(run_long(&mut emu, UMLAL, 0xFFFF_FFFF, 0x1, 2, 3), (0x5, 0x2));
+        // -1 + (-2 * 3)
+        assert_eq!(
+            run_long(
+                &mut emu,
+                SMLAL | SET_CONDITION,
+                0xFFFF_FFFF,
+                0xFFFF_FFFF,
+                0xFFFF_FFFE,
+                3
+            ),
+            (0xFFFF_FFF9, 0xFFFF_FFFF)
+        );
+        assert!(emu.cpu.registers.cpsr.sign());
+        assert!(!emu.cpu.registers.cpsr.zero());
+
+        // The result being zero should only consider all 64 bits.
+        assert_eq!(
+            run_long(&mut emu, UMLAL | SET_CONDITION, 0x0, 0x1, 0x0, 0x0),
+            (0x0, 0x1)
+        );
+        assert!(!emu.cpu.registers.cpsr.zero());
+    }
+
+    #[test]
+    fn test_multiply_accumulate_flags() {
+        let mut emu = emu();
+        // MLAS r0, r2, r3, r1
+        let instruction = 0xE030_1392;
+        let regs = &mut emu.cpu.registers.general_purpose;
+        regs[1] = 6;
+        regs[2] = 0xFFFF_FFFE;
+        regs[3] = 3;
+        emu.cpu.registers.cpsr.set_carry(true);
+
+        ArmV4::multiply(&mut emu.cpu, instruction, &mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0);
+        assert!(emu.cpu.registers.cpsr.zero());
+        assert!(!emu.cpu.registers.cpsr.sign());
+        assert!(emu.cpu.registers.cpsr.carry());
+    }
+
+    #[test]
+    fn test_multiply_cycles_use_original_multiplier() {
+        let mut emu = emu();
+        // MUL r0, r1, r0
+        let instruction = 0xE000_0091;
+        let regs = &mut emu.cpu.registers.general_purpose;
+        regs[0] = 2;
+        regs[1] = 0x0100_0000;
+        let start = emu.bus.scheduler.current_time;
+
+        ArmV4::multiply(&mut emu.cpu, instruction, &mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.general_purpose[0], 0x0200_0000);
+        // The product would take 4 cycles, the original multiplier only 1.
+        assert_eq!(emu.bus.scheduler.current_time.0 - start.0, 1);
+    }
+
+    #[test]
+    fn test_multiply_early_termination() {
+        assert_eq!(multiply_internal_cycles(0x0000_00FF, false), 1);
+        assert_eq!(multiply_internal_cycles(0x0000_FFFF, false), 2);
+        assert_eq!(multiply_internal_cycles(0x00FF_FFFF, false), 3);
+        assert_eq!(multiply_internal_cycles(0xFFFF_FFFF, false), 4);
+
+        assert_eq!(multiply_internal_cycles(0xFFFF_FFFF, true), 1);
+        assert_eq!(multiply_internal_cycles(0xFFFF_8000, true), 2);
+        assert_eq!(multiply_internal_cycles(0xFF80_0000, true), 3);
+        assert_eq!(multiply_internal_cycles(0x8000_0000, true), 4);
+    }
+}
@@ -17,7 +17,15 @@ impl ArmV4 {
 
         let contents = match source_psr {
             Psr::Cpsr => cpu.registers.cpsr,
-            Psr::Spsr => cpu.registers.spsr,
+            Psr::Spsr if cpu.registers.cpsr.mode().has_spsr() => cpu.registers.spsr,
+            Psr::Spsr => {
+                // Unpredictable on hardware, we follow other emulators in returning the CPSR instead.
+                crate::cpu_log!(
+                    "MRS of the SPSR in {:?} mode, which has none",
+                    cpu.registers.cpsr.mode()
+                );
+                cpu.registers.cpsr
+            }
         };
 
         cpu.write_reg(r_d, contents.as_raw(), bus);
@@ -87,3 +95,38 @@ impl From<bool> for Psr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::registers::{Mode, PSR};
+    use crate::emulator::cpu::test_utils::emu_with_arm;
+
+    #[test]
+    fn test_mrs_cpsr_and_spsr() {
+        // MRS r0, CPSR
+        // MRS r1, SPSR
+        let mut emu = emu_with_arm(&[0xE10F_0000, 0xE14F_1000]);
+        emu.cpu.switch_mode(Mode::IRQ, &mut emu.bus);
+        emu.cpu.registers.spsr = PSR::from_raw(0x6000_001F);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        assert_eq!(emu.cpu.registers.general_purpose[0], emu.cpu.registers.cpsr.as_raw());
+        assert_eq!(emu.cpu.registers.general_purpose[0] & 0x1F, 0x12);
+        assert_eq!(emu.cpu.registers.general_purpose[1], 0x6000_001F);
+    }
+
+    #[test]
+    fn test_mrs_spsr_without_spsr() {
+        // MRS r1, SPSR
+        let mut emu = emu_with_arm(&[0xE14F_1000]);
+        emu.cpu.registers.spsr = PSR::from_raw(0x6000_0013);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        // System mode has no SPSR, so the CPSR is read instead of whatever is left in the SPSR.
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::System);
+        assert_eq!(emu.cpu.registers.general_purpose[1], emu.cpu.registers.cpsr.as_raw());
+    }
+}
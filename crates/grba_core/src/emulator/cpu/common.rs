@@ -1,5 +1,6 @@
 //! Contains values common for the ARM and THUMB instruction sets.
 
+use crate::emulator::cpu::registers::PC_REG;
 use crate::emulator::cpu::CPU;
 use crate::emulator::MemoryAddress;
 use crate::utils::BitOps;
@@ -16,6 +17,9 @@ pub enum ShiftType {
 impl ShiftType {
     /// Performs the specified shift operation on the given value.
     /// Will return the shifted value, as well as the carry flag.
+    ///
+    /// This follows the immediate shift encoding, where a `shift_amount` of `0` has a special meaning for all but
+    /// [ShiftType::LogicalLeft] (e.g. `ROR #0` is `RRX`). See [Self::perform_register_shift] for register shifts.
     #[inline]
     pub fn perform_shift(self, value: u32, shift_amount: u8, current_carry: bool) -> (u32, bool) {
         match self {
@@ -63,6 +67,7 @@ impl ShiftType {
                 }
             },
             ShiftType::RotateRight => match shift_amount {
+                // RRX
                 0 => {
                     let carry_flag = (current_carry as u32) << 31;
                     // Carry flag is appended and everything is shifted by one position
@@ -75,9 +80,35 @@ impl ShiftType {
             },
         }
     }
+
+    /// Performs the specified shift operation with a shift amount taken from the lower byte of a register.
+    ///
+    /// Unlike the immediate encoding a `shift_amount` of `0` leaves both the value and the carry flag unchanged.
+    #[inline]
+    pub fn perform_register_shift(self, value: u32, shift_amount: u8, current_carry: bool) -> (u32, bool) {
+        if shift_amount == 0 {
+            (value, current_carry)
+        } else {
+            self.perform_shift(value, shift_amount, current_carry)
+        }
+    }
 }
 
 impl CPU {
+    /// Read a register which is only used as an operand after an additional internal cycle, such as the operands of
+    /// a data processing instruction with a register-specified shift, or the data of a store.
+    ///
+    /// By then the pipeline has advanced one more instruction, so reads of `R15` return `PC + 12` (`PC + 6` in THUMB)
+    /// instead of the usual `PC + 8` (`PC + 4`).
+    #[inline(always)]
+    pub(crate) fn read_reg_late(&self, reg: usize) -> u32 {
+        if reg == PC_REG {
+            self.read_reg(PC_REG).wrapping_add(self.registers.instruction_size())
+        } else {
+            self.read_reg(reg)
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn set_zero_and_sign(&mut self, value: u32) {
         self.registers.cpsr.set_zero(value == 0);
@@ -219,13 +250,8 @@ pub mod common_behaviour {
     }
 
     #[inline]
-    pub fn raise_software_interrupt(cpu: &mut CPU, _comment: u32, bus: &mut Bus) {
-        #[cfg(feature = "cpu-logging")]
-        {
-            println!("Raising SI with comment: {:#X}", _comment);
-            crate::cpu_log!("Raising SI with comment: {:#X}", _comment);
-        }
-        cpu.raise_exception(bus, Exception::SoftwareInterrupt);
+    pub fn raise_software_interrupt(cpu: &mut CPU, comment: u8, bus: &mut Bus) {
+        cpu.raise_exception(bus, Exception::SoftwareInterrupt { comment });
     }
 }
 
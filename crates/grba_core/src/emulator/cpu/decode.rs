@@ -0,0 +1,418 @@
+//! Classification of raw instructions, without executing them.
+//!
+//! The same classification is used to fill the CPU's instruction LUTs, so anything decoded here is guaranteed to
+//! match what the [CPU](crate::emulator::cpu::CPU) would execute.
+use crate::emulator::cpu::arm::ArmInstruction;
+use crate::emulator::cpu::registers::State;
+use crate::emulator::cpu::thumb::ThumbInstruction;
+use crate::emulator::MemoryAddress;
+use crate::utils::BitOps;
+
+/// The category of an ARM instruction.
+///
+/// More Info: [Here](https://problemkaputt.de/gbatek.htm#armopcodessummary)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ArmInstructionKind {
+    /// `SWI`
+    SoftwareInterrupt,
+    /// `LDM`/`STM`
+    BlockDataTransfer,
+    /// `MUL`/`MLA`
+    Multiply,
+    /// `UMULL`/`UMLAL`/`SMULL`/`SMLAL`
+    MultiplyLong,
+    /// `SWP`
+    SingleDataSwap,
+    /// `LDRH`/`STRH`/`LDRSB`/`LDRSH`
+    HalfwordDataTransfer,
+    /// `BX`
+    BranchAndExchange,
+    /// `B`/`BL`
+    Branch,
+    /// `LDR`/`STR`
+    SingleDataTransfer,
+    /// `MRS`
+    PsrToRegister,
+    /// `MSR`
+    RegisterToPsr,
+    /// `AND`/`EOR`/`SUB`/.../`MVN`
+    DataProcessing,
+    /// `CDP`/`LDC`/`STC`/`MCR`/`MRC`, the GBA has no coprocessors so these raise an undefined instruction exception.
+    Coprocessor,
+    Undefined,
+}
+
+/// The category of a THUMB instruction.
+///
+/// More Info: [Here](https://problemkaputt.de/gbatek.htm#thumbopcodessummary)
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ThumbInstructionKind {
+    MoveShiftedRegister,
+    AddSubtract,
+    MoveCompareAddSubtractImmediate,
+    AluOperation,
+    HiRegisterOperationBranchExchange,
+    PcRelativeLoad,
+    LoadStoreRegisterOffset,
+    LoadStoreSignExtended,
+    LoadStoreImmediateOffset,
+    LoadStoreHalfword,
+    SpRelativeLoadStore,
+    LoadAddress,
+    AddOffsetToStackPointer,
+    PushPopRegisters,
+    MultipleLoadStore,
+    ConditionalBranch,
+    SoftwareInterrupt,
+    UnconditionalBranch,
+    /// Either half of the two instruction `BL`.
+    LongBranchWithLink,
+    Undefined,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum InstructionKind {
+    Arm(ArmInstructionKind),
+    Thumb(ThumbInstructionKind),
+}
+
+/// A single instruction, as yielded by [decode_instructions].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction {
+    pub address: MemoryAddress,
+    /// The raw instruction, only the lower 16 bits are used for THUMB instructions.
+    pub raw: u32,
+    pub kind: InstructionKind,
+}
+
+/// Classify a single ARM instruction.
+pub fn decode_arm(instruction: ArmInstruction) -> ArmInstructionKind {
+    ArmInstructionKind::from_lut_index(arm_lut_index(instruction))
+}
+
+/// Classify a single THUMB instruction.
+pub fn decode_thumb(instruction: ThumbInstruction) -> ThumbInstructionKind {
+    ThumbInstructionKind::from_lut_index(thumb_lut_index(instruction))
+}
+
+/// Decode all instructions in `data` in the given `state`, where `data` is located at `base_address`.
+///
+/// Any trailing bytes which don't form a full instruction are ignored.
+pub fn decode_instructions(
+    data: &[u8],
+    base_address: MemoryAddress,
+    state: State,
+) -> impl Iterator<Item = DecodedInstruction> + '_ {
+    let instruction_size = match state {
+        State::Arm => 4,
+        State::Thumb => 2,
+    };
+
+    data.chunks_exact(instruction_size).enumerate().map(move |(i, bytes)| {
+        let address = base_address.wrapping_add((i * instruction_size) as MemoryAddress);
+
+        match state {
+            State::Arm => {
+                let raw = u32::from_le_bytes(bytes.try_into().unwrap());
+                DecodedInstruction {
+                    address,
+                    raw,
+                    kind: InstructionKind::Arm(decode_arm(raw)),
+                }
+            }
+            State::Thumb => {
+                let raw = u16::from_le_bytes(bytes.try_into().unwrap());
+                DecodedInstruction {
+                    address,
+                    raw: raw as u32,
+                    kind: InstructionKind::Thumb(decode_thumb(raw)),
+                }
+            }
+        }
+    })
+}
+
+/// The `12-bit` index into the ARM LUT, made up of bits `20..=27` and `4..=7` of the instruction.
+#[inline(always)]
+pub(crate) fn arm_lut_index(instruction: ArmInstruction) -> usize {
+    (((instruction.get_bits(20, 27)) << 4) | instruction.get_bits(4, 7)) as usize
+}
+
+/// The `8-bit` index into the THUMB LUT, the upper byte of the instruction.
+#[inline(always)]
+pub(crate) fn thumb_lut_index(instruction: ThumbInstruction) -> usize {
+    instruction.get_bits(8, 15) as usize
+}
+
+impl ArmInstructionKind {
+    /// Classify the given [arm_lut_index].
+    pub(crate) fn from_lut_index(i: usize) -> ArmInstructionKind {
+        // Software Interrupt:
+        // 1111_XXXX_XXXX
+        if (i & 0xF00) == 0b1111_0000_0000 {
+            return ArmInstructionKind::SoftwareInterrupt;
+        }
+
+        // Block Data Transfer:
+        // 100X_XXXX_XXXX
+        if (i & 0xE00) == 0b1000_0000_0000 {
+            return ArmInstructionKind::BlockDataTransfer;
+        }
+
+        // Multiply:
+        // 0000_00XX_1001
+        if (i & 0xFCF) == 0b0000_0000_1001 {
+            return ArmInstructionKind::Multiply;
+        }
+
+        // Multiply long:
+        // 0000_1XXX_1001
+        if (i & 0xF8F) == 0b0000_1000_1001 {
+            return ArmInstructionKind::MultiplyLong;
+        }
+
+        // Single data swap should always be matched before the halfword transfer (as it is a part of their matching).
+        // Single Data Swap:
+        // 0001_0X00_1001
+        if (i & 0xFBF) == 0b0001_0000_1001 {
+            return ArmInstructionKind::SingleDataSwap;
+        }
+
+        // Halfword Data Transfer:
+        // 000X_XXXX_1XX1
+        if (i & 0xE09) == 0b0000_0000_1001 {
+            return ArmInstructionKind::HalfwordDataTransfer;
+        }
+
+        // Branch and Exchange:
+        // 0001_0010_0001
+        if i == 0b0001_0010_0001 {
+            return ArmInstructionKind::BranchAndExchange;
+        }
+
+        // Branch:
+        // 101X_XXXX_XXXX
+        if (i & 0xE00) == 0b1010_0000_0000 {
+            return ArmInstructionKind::Branch;
+        }
+
+        // The undefined instruction occupies the register offset encoding of single data transfers, which never have
+        // bit 4 set.
+        // Undefined:
+        // 011X_XXXX_XXX1
+        if (i & 0xE01) == 0b0110_0000_0001 {
+            return ArmInstructionKind::Undefined;
+        }
+
+        // Single Data Transfer:
+        // 01XX_XXXX_XXXX
+        if (i & 0xC00) == 0b0100_0000_0000 {
+            return ArmInstructionKind::SingleDataTransfer;
+        }
+
+        // MRS (Transfer PSR to register):
+        // 0001_0X00_0000
+        if (i & 0xFBF) == 0b0001_0000_0000 {
+            return ArmInstructionKind::PsrToRegister;
+        }
+
+        // MSR (Transfer register to PSR Condition Flags):
+        // 00X1_0X10_XXXX
+        if (i & 0xDB0) == 0b0001_0010_0000 {
+            return ArmInstructionKind::RegisterToPsr;
+        }
+
+        // Data Processing:
+        // 00XX_XXXX_XXXX
+        if (i & 0xC00) == 0b0000_0000_0000 {
+            return ArmInstructionKind::DataProcessing;
+        }
+
+        // Coprocessor Data Transfer/Operation/Register Transfer:
+        // 110X_XXXX_XXXX
+        // 1110_XXXX_XXXX
+        if (i & 0xE00) == 0b1100_0000_0000 || (i & 0xF00) == 0b1110_0000_0000 {
+            return ArmInstructionKind::Coprocessor;
+        }
+
+        ArmInstructionKind::Undefined
+    }
+}
+
+impl ThumbInstructionKind {
+    /// Classify the given [thumb_lut_index].
+    pub(crate) fn from_lut_index(i: usize) -> ThumbInstructionKind {
+        // Add/Subtract
+        // 0001_1XXX
+        if (i & 0xF8) == 0b0001_1000 {
+            return ThumbInstructionKind::AddSubtract;
+        }
+
+        // Move Shifted Register:
+        // 000X_XXXX
+        if (i & 0xE0) == 0b0000_0000 {
+            return ThumbInstructionKind::MoveShiftedRegister;
+        }
+
+        // move/compare/add/subtract immediate
+        // 001X_XXXX
+        if (i & 0xE0) == 0b0010_0000 {
+            return ThumbInstructionKind::MoveCompareAddSubtractImmediate;
+        }
+
+        // ALU operations
+        // 0100_00XX
+        if (i & 0xFC) == 0b0100_0000 {
+            return ThumbInstructionKind::AluOperation;
+        }
+
+        // Hi register operations/branch exchange
+        // 0100_01XX
+        if (i & 0xFC) == 0b0100_0100 {
+            return ThumbInstructionKind::HiRegisterOperationBranchExchange;
+        }
+
+        // PC-Relative Load
+        // 0100_1XXX
+        if (i & 0xF8) == 0b0100_1000 {
+            return ThumbInstructionKind::PcRelativeLoad;
+        }
+
+        // Load/Store with Register Offset
+        // 0101_XX0X
+        if (i & 0xF2) == 0b0101_0000 {
+            return ThumbInstructionKind::LoadStoreRegisterOffset;
+        }
+
+        // Load/Store with Sign Extended Byte
+        // 0101_XX1X
+        if (i & 0xF2) == 0b0101_0010 {
+            return ThumbInstructionKind::LoadStoreSignExtended;
+        }
+
+        // Load/Store with immediate offset
+        // 011X_XXXX
+        if (i & 0xE0) == 0b0110_0000 {
+            return ThumbInstructionKind::LoadStoreImmediateOffset;
+        }
+
+        // Load/Store halfword
+        // 1000_XXXX
+        if (i & 0xF0) == 0b1000_0000 {
+            return ThumbInstructionKind::LoadStoreHalfword;
+        }
+
+        // SP-relative load/store
+        // 1001_XXXX
+        if (i & 0xF0) == 0b1001_0000 {
+            return ThumbInstructionKind::SpRelativeLoadStore;
+        }
+
+        // Load address
+        // 1010_XXXX
+        if (i & 0xF0) == 0b1010_0000 {
+            return ThumbInstructionKind::LoadAddress;
+        }
+
+        // Add offset to stack pointer
+        // 1011_0000
+        if (i & 0xFF) == 0b1011_0000 {
+            return ThumbInstructionKind::AddOffsetToStackPointer;
+        }
+
+        // Push/Pop registers
+        // 1011_X10X
+        if (i & 0xF6) == 0b1011_0100 {
+            return ThumbInstructionKind::PushPopRegisters;
+        }
+
+        // Multiple load/store
+        // 1100_XXXX
+        if (i & 0xF0) == 0b1100_0000 {
+            return ThumbInstructionKind::MultipleLoadStore;
+        }
+
+        // Software Interrupt
+        // 1101_1111
+        if i == 0b1101_1111 {
+            return ThumbInstructionKind::SoftwareInterrupt;
+        }
+
+        // Conditional Branch
+        // 1101_XXXX
+        if (i & 0xF0) == 0b1101_0000 {
+            return ThumbInstructionKind::ConditionalBranch;
+        }
+
+        // Unconditional Branch
+        // 1110_0XXX
+        if (i & 0xF8) == 0b1110_0000 {
+            return ThumbInstructionKind::UnconditionalBranch;
+        }
+
+        // Long branch with link
+        // 1111_XXXX
+        if (i & 0xF0) == 0b1111_0000 {
+            return ThumbInstructionKind::LongBranchWithLink;
+        }
+
+        ThumbInstructionKind::Undefined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::decode::{
+        decode_arm, decode_instructions, ArmInstructionKind, DecodedInstruction, InstructionKind, ThumbInstructionKind,
+    };
+    use crate::emulator::cpu::registers::State;
+
+    #[test]
+    fn test_decode_arm() {
+        // MOV r1, #0x0800_0000
+        assert_eq!(decode_arm(0xE3A0_1408), ArmInstructionKind::DataProcessing);
+        // BX r1
+        assert_eq!(decode_arm(0xE12F_FF11), ArmInstructionKind::BranchAndExchange);
+        // LDRH r0, [r1]
+        assert_eq!(decode_arm(0xE1D1_00B0), ArmInstructionKind::HalfwordDataTransfer);
+        // SWP r0, r1, [r2]
+        assert_eq!(decode_arm(0xE102_0091), ArmInstructionKind::SingleDataSwap);
+        // MSR CPSR_f, #0xF0000000
+        assert_eq!(decode_arm(0xE328_F20F), ArmInstructionKind::RegisterToPsr);
+        // SWI 0x060000
+        assert_eq!(decode_arm(0xEF06_0000), ArmInstructionKind::SoftwareInterrupt);
+        // MCR p15, 0, r0, c1, c0, 0
+        assert_eq!(decode_arm(0xEE01_0F10), ArmInstructionKind::Coprocessor);
+        // LDC p1, c0, [r0]
+        assert_eq!(decode_arm(0xED90_0100), ArmInstructionKind::Coprocessor);
+        // UDF, overlapping with `LDR r0, [r0, -r0]` apart from bit 4.
+        assert_eq!(decode_arm(0xE7F0_00F0), ArmInstructionKind::Undefined);
+        assert_eq!(decode_arm(0xE710_0000), ArmInstructionKind::SingleDataTransfer);
+    }
+
+    #[test]
+    fn test_decode_thumb_instructions() {
+        // SWI 0x05, BL (both halves), B .
+        let data = [0x05, 0xDF, 0x00, 0xF0, 0x00, 0xF8, 0xFE, 0xE7, 0xFF];
+        let decoded = decode_instructions(&data, 0x0800_0000, State::Thumb).collect::<Vec<_>>();
+
+        assert_eq!(decoded.len(), 4);
+        assert_eq!(
+            decoded[0],
+            DecodedInstruction {
+                address: 0x0800_0000,
+                raw: 0xDF05,
+                kind: InstructionKind::Thumb(ThumbInstructionKind::SoftwareInterrupt),
+            }
+        );
+        assert_eq!(
+            decoded[2].kind,
+            InstructionKind::Thumb(ThumbInstructionKind::LongBranchWithLink)
+        );
+        assert_eq!(decoded[3].address, 0x0800_0006);
+        assert_eq!(
+            decoded[3].kind,
+            InstructionKind::Thumb(ThumbInstructionKind::UnconditionalBranch)
+        );
+    }
+}
@@ -1,13 +1,16 @@
+use std::collections::BTreeSet;
+
 use registers::{Mode, State};
 
 use crate::emulator::bus::Bus;
 use crate::emulator::cpu::arm::{ArmInstruction, ArmLUT, ArmV4};
 use crate::emulator::cpu::registers::{Registers, LINK_REG, PC_REG};
 use crate::emulator::cpu::thumb::{ThumbInstruction, ThumbLUT};
-use crate::utils::BitOps;
+use crate::emulator::BiosSkipState;
 
 mod arm;
 mod common;
+pub mod decode;
 pub mod registers;
 mod thumb;
 
@@ -21,6 +24,22 @@ pub struct CPU {
     pub pipeline: [u32; 3],
     pub arm_lut: ArmLUT,
     pub thumb_lut: ThumbLUT,
+    /// Set when the [crate::logging::BinaryLogger] requested a break *before* the current instruction was executed.
+    #[cfg(feature = "bin-logging")]
+    pub(crate) logger_break: bool,
+    /// Whether to raise an undefined instruction exception on unimplemented instructions, instead of panicking.
+    pub(crate) trap_unimplemented: bool,
+    /// All unimplemented instructions which were trapped so far.
+    pub(crate) unimplemented_opcodes: BTreeSet<UnimplementedOpcode>,
+    /// The comment of the most recent `SWI`, which the GBA BIOS uses as the function number.
+    pub last_software_interrupt: Option<u8>,
+}
+
+/// An instruction which isn't implemented by the [CPU], see [crate::emulator::EmuOptions::trap_unimplemented].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum UnimplementedOpcode {
+    Arm(u32),
+    Thumb(u16),
 }
 
 impl CPU {
@@ -28,25 +47,42 @@ impl CPU {
     ///
     /// # Arguments
     ///
-    /// * `skip_bios` - Whether to skip the BIOS. If skipped will initialise appropriate registers
-    pub fn new(skip_bios: bool, bus: &mut Bus) -> CPU {
+    /// * `skip_bios` - Whether to skip the BIOS, and if so, which register state to initialise.
+    ///     * [BiosSkipState::Full] sets everything the BIOS would have left behind: `CPSR = 0x6000001F`,
+    ///       `r0 = 0x08000000`, `r1 = 0xEA`, the System/Supervisor/IRQ stack pointers, `PC = 0x08000000`, as well as
+    ///       the `POSTFLG` and `SOUNDBIAS` I/O registers.
+    ///     * [BiosSkipState::Minimal] only sets the stack pointers and `PC`, leaving the default `CPSR` (System mode,
+    ///       IRQs disabled). Useful for diffing logs against emulators which do the same.
+    pub fn new(skip_bios: Option<BiosSkipState>, bus: &mut Bus) -> CPU {
         let mut result = CPU {
             registers: Registers::default(),
             pipeline: [0; 3],
             arm_lut: arm::create_arm_lut(),
             thumb_lut: thumb::create_thumb_lut(),
+            #[cfg(feature = "bin-logging")]
+            logger_break: false,
+            trap_unimplemented: false,
+            unimplemented_opcodes: BTreeSet::new(),
+            last_software_interrupt: None,
         };
 
-        if skip_bios {
-            // Temporarily commented out for log diffing.
-            // result.registers.cpsr = registers::PSR::from(0x6000001F);
-            // result.registers.general_purpose[0] = 0x08000000;
-            // result.registers.general_purpose[1] = 0x000000EA;
+        if let Some(skip_state) = skip_bios {
+            // The BIOS sets `POSTFLG` after the first boot, which some homebrew checks to distinguish a cold boot from a reset.
+            bus.system_control.write_post_flag(1);
+
+            if skip_state == BiosSkipState::Full {
+                result.registers.cpsr = registers::PSR::from(0x6000001F);
+                result.registers.general_purpose[0] = 0x08000000;
+                result.registers.general_purpose[1] = 0x000000EA;
+
+                bus.initialise_post_bios_io();
+            }
+
             result.registers.general_purpose[13] = 0x03007F00; // SP
 
             result.registers.r13_bank[registers::Mode::Supervisor.to_bank_index()] = 0x03007FE0; // SP SVC
             result.registers.r13_bank[registers::Mode::IRQ.to_bank_index()] = 0x03007FA0; // SP IRQ
-            result.registers.general_purpose[15] = 0x08000000; // PC
+            result.registers.general_purpose[15] = bus.rom.entry_point(); // PC
         }
 
         result.flush_pipeline(bus);
@@ -58,12 +94,25 @@ impl CPU {
     /// Advances the CPU one instruction.
     #[inline(always)]
     pub fn step_instruction(&mut self, bus: &mut Bus) {
+        // The logged state is the one the instruction executes with, but the break has to happen before the prefetch
+        // below touches the bus, as the instruction will be stepped again afterwards.
+        #[cfg(feature = "bin-logging")]
+        {
+            let old_pc = self.registers.pc();
+            self.registers.advance_pc();
+            log_cpu_state(self, self.pipeline[1]);
+            self.registers.general_purpose[PC_REG] = old_pc;
+
+            self.logger_break = crate::logging::should_break();
+            if self.logger_break {
+                return;
+            }
+        }
+
         // We immediately advance the pipeline once to recover from pipeline flush (which only partly fills the pipeline)
         self.advance_pipeline(bus);
 
         crate::cpu_log!("Registers: {:X?}", self.registers);
-        #[cfg(feature = "bin-logging")]
-        log_cpu_state(self);
 
         match self.state() {
             State::Arm => {
@@ -89,13 +138,19 @@ impl CPU {
             State::Arm => bus.read_32(self.registers.pc(), self),
             State::Thumb => bus.read_16(self.registers.pc(), self) as u32,
         };
+        // The opcode fetch takes back the data bus from any DMA which ran in the meantime.
+        bus.dma_bus_latch = None;
     }
 
     /// Clear the entire pipeline, and partly refills it afterwards.
     ///
     /// This is a partial refill to account for us immediately incrementing the PC when we next execute an instruction.
+    ///
+    /// The refill is charged by the fetches themselves, using the wait states of the region that was branched to. The
+    /// first fetch is always non-sequential, even when the target directly follows the last prefetched opcode.
     fn flush_pipeline(&mut self, bus: &mut Bus) {
         self.pipeline[0] = 0;
+        bus.break_sequential_access();
 
         match self.state() {
             State::Arm => {
@@ -109,6 +164,8 @@ impl CPU {
                 self.pipeline[2] = bus.read_16(self.registers.pc(), self) as u32;
             }
         }
+
+        bus.dma_bus_latch = None;
     }
 
     #[profiling::function]
@@ -118,7 +175,7 @@ impl CPU {
             return;
         }
 
-        let lut_index = (((instruction.get_bits(20, 27)) << 4) | instruction.get_bits(4, 7)) as usize;
+        let lut_index = decode::arm_lut_index(instruction);
 
         crate::cpu_log!("Executing Arm LUT: {:#b} - Raw: {:#X}", lut_index, instruction);
         self.arm_lut[lut_index](self, instruction, bus);
@@ -127,7 +184,7 @@ impl CPU {
     #[profiling::function]
     #[inline(always)]
     fn execute_thumb(&mut self, bus: &mut Bus, instruction: ThumbInstruction) {
-        let lut_index = instruction.get_bits(8, 15) as usize;
+        let lut_index = decode::thumb_lut_index(instruction);
 
         crate::cpu_log!("Executing Thumb LUT: {:#b} - Raw: {:#X}", lut_index, instruction);
         self.thumb_lut[lut_index](self, instruction, bus);
@@ -149,21 +206,51 @@ impl CPU {
         // Switch to a new state
         crate::cpu_log!("Switching CPU state to {:?}", new_state);
         if self.state() != new_state {
+            // No need to flush the pipeline here, the state can only change as part of `BX` or an exception, both of
+            // which write to R15 afterwards. That flush then refills the pipeline with the new instruction size.
             self.registers.cpsr.set_state(new_state);
-            // Do we need to flush pipeline here? At the very least probably need to align PC to new state?
-            // After looking at the EMU discord I think the answer is: No, so long as we don't write to R15 we don't
-            // flush the pipeline.
         }
     }
 
+    /// Handle an instruction which isn't implemented.
+    ///
+    /// Panics, unless [crate::emulator::EmuOptions::trap_unimplemented] is set. In that case the instruction is recorded
+    /// and an undefined instruction exception is raised instead, allowing execution to continue.
+    pub(crate) fn unimplemented_instruction(&mut self, bus: &mut Bus, opcode: UnimplementedOpcode) {
+        if !self.trap_unimplemented {
+            match opcode {
+                UnimplementedOpcode::Arm(instruction) => panic!("Unimplemented Arm instruction: {:08x}", instruction),
+                UnimplementedOpcode::Thumb(instruction) => {
+                    panic!("Unimplemented Thumb instruction: {:#06X}", instruction)
+                }
+            }
+        }
+
+        crate::cpu_log!("Trapped unimplemented instruction: {:X?}", opcode);
+        self.unimplemented_opcodes.insert(opcode);
+        self.raise_exception(bus, Exception::UndefinedInstruction);
+    }
+
     fn raise_exception(&mut self, bus: &mut Bus, exception: Exception) {
-        // SoftwareInterrupt and IRQ are the only exceptions that can be raised (besides UndefinedInstruction) in the GBA
+        // SoftwareInterrupt, UndefinedInstruction, and IRQ are the only exceptions that can be raised in the GBA
+        const UNDEFINED_INSTRUCTION_ADDR: u32 = 0x00000004;
         const SOFTWARE_INTERRUPT_ADDR: u32 = 0x00000008;
         const IRQ_ADDR: u32 = 0x00000018;
 
         let (pipeline_subtraction, jump_addr, new_mode) = match exception {
-            Exception::SoftwareInterrupt => {
-                // println!("Raising Software Interrupt");
+            Exception::UndefinedInstruction => {
+                // Just like software interrupts the link register should point to the next instruction.
+                let pipeline_subtraction = match self.state() {
+                    State::Arm => 4,
+                    State::Thumb => 2,
+                };
+
+                (pipeline_subtraction, UNDEFINED_INSTRUCTION_ADDR, Mode::Undefined)
+            }
+            Exception::SoftwareInterrupt { comment } => {
+                crate::cpu_log!("Raising SI with comment: {:#X}", comment);
+                self.last_software_interrupt = Some(comment);
+
                 let pipeline_subtraction = match self.state() {
                     State::Arm => 4,
                     State::Thumb => 2,
@@ -271,10 +358,10 @@ impl CPU {
 }
 
 #[inline(always)]
-fn log_cpu_state(cpu: &CPU) {
+fn log_cpu_state(cpu: &CPU, instruction: u32) {
     let frame = crate::logging::InstructionFrame {
         registers: crate::logging::InstructionSnapshot::from_registers(&cpu.registers),
-        instruction: cpu.pipeline[0],
+        instruction,
     };
 
     crate::bin_log!(crate::logging::BIN_TARGET_FRAME, frame.as_ref());
@@ -282,7 +369,10 @@ fn log_cpu_state(cpu: &CPU) {
 
 #[derive(Debug)]
 pub enum Exception {
-    SoftwareInterrupt,
+    SoftwareInterrupt {
+        /// The BIOS function number, taken from bits `16..=23` in ARM, or bits `0..=7` in THUMB.
+        comment: u8,
+    },
     UndefinedInstruction,
     /// Unused in GBA
     PrefetchAbort,
@@ -293,3 +383,403 @@ pub enum Exception {
     DataAbort,
     Reset,
 }
+
+/// Shared helpers for tests which execute a handful of instructions.
+#[cfg(test)]
+pub(crate) mod test_utils {
+    use crate::emulator::cpu::registers::{PC_REG, PSR};
+    use crate::emulator::{EmuOptions, GBAEmulator};
+
+    /// Create a ROM with the given ARM `instructions` at its start, padded to leave room for the cartridge header.
+    pub(crate) fn arm_rom(instructions: &[u32]) -> Vec<u8> {
+        let mut rom: Vec<u8> = instructions.iter().flat_map(|instr| instr.to_le_bytes()).collect();
+        rom.resize(rom.len().max(0x200), 0);
+        rom
+    }
+
+    /// Create a ROM with the given THUMB `instructions` at its start, padded to leave room for the cartridge header.
+    pub(crate) fn thumb_rom(instructions: &[u16]) -> Vec<u8> {
+        let mut rom: Vec<u8> = instructions.iter().flat_map(|instr| instr.to_le_bytes()).collect();
+        rom.resize(rom.len().max(0x200), 0);
+        rom
+    }
+
+    /// Create an emulator which executes the given ARM `instructions` from the start of the ROM.
+    pub(crate) fn emu_with_arm(instructions: &[u32]) -> GBAEmulator {
+        crate::headless::headless_emulator(arm_rom(instructions), None, EmuOptions::default()).unwrap()
+    }
+
+    /// Create an emulator in System mode with IRQs disabled, which executes the given THUMB `instructions` from the
+    /// start of the ROM.
+    pub(crate) fn emu_with_thumb(instructions: &[u16]) -> GBAEmulator {
+        let mut emu = crate::headless::headless_emulator(thumb_rom(instructions), None, EmuOptions::default()).unwrap();
+        emu.cpu.registers.cpsr = PSR::from_raw(0xBF);
+        emu.cpu.write_reg(PC_REG, 0x0800_0000, &mut emu.bus);
+        emu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::interrupts::{Interrupts, IE_START, IF_START, IME_START};
+    use crate::emulator::cpu::registers::{Mode, State, LINK_REG, PC_REG, PSR};
+    use crate::emulator::cpu::test_utils::{emu_with_arm, emu_with_thumb};
+    use crate::emulator::cpu::UnimplementedOpcode;
+    use crate::emulator::{EmuOptions, GBAEmulator};
+    use crate::scheduler::EmuTime;
+
+    const SP_SYSTEM: u32 = 0x03007F00;
+    const SP_IRQ: u32 = 0x03007FA0;
+
+    /// Create an emulator with a zeroed ROM and BIOS, with a pending (and enabled) VBlank interrupt.
+    fn emu_with_pending_irq() -> GBAEmulator {
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        let bus = &mut emu.bus;
+
+        bus.interrupts.write_ie(IE_START, Interrupts::Vblank as u8);
+        bus.interrupts.write_ime(IME_START, 1, &mut bus.scheduler);
+        bus.interrupts.request_interrupt(Interrupts::Vblank, &mut bus.scheduler);
+
+        emu
+    }
+
+    #[test]
+    fn test_irq_entry_from_arm() {
+        let mut emu = emu_with_pending_irq();
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        // System mode, ARM, IRQs enabled.
+        cpu.registers.cpsr = PSR::from_raw(0x1F);
+
+        // Execute the instruction at 0x0800_0000, the IRQ should return to 0x0800_0004
+        cpu.step_instruction(bus);
+        let old_cpsr = cpu.registers.cpsr.as_raw();
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(cpu.registers.cpsr.state(), State::Arm);
+        assert!(cpu.registers.cpsr.irq_disable());
+        assert_eq!(cpu.registers.spsr.as_raw(), old_cpsr);
+        assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0008);
+        assert_eq!(cpu.read_reg(13), SP_IRQ);
+        assert_eq!(cpu.registers.next_pc(), 0x18);
+        // The System stack pointer should have been banked
+        assert_eq!(cpu.registers.r13_bank[Mode::System.to_bank_index()], SP_SYSTEM);
+    }
+
+    #[test]
+    fn test_irq_entry_from_thumb() {
+        let mut emu = emu_with_pending_irq();
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        // System mode, THUMB, IRQs enabled.
+        cpu.registers.cpsr = PSR::from_raw(0x3F);
+        cpu.write_reg(PC_REG, 0x0800_0000, bus);
+
+        // Execute the instruction at 0x0800_0000, `SUBS PC, LR, #4` should return to 0x0800_0002
+        cpu.step_instruction(bus);
+        let old_cpsr = cpu.registers.cpsr.as_raw();
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(cpu.registers.cpsr.state(), State::Arm);
+        assert!(cpu.registers.cpsr.irq_disable());
+        assert_eq!(cpu.registers.spsr.as_raw(), old_cpsr);
+        assert_eq!(cpu.registers.spsr.state(), State::Thumb);
+        assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0006);
+        assert_eq!(cpu.read_reg(13), SP_IRQ);
+        assert_eq!(cpu.registers.next_pc(), 0x18);
+    }
+
+    #[test]
+    fn test_nested_irq_banks_spsr() {
+        let mut emu = emu_with_pending_irq();
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        cpu.registers.cpsr = PSR::from_raw(0x1F);
+
+        cpu.step_instruction(bus);
+        let system_cpsr = cpu.registers.cpsr.as_raw();
+        cpu.poll_interrupts(bus);
+
+        // Re-enable IRQs within the handler without acknowledging the interrupt, as a nested handler would.
+        cpu.registers.cpsr.set_irq_disable(false);
+        let irq_cpsr = cpu.registers.cpsr.as_raw();
+        // Execute the instruction at 0x18, the nested IRQ should return to 0x1C
+        cpu.step_instruction(bus);
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert!(cpu.registers.cpsr.irq_disable());
+        assert_eq!(cpu.registers.spsr.as_raw(), irq_cpsr);
+        assert_eq!(cpu.registers.spsr.mode(), Mode::IRQ);
+        assert_eq!(cpu.read_reg(LINK_REG), 0x20);
+        assert_eq!(cpu.read_reg(13), SP_IRQ);
+
+        // Returning to System mode should bank the (nested) SPSR and restore the System registers.
+        cpu.registers.write_cpsr(PSR::from_raw(system_cpsr), bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::System);
+        assert_eq!(cpu.read_reg(13), SP_SYSTEM);
+        assert_eq!(cpu.registers.spsr_bank[Mode::IRQ.to_spsr_index()].as_raw(), irq_cpsr);
+        assert_eq!(cpu.registers.r14_bank[Mode::IRQ.to_bank_index()], 0x20);
+    }
+
+    #[test]
+    fn test_irq_acknowledge_does_not_refire() {
+        let mut emu = emu_with_pending_irq();
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        cpu.registers.cpsr = PSR::from_raw(0x1F);
+        // A disabled interrupt which shouldn't be affected by acknowledging the VBlank.
+        bus.interrupts.request_interrupt(Interrupts::Hblank, &mut bus.scheduler);
+
+        cpu.step_instruction(bus);
+        let system_cpsr = cpu.registers.cpsr.as_raw();
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+
+        // Writing a `0` shouldn't acknowledge anything, writing a `1` should only clear that specific flag.
+        bus.write_16(IF_START, 0);
+        assert_eq!(
+            u16::from(bus.interrupts.flags),
+            Interrupts::Vblank as u16 | Interrupts::Hblank as u16
+        );
+        bus.write_16(IF_START, Interrupts::Vblank as u16);
+        assert_eq!(u16::from(bus.interrupts.flags), Interrupts::Hblank as u16);
+
+        // Returning from the handler shouldn't immediately re-enter it.
+        cpu.registers.write_cpsr(PSR::from_raw(system_cpsr), bus);
+        cpu.step_instruction(bus);
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::System);
+        assert!(!cpu.registers.cpsr.irq_disable());
+    }
+
+    #[test]
+    fn test_simultaneous_irqs_acknowledged_individually() {
+        let mut emu = emu_with_pending_irq();
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        cpu.registers.cpsr = PSR::from_raw(0x1F);
+        bus.interrupts
+            .write_ie(IE_START, Interrupts::Vblank as u8 | Interrupts::Timer0 as u8);
+        bus.interrupts.request_interrupt(Interrupts::Timer0, &mut bus.scheduler);
+
+        cpu.step_instruction(bus);
+        let system_cpsr = cpu.registers.cpsr.as_raw();
+        cpu.poll_interrupts(bus);
+
+        // A single IRQ is raised, leaving it up to the handler to pick which interrupt to service first.
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(
+            u16::from(bus.interrupts.flags),
+            Interrupts::Vblank as u16 | Interrupts::Timer0 as u16
+        );
+
+        // Servicing the timer first shouldn't affect the VBlank.
+        bus.write_16(IF_START, Interrupts::Timer0 as u16);
+        assert_eq!(u16::from(bus.interrupts.flags), Interrupts::Vblank as u16);
+
+        // The VBlank is still pending, so returning from the handler enters it again.
+        cpu.registers.write_cpsr(PSR::from_raw(system_cpsr), bus);
+        cpu.step_instruction(bus);
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::IRQ);
+
+        bus.write_16(IF_START, Interrupts::Vblank as u16);
+        assert_eq!(u16::from(bus.interrupts.flags), 0);
+
+        cpu.registers.write_cpsr(PSR::from_raw(system_cpsr), bus);
+        cpu.step_instruction(bus);
+        cpu.poll_interrupts(bus);
+
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::System);
+    }
+
+    #[test]
+    fn test_software_interrupt_comment() {
+        // SWI 0x060000 (Div)
+        let mut emu = emu_with_arm(&[0xEF06_0000]);
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+
+        cpu.step_instruction(bus);
+
+        assert_eq!(cpu.last_software_interrupt, Some(0x06));
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::Supervisor);
+        assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0004);
+        assert_eq!(cpu.registers.next_pc(), 0x08);
+    }
+
+    #[test]
+    fn test_undefined_instruction_exception() {
+        // MCR p15, 0, r0, c1, c0, 0 and UDF
+        for instruction in [0xEE01_0F10u32, 0xE7F0_00F0] {
+            let mut emu = emu_with_arm(&[instruction]);
+            let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+            let old_cpsr = cpu.registers.cpsr.as_raw();
+
+            cpu.step_instruction(bus);
+
+            assert_eq!(cpu.registers.cpsr.mode(), Mode::Undefined, "{:08X}", instruction);
+            assert!(cpu.registers.cpsr.irq_disable());
+            assert_eq!(cpu.registers.spsr.as_raw(), old_cpsr);
+            assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0004);
+            assert_eq!(cpu.registers.next_pc(), 0x04);
+        }
+    }
+
+    #[test]
+    fn test_software_interrupt_comment_from_thumb() {
+        // SWI 0x05 (VBlankIntrWait)
+        let mut emu = emu_with_thumb(&[0xDF05]);
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+
+        cpu.step_instruction(bus);
+
+        assert_eq!(cpu.last_software_interrupt, Some(0x05));
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::Supervisor);
+        assert_eq!(cpu.registers.cpsr.state(), State::Arm);
+        assert_eq!(cpu.registers.spsr.state(), State::Thumb);
+        // Should return to the instruction after the `SWI`
+        assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0002);
+        assert_eq!(cpu.registers.next_pc(), 0x08);
+    }
+
+    #[test]
+    fn test_branch_refill_cycles() {
+        fn branch_cost(emu: &mut GBAEmulator, target: u32) -> EmuTime {
+            emu.cpu.registers.general_purpose[0] = target;
+            emu.cpu.write_reg(PC_REG, 0x0300_0000, &mut emu.bus);
+            let start = emu.bus.scheduler.current_time;
+
+            emu.step_instruction();
+
+            assert_eq!(emu.cpu.registers.next_pc(), target);
+            emu.bus.scheduler.current_time - start
+        }
+
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        // BX r0
+        emu.bus.write_32(0x0300_0000, 0xE12F_FF10);
+
+        // IWRAM has no wait states, so only the flat instruction cost remains.
+        assert_eq!(branch_cost(&mut emu, 0x0300_0010), EmuTime(2));
+        // A non-sequential and sequential 32 bit fetch from ROM take `(5 + 3) + (3 + 3)` cycles, minus the 4 base cycles.
+        assert_eq!(branch_cost(&mut emu, 0x0800_0000), EmuTime(2 + 10));
+
+        // Fastest WAITCNT timings for wait state 0 (`N = 3`, `S = 2`)
+        emu.bus.write_16(0x0400_0204, 0x0018);
+        assert_eq!(branch_cost(&mut emu, 0x0800_0000), EmuTime(2 + 5));
+    }
+
+    #[test]
+    fn test_branch_refill_is_non_sequential() {
+        let mut emu = emu_with_arm(&[
+            // B 0x0800_000C, the address the next prefetch would have been at.
+            0xEA00_0001,
+            0,
+            0,
+            0xEAFF_FFFE,
+        ]);
+        emu.cpu.write_reg(PC_REG, 0x0800_0000, &mut emu.bus);
+        let start = emu.bus.scheduler.current_time;
+
+        emu.step_instruction();
+
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_000C);
+        // The sequential prefetch takes `3 + 3` cycles, the refill `(5 + 3) + (3 + 3)`, minus the 6 base cycles.
+        assert_eq!(emu.bus.scheduler.current_time - start, EmuTime(2 + 4 + 10));
+    }
+
+    #[test]
+    fn test_pc_relative_after_arm_branch() {
+        let mut emu = emu_with_arm(&[
+            // B 0x0800_0010
+            0xEA00_0002,
+            0,
+            0,
+            0,
+            // LDR r0, [pc, #4]
+            0xE59F_0004,
+            // ADD r2, pc, #0
+            0xE28F_2000,
+            // STR pc, [r1]
+            0xE581_F000,
+            0xDEAD_BEEF,
+        ]);
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        cpu.registers.general_purpose[1] = 0x0300_0000;
+
+        for _ in 0..4 {
+            cpu.step_instruction(bus);
+        }
+
+        assert_eq!(cpu.read_reg(0), 0xDEAD_BEEF);
+        assert_eq!(cpu.read_reg(2), 0x0800_001C);
+        // Stores see PC one instruction further ahead.
+        assert_eq!(bus.read_32(0x0300_0000, cpu), 0x0800_0024);
+        assert_eq!(cpu.registers.next_pc(), 0x0800_001C);
+    }
+
+    #[test]
+    fn test_pc_relative_after_thumb_branch() {
+        // 0x0800_0000: B 0x0800_0006
+        // 0x0800_0006: LDR r0, [pc, #4]
+        // 0x0800_0008: ADD r1, pc, #4
+        // 0x0800_000C: 0xCAFE_BABE
+        let mut emu = emu_with_thumb(&[0xE001, 0, 0, 0x4801, 0xA101, 0, 0xBABE, 0xCAFE]);
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+
+        for _ in 0..3 {
+            cpu.step_instruction(bus);
+        }
+
+        // Both are relative to the word aligned `PC + 4`
+        assert_eq!(cpu.read_reg(0), 0xCAFE_BABE);
+        assert_eq!(cpu.read_reg(1), 0x0800_0010);
+        assert_eq!(cpu.registers.next_pc(), 0x0800_000A);
+    }
+
+    /// Create an emulator in THUMB mode, with the undefined (in ARMv4T) `0xE800` instruction at the start of the ROM.
+    fn emu_with_undefined_thumb(trap_unimplemented: bool) -> GBAEmulator {
+        let mut emu = emu_with_thumb(&[0xE800]);
+        emu.cpu.trap_unimplemented = trap_unimplemented;
+        emu
+    }
+
+    #[test]
+    fn test_undefined_thumb_instruction() {
+        // Even without trapping, as the instruction is undefined rather than unimplemented.
+        let mut emu = emu_with_undefined_thumb(false);
+
+        emu.cpu.step_instruction(&mut emu.bus);
+
+        let cpu = &emu.cpu;
+        assert_eq!(cpu.registers.cpsr.mode(), Mode::Undefined);
+        assert_eq!(cpu.registers.cpsr.state(), State::Arm);
+        assert_eq!(cpu.read_reg(LINK_REG), 0x0800_0002);
+        assert_eq!(cpu.registers.next_pc(), 0x04);
+        assert!(emu.unimplemented_opcodes().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Unimplemented Thumb instruction")]
+    fn test_unimplemented_panics_by_default() {
+        let mut emu = emu_with_undefined_thumb(false);
+
+        emu.cpu
+            .unimplemented_instruction(&mut emu.bus, UnimplementedOpcode::Thumb(0xE800));
+    }
+
+    #[test]
+    fn test_trap_unimplemented() {
+        let mut emu = emu_with_undefined_thumb(true);
+
+        emu.cpu
+            .unimplemented_instruction(&mut emu.bus, UnimplementedOpcode::Thumb(0xE800));
+
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::Undefined);
+        assert!(emu
+            .unimplemented_opcodes()
+            .contains(&UnimplementedOpcode::Thumb(0xE800)));
+    }
+}
@@ -75,14 +75,17 @@ impl Registers {
     /// Aka, in `ARM` mode it'll be `4` bytes behind the current 'truthful' PC, and in `THUMB` mode it'll be `2` bytes behind.
     #[inline]
     pub fn next_pc(&self) -> u32 {
-        let state = self.cpsr.state();
+        self.pc().saturating_sub(self.instruction_size())
+    }
 
-        let to_subtract = match state {
+    /// The size in bytes of a single instruction in the current [State], and thus the distance between each pipeline
+    /// stage.
+    #[inline(always)]
+    pub fn instruction_size(&self) -> u32 {
+        match self.cpsr.state() {
             State::Arm => 4,
             State::Thumb => 2,
-        };
-
-        self.pc().saturating_sub(to_subtract)
+        }
     }
 
     /// Swap the register banks. Saving the current registers in the `from_mode` bank, and loading the `to_mode` bank.
@@ -164,6 +167,52 @@ impl Registers {
         true
     }
 
+    /// The values of `r8..=r14` as seen from the given `mode`, regardless of which mode is currently active.
+    ///
+    /// The registers of the current mode live in [Self::general_purpose], whereas those of all other modes are taken
+    /// from their banks.
+    pub fn banked_registers(&self, mode: Mode) -> [u32; 7] {
+        let current_mode = self.cpsr.mode();
+        let mut result = [0; 7];
+
+        // `r8..=r12` are only banked separately for FIQ
+        if (mode == Mode::FIQ) == (current_mode == Mode::FIQ) {
+            result[..5].copy_from_slice(&self.general_purpose[8..=12]);
+        } else {
+            let bank = if mode == Mode::FIQ { Mode::FIQ.to_bank_index() } else { Mode::User.to_bank_index() };
+
+            result[..5].copy_from_slice(&[
+                self.r8_bank[bank],
+                self.r9_bank[bank],
+                self.r10_bank[bank],
+                self.r11_bank[bank],
+                self.r12_bank[bank],
+            ]);
+        }
+
+        if mode.to_bank_index() == current_mode.to_bank_index() {
+            result[5..].copy_from_slice(&self.general_purpose[13..=14]);
+        } else {
+            result[5] = self.r13_bank[mode.to_bank_index()];
+            result[6] = self.r14_bank[mode.to_bank_index()];
+        }
+
+        result
+    }
+
+    /// The SPSR of the given `mode`, regardless of which mode is currently active.
+    ///
+    /// Returns `None` for [Mode::User] and [Mode::System], as they don't have an SPSR.
+    pub fn banked_spsr(&self, mode: Mode) -> Option<PSR> {
+        if !mode.has_spsr() {
+            None
+        } else if mode == self.cpsr.mode() {
+            Some(self.spsr)
+        } else {
+            Some(self.spsr_bank[mode.to_spsr_index()])
+        }
+    }
+
     #[inline(always)]
     pub(crate) fn read_reg(&self, reg: usize) -> u32 {
         self.general_purpose[reg]
@@ -180,14 +229,7 @@ impl Registers {
 
     #[inline(always)]
     pub(crate) fn advance_pc(&mut self) {
-        match self.cpsr.state() {
-            State::Arm => {
-                self.general_purpose[PC_REG] += 4;
-            }
-            State::Thumb => {
-                self.general_purpose[PC_REG] += 2;
-            }
-        }
+        self.general_purpose[PC_REG] += self.instruction_size();
     }
 
     /// Write to the CPSR, and if the new mode is different from the current mode, swap the register banks.
@@ -223,7 +265,7 @@ impl From<u8> for State {
 
 /// The mode the CPU can find itself in.
 /// Triggered by different exceptions.
-#[derive(Debug, Eq, PartialEq, Copy, Clone, num_derive::FromPrimitive)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, num_derive::FromPrimitive, enum_iterator::Sequence)]
 pub enum Mode {
     User = 0b1_0000,
     FIQ = 0b1_0001,
@@ -442,7 +484,7 @@ impl PSR {
 
 #[cfg(test)]
 mod tests {
-    use crate::emulator::cpu::registers::{Mode, PSR};
+    use crate::emulator::cpu::registers::{Mode, Registers, PSR};
 
     #[test]
     fn psr_test() {
@@ -461,4 +503,32 @@ mod tests {
         assert_eq!(cpsr.state(), super::State::Thumb);
         assert_eq!(cpsr.as_raw(), value);
     }
+
+    #[test]
+    fn test_banked_registers() {
+        let mut registers = Registers::default();
+        registers.general_purpose[8] = 8;
+        registers.general_purpose[13] = 13;
+        registers.general_purpose[14] = 14;
+        registers.spsr_bank[Mode::FIQ.to_spsr_index()] = PSR::from_raw(0x1F);
+        registers.r8_bank[Mode::FIQ.to_bank_index()] = 0xF8;
+        registers.r13_bank[Mode::IRQ.to_bank_index()] = 0x1D;
+
+        // The current (System) mode should read the live registers, as should User mode, which shares its bank.
+        assert_eq!(registers.banked_registers(Mode::System)[0], 8);
+        assert_eq!(registers.banked_registers(Mode::User)[5..], [13, 14]);
+        assert_eq!(registers.banked_registers(Mode::FIQ)[0], 0xF8);
+        assert_eq!(registers.banked_registers(Mode::IRQ)[0], 8);
+        assert_eq!(registers.banked_registers(Mode::IRQ)[5], 0x1D);
+        assert!(registers.banked_spsr(Mode::System).is_none());
+
+        // Once in FIQ the roles are reversed.
+        registers.cpsr.set_mode(Mode::FIQ);
+        registers.swap_register_banks(Mode::System, Mode::FIQ, true);
+
+        assert_eq!(registers.banked_registers(Mode::FIQ)[0], 0xF8);
+        assert_eq!(registers.banked_registers(Mode::System)[0], 8);
+        assert_eq!(registers.banked_registers(Mode::System)[5..], [13, 14]);
+        assert_eq!(registers.banked_spsr(Mode::FIQ).unwrap().as_raw(), 0x1F);
+    }
 }
@@ -123,48 +123,24 @@ impl ThumbV4 {
 
                 cpu.set_zero_and_sign(result);
             }
+            // Register shifts only use the lower byte of `op2`, where `0` leaves the value and carry untouched.
             AluDataOperation::Lsl => {
-                let (result, carry) = ShiftType::LogicalLeft.perform_shift(op1, op2 as u8, cpu.registers.cpsr.carry());
+                let (result, carry) =
+                    ShiftType::LogicalLeft.perform_register_shift(op1, op2 as u8, cpu.registers.cpsr.carry());
                 cpu.write_reg(r_d, result, bus);
 
                 cpu.set_logical_flags(result, carry);
             }
             AluDataOperation::Lsr => {
-                // It seems Thumb ALU shift behaviour doesn't match ARM barrel shifter edge cases with regard to shift == 0?
-                let (result, carry) = match op2 {
-                    0 => (op1, cpu.registers.cpsr.carry()),
-                    1..=31 => {
-                        let carry = op1.check_bit(op2.saturating_sub(1) as u8);
-                        let shifted = op1 >> op2;
-                        (shifted, carry)
-                    }
-                    32 => (0, op1.check_bit(31)),
-                    _ => (0, false),
-                };
-
+                let (result, carry) =
+                    ShiftType::LogicalRight.perform_register_shift(op1, op2 as u8, cpu.registers.cpsr.carry());
                 cpu.write_reg(r_d, result, bus);
 
                 cpu.set_logical_flags(result, carry);
             }
             AluDataOperation::Asr => {
-                // It seems Thumb ALU shift behaviour doesn't match ARM barrel shifter edge cases with regard to shift == 0?
-                let (result, carry) = match op2 {
-                    0 => (op1, cpu.registers.cpsr.carry()),
-                    1..=31 => {
-                        let carry = op1.check_bit(op2.saturating_sub(1) as u8);
-                        // We cast to an i32 to get an arithmetic shift, then cast back.
-                        let shifted = ((op1 as i32) >> op2) as u32;
-
-                        (shifted, carry)
-                    }
-                    _ => {
-                        let carry = op1.check_bit(31);
-                        // Since we're doing signed extension we either return nothing at all or all ones.
-                        let shifted = if carry { u32::MAX } else { 0 };
-                        (shifted, carry)
-                    }
-                };
-
+                let (result, carry) =
+                    ShiftType::ArithRight.perform_register_shift(op1, op2 as u8, cpu.registers.cpsr.carry());
                 cpu.write_reg(r_d, result, bus);
 
                 cpu.set_logical_flags(result, carry);
@@ -180,18 +156,11 @@ impl ThumbV4 {
                 cpu.write_reg(r_d, result, bus);
             }
             AluDataOperation::Ror => {
-                // It seems Thumb ALU shift behaviour doesn't match ARM barrel shifter edge cases with regard to shift == 0?
-                let (result, carry) = match op2 {
-                    0 => (op1, cpu.registers.cpsr.carry()),
-                    _ => {
-                        let shifted = op1.rotate_right(op2 as u32);
-                        (shifted, shifted.check_bit(31))
-                    }
-                };
+                let (result, carry) =
+                    ShiftType::RotateRight.perform_register_shift(op1, op2 as u8, cpu.registers.cpsr.carry());
+                cpu.write_reg(r_d, result, bus);
 
                 cpu.set_logical_flags(result, carry);
-
-                cpu.write_reg(r_d, result, bus);
             }
             AluDataOperation::Tst => {
                 let result = op1 & op2;
@@ -256,3 +225,29 @@ enum AluDataOperation {
     Bic = 0b1110,
     Mvn = 0b1111,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::cpu::test_utils::emu_with_thumb;
+
+    #[test]
+    fn test_register_shift_uses_lower_byte() {
+        // ROR r0, r1
+        // LSR r2, r1
+        let mut emu = emu_with_thumb(&[0x41C8, 0x40CA]);
+        let (cpu, bus) = (&mut emu.cpu, &mut emu.bus);
+        cpu.registers.cpsr.set_carry(true);
+        cpu.registers.general_purpose[0] = 0x1234_5678;
+        cpu.registers.general_purpose[1] = 0x100;
+        cpu.registers.general_purpose[2] = 0x8765_4321;
+
+        // A shift amount of 0x100 is a shift by 0, so neither the value nor the carry should change.
+        cpu.step_instruction(bus);
+        assert_eq!(cpu.registers.general_purpose[0], 0x1234_5678);
+        assert!(cpu.registers.cpsr.carry());
+
+        cpu.step_instruction(bus);
+        assert_eq!(cpu.registers.general_purpose[2], 0x8765_4321);
+        assert!(cpu.registers.cpsr.carry());
+    }
+}
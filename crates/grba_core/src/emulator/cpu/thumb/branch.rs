@@ -72,7 +72,7 @@ impl ThumbV4 {
         let offset = (instruction.get_bits(0, 10) as u32) << 1;
         let lr = cpu.read_reg(LINK_REG);
         let final_value = lr.wrapping_add(offset as u32);
-        let next_instruction_address = cpu.read_reg(PC_REG).wrapping_sub(2);
+        let next_instruction_address = cpu.registers.next_pc();
 
         cpu.write_reg(PC_REG, final_value, bus);
         // The link register should contain the next instruction's (at this instruction, aka, before the jump) address
@@ -59,9 +59,8 @@ impl ThumbV4 {
 
         // Handle edge case of empty register list.
         if register_list == 0 {
-            // PC is +2 further ahead than usual during this edge case
-            // Empty register list is interpreted as PC_REG being transferred.
-            let reg_value = cpu.read_reg(PC_REG) + 2;
+            // Empty register list is interpreted as PC_REG being transferred, which is read one instruction later than usual.
+            let reg_value = cpu.read_reg_late(PC_REG);
             bus.write_32(base_address, reg_value);
 
             // When the register list is empty we add 0x40 to the base address.
@@ -1,3 +1,5 @@
+use std::ops::RangeInclusive;
+
 use crate::emulator::bus::Bus;
 use crate::emulator::cpu::CPU;
 use crate::emulator::{GBAEmulator, MemoryAddress};
@@ -27,6 +29,25 @@ impl<'a> DebugEmulator<'a> {
     pub fn bus_and_cpu(&mut self) -> (&mut Bus, &mut CPU) {
         (&mut self.0.bus, &mut self.0.cpu)
     }
+
+    /// Replace all memory breakpoints.
+    ///
+    /// Conditions which already hold won't break until they stop holding and are met again.
+    pub fn set_memory_breakpoints(&mut self, mut conditions: Vec<MemoryCondition>) {
+        let (bus, cpu) = (&mut self.0.bus, &self.0.cpu);
+
+        for condition in &mut conditions {
+            condition.was_met = condition.is_met(bus, cpu);
+        }
+
+        self.0.debug.memory_breakpoints = conditions;
+    }
+
+    /// Replace all DMA watchpoints, discarding any hit which hasn't been reported yet.
+    #[cfg(feature = "debug-functionality")]
+    pub fn set_dma_watchpoints(&mut self, watchpoints: Vec<DmaWatchpoint>) {
+        self.0.bus.dma_watch = DmaWatchpoints { watchpoints, hit: None };
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -38,11 +59,127 @@ pub enum Breakpoint {
     ///
     /// Will be removed once hit.
     Cycle(EmuTime),
+    /// The [crate::logging::BinaryLogger] requested a break before the instruction at the given address was executed.
+    ///
+    /// Only occurs with the `bin-logging` feature.
+    Logger(MemoryAddress),
+    /// The given [MemoryCondition] started to hold after the last executed instruction.
+    Memory(MemoryCondition),
+    /// A DMA accessed a range watched by a [DmaWatchpoint] during the last executed instruction.
+    ///
+    /// As DMAs are instant the transfer will have completed already.
+    Dma(DmaWatchHit),
+}
+
+/// The kind of DMA access a [DmaWatchpoint] triggers on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DmaAccess {
+    Read,
+    Write,
+}
+
+/// Break when a DMA reads from, or writes to, any address in `range`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DmaWatchpoint {
+    pub range: RangeInclusive<MemoryAddress>,
+    pub access: DmaAccess,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DmaWatchHit {
+    pub channel: usize,
+    /// The offset in bytes from the start of the transfer at which the watched range was first accessed.
+    pub offset: u32,
+    pub address: MemoryAddress,
+    pub access: DmaAccess,
+}
+
+/// All [DmaWatchpoint]s, checked by the DMA transfer loop if the `debug-functionality` feature is enabled.
+#[cfg(feature = "debug-functionality")]
+#[derive(Clone, Debug, Default)]
+pub struct DmaWatchpoints {
+    pub watchpoints: Vec<DmaWatchpoint>,
+    /// The first hit since the last executed instruction, reported by [GBAEmulator::step_instruction_debug].
+    pub(crate) hit: Option<DmaWatchHit>,
+}
+
+#[cfg(feature = "debug-functionality")]
+impl DmaWatchpoints {
+    /// Check a single DMA transfer from `source` to `dest`, ignored if an earlier hit hasn't been reported yet.
+    #[inline]
+    pub(crate) fn check(&mut self, channel: usize, offset: u32, source: MemoryAddress, dest: MemoryAddress) {
+        if self.hit.is_some() {
+            return;
+        }
+
+        for watchpoint in &self.watchpoints {
+            let address = match watchpoint.access {
+                DmaAccess::Read => source,
+                DmaAccess::Write => dest,
+            };
+
+            if watchpoint.range.contains(&address) {
+                self.hit = Some(DmaWatchHit {
+                    channel,
+                    offset,
+                    address,
+                    access: watchpoint.access,
+                });
+                return;
+            }
+        }
+    }
+}
+
+/// A condition on the contents of memory, met when `[address] & mask == value`.
+///
+/// The `32` bits starting at `address` are compared, a smaller `mask` can be used to only look at a byte or halfword.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryCondition {
+    pub address: MemoryAddress,
+    pub mask: u32,
+    pub value: u32,
+    /// Whether the condition held after the previous instruction, so that only the moment it becomes true breaks.
+    was_met: bool,
+}
+
+impl MemoryCondition {
+    pub fn new(address: MemoryAddress, mask: u32, value: u32) -> Self {
+        Self {
+            address,
+            mask,
+            value,
+            was_met: false,
+        }
+    }
+
+    /// Whether `other` checks the same memory for the same value, regardless of whether either was met before.
+    pub fn is_same_condition(&self, other: &MemoryCondition) -> bool {
+        self.address == other.address && self.mask == other.mask && self.value == other.value
+    }
+
+    pub fn is_met(&self, bus: &mut Bus, cpu: &CPU) -> bool {
+        let bytes = [0, 1, 2, 3].map(|offset| bus.read_dbg(self.address.wrapping_add(offset), cpu));
+
+        u32::from_le_bytes(bytes) & self.mask == self.value
+    }
+
+    /// Check the condition, returning `true` only if it holds now, but didn't the last time it was checked.
+    #[cfg(feature = "debug-functionality")]
+    pub(crate) fn check(&mut self, bus: &mut Bus, cpu: &CPU) -> bool {
+        let met = self.is_met(bus, cpu);
+        let became_met = met && !self.was_met;
+        self.was_met = met;
+
+        became_met
+    }
 }
 
 pub struct EmuDebugState {
     /// Expects a sorted [Vec].
     pub breakpoints: Vec<MemoryAddress>,
     pub break_at_cycle: Option<u64>,
+    /// Only checked with the `debug-functionality` feature, see [DebugEmulator::set_memory_breakpoints].
+    pub memory_breakpoints: Vec<MemoryCondition>,
     pub last_hit_breakpoint: Option<Breakpoint>,
 }
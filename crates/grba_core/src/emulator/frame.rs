@@ -44,4 +44,38 @@ impl RgbaFrame {
             )
         }
     }
+
+    /// Calculate a (non-cryptographic) 64-bit FNV-1a hash of the frame's contents.
+    ///
+    /// The hash is stable across runs and platforms, which makes it suitable for quickly checking whether two frames
+    /// are identical without having to compare them pixel by pixel.
+    pub fn hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+        self.as_bytes().iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::frame::RgbaFrame;
+    use crate::emulator::ppu::RGBA;
+
+    #[test]
+    fn test_frame_hash() {
+        let mut frame = RgbaFrame::new();
+        let original_hash = frame.hash();
+
+        assert_eq!(original_hash, RgbaFrame::new().hash());
+
+        frame[100] = RGBA {
+            red: 0xFF,
+            ..RGBA::default()
+        };
+
+        assert_ne!(frame.hash(), original_hash);
+    }
 }
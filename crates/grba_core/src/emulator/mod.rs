@@ -1,14 +1,24 @@
+use std::collections::BTreeSet;
+use std::ops::RangeInclusive;
+use std::path::Path;
+
 use bus::Bus;
 use cartridge::Cartridge;
 use cpu::CPU;
 use debug::EmuDebugState;
 
 use crate::emulator::bus::dma::DmaStartTiming;
+use crate::emulator::bus::interrupts::Interrupts;
 use crate::emulator::bus::BiosData;
+use crate::emulator::cartridge::header::CartBackupId;
 use crate::emulator::cpu::registers::PC_REG;
+use crate::emulator::cpu::UnimplementedOpcode;
 use crate::emulator::frame::RgbaFrame;
+use crate::emulator::ppu::RGBA;
+use crate::error::CoreError;
+use crate::logging::LogTargets;
 use crate::scheduler::{EmuTime, Event, EventTag};
-use crate::InputKeys;
+use crate::{InputKeys, InputState};
 
 pub mod bus;
 pub mod cartridge;
@@ -16,6 +26,8 @@ pub mod cpu;
 pub mod debug;
 pub mod frame;
 pub mod ppu;
+#[cfg(feature = "subsystem-timing")]
+pub mod timing;
 
 /// Refers to an *absolute* memory address.
 /// Therefore any component which takes this as an incoming type *must* pre-process the value to turn it into an address
@@ -23,50 +35,172 @@ pub mod ppu;
 pub type MemoryAddress = u32;
 pub type AlignedAddress = u32;
 
+/// Called with the finished frame whenever the emulator reaches VBlank, see [GBAEmulator::on_vblank].
+pub type VblankCallback = Box<dyn FnMut(&[RGBA]) + Send>;
+
 /// The main emulator struct
 pub struct GBAEmulator {
     pub(crate) cpu: CPU,
     pub(crate) bus: Bus,
     pub(crate) debug: EmuDebugState,
     pub options: EmuOptions,
+    /// Input which should be applied at the start of the next frame.
+    pending_input: Option<InputState>,
+    /// Keys which were pressed since the last completed frame.
+    pressed_this_frame: InputState,
+    /// Keys which were released before the frame they were pressed in completed, see [Self::key_up].
+    deferred_releases: InputState,
+    /// The fractional cycles left over from the last [EmuOptions::clock_scale] adjustment.
+    clock_scale_remainder: f64,
+    /// Invoked at the end of every frame, see [Self::on_vblank].
+    vblank_callback: Option<VblankCallback>,
+    /// The amount of frames completed since the emulator was created, see [Self::frame_count].
+    frame_count: u64,
+    /// Set to `Some` to start recording the time spent in each subsystem, `None` by default.
+    #[cfg(feature = "subsystem-timing")]
+    pub subsystem_timings: Option<timing::SubsystemTimings>,
 }
 
 impl GBAEmulator {
-    pub fn new(rom: Cartridge, mut options: EmuOptions) -> Self {
-        let mut mmu = Bus::new(rom, vec_to_bios_data(options.bios.clone()));
-
-        GBAEmulator {
-            cpu: CPU::new(options.should_skip_bios(), &mut mmu),
+    /// Create a new emulator for the given cartridge.
+    ///
+    /// # Errors
+    ///
+    /// If the [EmuOptions::bios] isn't a valid BIOS.
+    pub fn new(mut rom: Cartridge, mut options: EmuOptions) -> Result<Self, CoreError> {
+        rom.set_backup_override(options.backup_override);
+        options.clock_scale = EmuOptions::clamp_clock_scale(options.clock_scale);
+        let mut mmu = Bus::new(rom, vec_to_bios_data(options.bios.clone())?);
+        crate::logging::set_log_targets(options.log_targets);
+        mmu.ppu.access_stalls = options.ppu_access_stalls;
+        let mut cpu = CPU::new(options.bios_skip_state(), &mut mmu);
+        cpu.trap_unimplemented = options.trap_unimplemented;
+
+        Ok(GBAEmulator {
+            cpu,
             bus: mmu,
             options,
+            pending_input: None,
+            pressed_this_frame: InputState::empty(),
+            deferred_releases: InputState::empty(),
+            clock_scale_remainder: 0.0,
+            vblank_callback: None,
+            frame_count: 0,
+            #[cfg(feature = "subsystem-timing")]
+            subsystem_timings: None,
             debug: EmuDebugState {
                 breakpoints: Vec::new(),
                 break_at_cycle: None,
+                memory_breakpoints: Vec::new(),
                 last_hit_breakpoint: None,
             },
-        }
+        })
     }
 
     /// Reset the emulator, while keeping breakpoints/settings.
-    pub fn reset(&mut self) {
-        let cartridge = std::mem::replace(&mut self.bus.rom, Cartridge::default());
-        self.bus = Bus::new(cartridge, vec_to_bios_data(self.options.bios.clone()));
-        self.cpu = CPU::new(self.options.should_skip_bios(), &mut self.bus);
+    ///
+    /// Changes to [Self::options], like [EmuOptions::skip_bios], take effect from here on.
+    ///
+    /// # Errors
+    ///
+    /// If the [EmuOptions::bios] was changed to an invalid BIOS, in which case the emulator is left untouched.
+    pub fn reset(&mut self) -> Result<(), CoreError> {
+        let bios = vec_to_bios_data(self.options.bios.clone())?;
+        let mut cartridge = std::mem::replace(&mut self.bus.rom, Cartridge::default());
+        cartridge.set_backup_override(self.options.backup_override);
+        let unimplemented_opcodes = std::mem::take(&mut self.cpu.unimplemented_opcodes);
+        #[cfg(feature = "debug-functionality")]
+        let dma_watch = std::mem::take(&mut self.bus.dma_watch);
+
+        self.bus = Bus::new(cartridge, bios);
+        self.bus.ppu.access_stalls = self.options.ppu_access_stalls;
+        #[cfg(feature = "debug-functionality")]
+        {
+            self.bus.dma_watch = dma_watch;
+        }
+        self.cpu = CPU::new(self.options.bios_skip_state(), &mut self.bus);
+        self.cpu.trap_unimplemented = self.options.trap_unimplemented;
+        self.cpu.unimplemented_opcodes = unimplemented_opcodes;
+        self.set_clock_scale(self.options.clock_scale);
+
+        Ok(())
+    }
+
+    /// Change [EmuOptions::clock_scale], clamped by [EmuOptions::clamp_clock_scale], taking effect immediately.
+    pub fn set_clock_scale(&mut self, clock_scale: f32) {
+        self.options.clock_scale = EmuOptions::clamp_clock_scale(clock_scale);
+        self.clock_scale_remainder = 0.0;
+    }
+
+    /// Persist any writes to the cartridge's save since the last flush, see [Cartridge::flush_save].
+    pub fn flush_save(&mut self) -> std::io::Result<()> {
+        self.bus.rom.flush_save()
+    }
+
+    /// All unimplemented instructions which have been encountered so far, these are only collected if
+    /// [EmuOptions::trap_unimplemented] is set.
+    pub fn unimplemented_opcodes(&self) -> &BTreeSet<UnimplementedOpcode> {
+        &self.cpu.unimplemented_opcodes
+    }
+
+    /// Register a callback which is invoked with the finished frame whenever the emulator reaches VBlank, replacing any
+    /// previous callback.
+    ///
+    /// This is an alternative to polling [Self::frame_buffer] after every frame, which keeps working as before.
+    ///
+    /// The callback runs on the emulation thread, after the frame has fully completed, and can't access the emulator
+    /// itself. Should it panic the panic propagates out of the call which reached VBlank, the emulator is left in a
+    /// consistent state at the start of the next frame.
+    pub fn on_vblank(&mut self, callback: VblankCallback) {
+        self.vblank_callback = Some(callback);
+    }
+
+    /// Remove the callback registered with [Self::on_vblank], returning it.
+    pub fn clear_on_vblank(&mut self) -> Option<VblankCallback> {
+        self.vblank_callback.take()
+    }
+
+    /// The amount of frames which reached VBlank since the emulator was created, including those before a [Self::reset].
+    ///
+    /// Frames completed while single stepping count as well, whereas a frame which is still in progress doesn't.
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count
     }
 
     /// Run the emulator until it has reached Vblank
     #[profiling::function]
     pub fn run_to_vblank(&mut self) {
+        self.apply_pending_input();
+
         while !self.step_instruction() {}
         profiling::finish_frame!();
     }
 
+    /// Run the emulator until it has reached Vblank, executing at most `max_instructions` instructions.
+    ///
+    /// # Returns
+    ///
+    /// The amount of instructions executed, or `None` if Vblank wasn't reached within `max_instructions`.
+    pub fn run_to_vblank_limited(&mut self, max_instructions: u64) -> Option<u64> {
+        self.apply_pending_input();
+
+        for executed in 1..=max_instructions {
+            if self.step_instruction() {
+                return Some(executed);
+            }
+        }
+
+        None
+    }
+
     /// Run the emulator until it has reached `Vblank`.
     ///
     /// # Returns
     ///
     /// `true` if the emulator hit a breakpoint, stopping execution early.
     pub fn run_to_vblank_debug(&mut self) -> bool {
+        self.apply_pending_input();
+
         loop {
             let (vblank, breakpoint) = self.step_instruction_debug();
 
@@ -81,24 +215,62 @@ impl GBAEmulator {
 
     /// Step the emulator for a single instruction.
     ///
+    /// Reaching `Vblank` completes the frame, releasing keys deferred by [Self::key_up] and invoking the
+    /// [Self::on_vblank] callback.
+    ///
     /// # Returns
     ///
     /// `true` if `Vblank` was reached, `false` otherwise.
     #[inline(always)]
     pub fn step_instruction(&mut self) -> bool {
+        let start_time = self.bus.scheduler.current_time;
         self.cpu.step_instruction(&mut self.bus);
 
+        // The CPU broke before it touched the bus, so no time should pass either.
+        #[cfg(feature = "bin-logging")]
+        if self.cpu.logger_break {
+            return false;
+        }
+
         // Very basic cycle counting to get things going. In the future ought to count cycles properly.
         //TODO: Instruction timing
         self.bus.scheduler.add_time(2);
 
+        if self.options.clock_scale != 1.0 {
+            self.scale_cpu_time(start_time);
+        }
+
+        let mut vblank = false;
+
+        // Even when we hit VBlank we keep going, as any events sharing its timestamp would otherwise be delayed until
+        // after the next instruction.
         while let Some(event) = self.bus.scheduler.pop_current() {
-            if self.handle_scheduled_event(event) {
-                return true;
+            // A HALT persisted during VBlank will handle the remaining events itself once we resume.
+            if vblank && event.tag == EventTag::Halt {
+                self.bus.scheduler.schedule_event(event.tag, event.timestamp);
+                break;
             }
+
+            vblank |= self.handle_scheduled_event(event);
         }
 
-        false
+        if vblank {
+            self.finish_frame();
+        }
+
+        vblank
+    }
+
+    /// Rescale the time the CPU spent since `start_time` by [EmuOptions::clock_scale].
+    ///
+    /// Fractional cycles are carried over to the next instruction, so the average speed matches the scale exactly.
+    fn scale_cpu_time(&mut self, start_time: EmuTime) {
+        let elapsed = (self.bus.scheduler.current_time.0 - start_time.0) as f64;
+        let scaled = elapsed / self.options.clock_scale as f64 + self.clock_scale_remainder;
+        let whole_cycles = scaled.floor();
+
+        self.clock_scale_remainder = scaled - whole_cycles;
+        self.bus.scheduler.current_time = EmuTime(start_time.0 + whole_cycles as u64);
     }
 
     /// Handle a scheduled event.
@@ -109,17 +281,37 @@ impl GBAEmulator {
     /// * `false` in all other cases.
     #[inline(always)]
     fn handle_scheduled_event(&mut self, event: Event) -> bool {
+        #[cfg(feature = "subsystem-timing")]
+        if self.subsystem_timings.is_some() {
+            let start = std::time::Instant::now();
+            let vblank = self.dispatch_event(event);
+
+            if let Some(timings) = &mut self.subsystem_timings {
+                timings.record(event.tag, start.elapsed());
+            }
+
+            return vblank;
+        }
+
+        self.dispatch_event(event)
+    }
+
+    #[inline(always)]
+    fn dispatch_event(&mut self, event: Event) -> bool {
         match event.tag {
             EventTag::Exit => {
                 panic!("Exit shouldn't ever be triggered!");
             }
             EventTag::VBlank => {
                 self.bus.ppu.vblank(&mut self.bus.scheduler, &mut self.bus.interrupts);
+                #[cfg(feature = "access-heatmap")]
+                self.bus.access_heatmap.end_frame();
 
                 self.bus.poll_dmas(&self.cpu, DmaStartTiming::VBlank);
 
                 return true;
             }
+            EventTag::VBlankEnd => self.bus.ppu.vblank_end(),
             EventTag::HBlank => {
                 self.bus
                     .ppu
@@ -179,6 +371,8 @@ impl GBAEmulator {
                 match event.tag {
                     EventTag::VBlank => {
                         self.bus.ppu.vblank(&mut self.bus.scheduler, &mut self.bus.interrupts);
+                        #[cfg(feature = "access-heatmap")]
+                        self.bus.access_heatmap.end_frame();
 
                         // Persist the HALT state
                         self.bus.scheduler.schedule_event(EventTag::Halt, EmuTime(0));
@@ -218,6 +412,26 @@ impl GBAEmulator {
 
         let breakpoint_hit = self.debug.breakpoints.binary_search(&next_pc).ok();
 
+        #[cfg(feature = "bin-logging")]
+        if std::mem::take(&mut self.cpu.logger_break) {
+            self.debug.last_hit_breakpoint = Some(debug::Breakpoint::Logger(next_pc));
+            return (vsync, true);
+        }
+
+        #[cfg(feature = "debug-functionality")]
+        if let Some(hit) = self.bus.dma_watch.hit.take() {
+            self.debug.last_hit_breakpoint = Some(debug::Breakpoint::Dma(hit));
+            return (vsync, true);
+        }
+
+        #[cfg(feature = "debug-functionality")]
+        for condition in &mut self.debug.memory_breakpoints {
+            if condition.check(&mut self.bus, &self.cpu) {
+                self.debug.last_hit_breakpoint = Some(debug::Breakpoint::Memory(*condition));
+                return (vsync, true);
+            }
+        }
+
         if matches!(self.debug.break_at_cycle, Some(cycle) if cycle <= self.bus.scheduler.current_time.0) {
             self.debug.break_at_cycle = None;
             self.debug.last_hit_breakpoint = Some(debug::Breakpoint::Cycle(self.bus.scheduler.current_time));
@@ -232,25 +446,123 @@ impl GBAEmulator {
     }
 
     pub fn key_down(&mut self, key: InputKeys) {
+        self.pressed_this_frame.insert(key.into());
+        self.deferred_releases.remove(key.into());
+
         self.bus
             .keypad
             .button_changed(key, true, &mut self.bus.scheduler, &mut self.bus.interrupts);
     }
 
+    /// Release the given key.
+    ///
+    /// If the key was only pressed since the last completed frame the release is delayed until the end of the current
+    /// frame, as the game might otherwise never see a quick tap in between its once-per-frame input polling.
     pub fn key_up(&mut self, key: InputKeys) {
+        if self.pressed_this_frame.contains(key.into()) {
+            self.deferred_releases.insert(key.into());
+            return;
+        }
+
         self.bus
             .keypad
             .button_changed(key, false, &mut self.bus.scheduler, &mut self.bus.interrupts);
     }
 
+    /// Should be called whenever a frame completes.
+    fn finish_frame(&mut self) {
+        self.frame_count += 1;
+        self.apply_deferred_releases();
+
+        if let Some(callback) = &mut self.vblank_callback {
+            callback(&self.bus.ppu.frame_buffer()[..]);
+        }
+    }
+
+    /// Release all keys whose release was delayed by [Self::key_up], should be called whenever a frame completes.
+    fn apply_deferred_releases(&mut self) {
+        self.pressed_this_frame = InputState::empty();
+
+        if self.deferred_releases.is_empty() {
+            return;
+        }
+
+        let held = self.bus.keypad.state() - std::mem::take(&mut self.deferred_releases);
+        self.bus
+            .keypad
+            .set_state(held, &mut self.bus.scheduler, &mut self.bus.interrupts);
+    }
+
+    /// Request the Game Pak interrupt, as an accessory on the cartridge would by pulling its `IREQ` line.
+    ///
+    /// No emulated cartridge uses this, so it's up to the caller to decide when it should fire.
+    pub fn raise_game_pak_interrupt(&mut self) {
+        self.bus
+            .interrupts
+            .request_interrupt(Interrupts::GamePak, &mut self.bus.scheduler);
+    }
+
+    /// Simulate the cartridge being pulled out, which games detect through the Game Pak interrupt.
+    ///
+    /// Only the interrupt is emulated, the cartridge itself stays readable.
+    pub fn simulate_cartridge_removal(&mut self) {
+        log::info!("Simulating cartridge removal");
+        self.raise_game_pak_interrupt();
+    }
+
+    /// Set the state of all buttons at once, which will be applied at the start of the next frame.
+    ///
+    /// Unlike [Self::key_down] and [Self::key_up] this is unaffected by the order in which buttons are pressed or
+    /// released, making it suitable for frame-perfect input in tests.
+    pub fn set_input_state(&mut self, keys: InputState) {
+        self.pending_input = Some(keys);
+    }
+
+    fn apply_pending_input(&mut self) {
+        if let Some(keys) = self.pending_input.take() {
+            // The full keypad state overrides any tap which was still waiting to be released.
+            self.pressed_this_frame = InputState::empty();
+            self.deferred_releases = InputState::empty();
+            self.bus
+                .keypad
+                .set_state(keys, &mut self.bus.scheduler, &mut self.bus.interrupts);
+        }
+    }
+
     pub fn frame_buffer(&mut self) -> &mut RgbaFrame {
         self.bus.ppu.frame_buffer()
     }
+
+    /// Dump the raw contents of VRAM, OAM, and palette RAM to `vram.bin`, `oam.bin`, and `palette.bin` respectively
+    /// in the given `directory`.
+    ///
+    /// Useful for inspecting the PPU state in external tile/sprite viewers.
+    pub fn dump_ppu_memory(&self, directory: impl AsRef<Path>) -> std::io::Result<()> {
+        let directory = directory.as_ref();
+        let ppu = &self.bus.ppu;
+
+        std::fs::create_dir_all(directory)?;
+        std::fs::write(directory.join("vram.bin"), ppu.vram().as_slice())?;
+        std::fs::write(directory.join("oam.bin"), ppu.oam().as_slice())?;
+        std::fs::write(directory.join("palette.bin"), ppu.palette_cache().ram().as_slice())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "debug-functionality")]
+impl Drop for GBAEmulator {
+    fn drop(&mut self) {
+        if let Some(report) = self.bus.unhandled_io_report() {
+            log::info!("{}", report);
+        }
+    }
 }
 
-fn vec_to_bios_data(data: Option<Vec<u8>>) -> Box<BiosData> {
+fn vec_to_bios_data(data: Option<Vec<u8>>) -> Result<Box<BiosData>, CoreError> {
     let data = data.unwrap_or_else(|| vec![0; std::mem::size_of::<BiosData>()]);
-    Box::try_from(data.into_boxed_slice()).unwrap()
+
+    Box::try_from(data.into_boxed_slice()).map_err(|data: Box<[u8]>| CoreError::InvalidBiosSize(data.len()))
 }
 
 #[derive(Debug)]
@@ -264,12 +576,58 @@ pub struct EmuOptions {
     /// `true` if the emulator should run in debug mode.
     /// This will enable breakpoints.
     pub debugging: bool,
+    /// The state to initialise when skipping the BIOS.
+    pub skip_bios_state: BiosSkipState,
+    /// Raise an undefined instruction exception when an unimplemented instruction is executed, instead of panicking.
+    ///
+    /// The offending opcodes are collected in [GBAEmulator::unimplemented_opcodes], which allows fuzzers and batch
+    /// runners to keep going.
+    pub trap_unimplemented: bool,
+    /// The [cpu_log](crate::cpu_log) targets to enable at runtime, defaults to the ones set in
+    /// [LOG_TARGETS_ENV](crate::logging::LOG_TARGETS_ENV).
+    ///
+    /// Note that these are process wide, the most recently created emulator decides which targets are enabled.
+    pub log_targets: LogTargets,
+    /// Force the given backup type, for games where autodetection picks the wrong one.
+    ///
+    /// `None` uses the type detected from the ROM.
+    pub backup_override: Option<CartBackupId>,
+    /// How many times faster the CPU runs relative to the rest of the system, for probing timing sensitive bugs.
+    ///
+    /// The cycles every instruction takes are divided by this, so `2.0` lets the CPU execute twice as many instructions
+    /// per frame. Any value other than `1.0` therefore results in inaccurate timing!
+    ///
+    /// Clamped to [Self::CLOCK_SCALE_RANGE] by [GBAEmulator::new], see [Self::clamp_clock_scale].
+    pub clock_scale: f32,
+    /// Stall CPU accesses to palette RAM, VRAM, and OAM by a cycle while the PPU is drawing, like on hardware.
+    ///
+    /// Can be disabled to rule these stalls out when debugging timing issues.
+    pub ppu_access_stalls: bool,
 }
 
 impl EmuOptions {
+    /// The range [Self::clock_scale] is clamped to.
+    pub const CLOCK_SCALE_RANGE: RangeInclusive<f32> = 0.25..=4.0;
+
+    /// Clamp `clock_scale` to [Self::CLOCK_SCALE_RANGE], with NaN and infinity falling back to `1.0`.
+    ///
+    /// Scaling by `0`, a negative, or a non-finite value would stop or overflow the emulated time.
+    pub fn clamp_clock_scale(clock_scale: f32) -> f32 {
+        if clock_scale.is_finite() {
+            clock_scale.clamp(*Self::CLOCK_SCALE_RANGE.start(), *Self::CLOCK_SCALE_RANGE.end())
+        } else {
+            1.0
+        }
+    }
+
     pub fn should_skip_bios(&self) -> bool {
         self.skip_bios || self.bios.is_none()
     }
+
+    /// Returns the [BiosSkipState] to initialise, if the BIOS should be skipped at all.
+    pub fn bios_skip_state(&self) -> Option<BiosSkipState> {
+        self.should_skip_bios().then_some(self.skip_bios_state)
+    }
 }
 
 impl Default for EmuOptions {
@@ -278,6 +636,319 @@ impl Default for EmuOptions {
             skip_bios: true,
             bios: None,
             debugging: false,
+            skip_bios_state: BiosSkipState::Full,
+            trap_unimplemented: false,
+            log_targets: LogTargets::from_env(),
+            backup_override: None,
+            clock_scale: 1.0,
+            ppu_access_stalls: true,
+        }
+    }
+}
+
+/// The register state the emulator should start with when the BIOS is skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BiosSkipState {
+    /// Initialise the full register state the BIOS leaves behind before jumping to the cartridge.
+    Full,
+    /// Only initialise the stack pointers and `PC`, leaving the `CPSR` in System mode with IRQs disabled.
+    ///
+    /// Mostly useful for diffing logs.
+    Minimal,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::interrupts::{Interrupts, IE_START, IME_START};
+    use crate::emulator::bus::timers::TIMER_IO_START;
+    use crate::emulator::cpu::registers::{Mode, LINK_REG};
+    use crate::emulator::{EmuOptions, GBAEmulator};
+    use crate::scheduler::{EmuTime, EventTag};
+    use crate::InputKeys;
+
+    #[test]
+    fn test_reset_honours_skip_bios() {
+        let mut emu =
+            crate::headless::headless_emulator(vec![0; 0x200], Some(vec![0; 0x4000]), EmuOptions::default()).unwrap();
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0000);
+
+        emu.options.skip_bios = false;
+        emu.reset().unwrap();
+        assert_eq!(emu.cpu.registers.next_pc(), 0);
+
+        emu.options.skip_bios = true;
+        emu.reset().unwrap();
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0000);
+    }
+
+    #[test]
+    fn test_single_frame_key_tap() {
+        // `B .`
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        let a_held = |emu: &GBAEmulator| !emu.bus.keypad.status.button_a();
+
+        // A tap in between two frames should still be visible for the entire next frame.
+        emu.key_down(InputKeys::A);
+        emu.key_up(InputKeys::A);
+        assert!(a_held(&emu));
+
+        emu.run_to_vblank();
+        assert!(!a_held(&emu));
+
+        // Whereas a key held across frames is released immediately.
+        emu.key_down(InputKeys::A);
+        emu.run_to_vblank();
+        emu.key_up(InputKeys::A);
+        assert!(!a_held(&emu));
+
+        // A later full keypad state which holds the key should cancel a pending release.
+        emu.key_down(InputKeys::A);
+        emu.key_up(InputKeys::A);
+        emu.set_input_state(InputKeys::A.into());
+        emu.run_to_vblank();
+        assert!(a_held(&emu));
+    }
+
+    #[test]
+    fn test_frame_finished_while_stepping() {
+        // `B .`
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        let a_held = |emu: &GBAEmulator| !emu.bus.keypad.status.button_a();
+
+        emu.key_down(InputKeys::A);
+        emu.key_up(InputKeys::A);
+
+        while !emu.step_instruction() {}
+
+        // Reaching VBlank through single steps should complete the frame just like `run_to_vblank` does.
+        assert!(!a_held(&emu));
+        assert_eq!(emu.frame_count(), 1);
+    }
+
+    #[test]
+    fn test_clock_scale() {
+        // `B .`, which takes 2 cycles plus the wait states of a sequential fetch (4), and of refilling the pipeline
+        // with a non-sequential and sequential fetch (6 + 4) from ROM.
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+
+        for (clock_scale, expected_cycles) in [(1.0, 1600), (2.0, 800), (4.0, 400), (0.5, 3200)] {
+            let options = EmuOptions {
+                clock_scale,
+                ..Default::default()
+            };
+            let mut emu = crate::headless::headless_emulator(rom.clone(), None, options).unwrap();
+            let start = emu.bus.scheduler.current_time;
+
+            for _ in 0..100 {
+                emu.step_instruction();
+            }
+
+            let elapsed = emu.bus.scheduler.current_time.0 - start.0;
+            assert_eq!(elapsed, expected_cycles, "Clock scale: {}", clock_scale);
+        }
+    }
+
+    #[test]
+    fn test_invalid_clock_scale() {
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+
+        for (clock_scale, expected) in [
+            (0.0, 0.25),
+            (-2.0, 0.25),
+            (100.0, 4.0),
+            (f32::NAN, 1.0),
+            (f32::INFINITY, 1.0),
+        ] {
+            let options = EmuOptions {
+                clock_scale,
+                ..Default::default()
+            };
+            let mut emu = crate::headless::headless_emulator(rom.clone(), None, options).unwrap();
+            assert_eq!(emu.options.clock_scale, expected, "Clock scale: {}", clock_scale);
+
+            // Time should still advance, without overflowing.
+            let start = emu.bus.scheduler.current_time;
+            emu.step_instruction();
+            assert!(emu.bus.scheduler.current_time > start, "Clock scale: {}", clock_scale);
         }
+
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        emu.set_clock_scale(f32::NEG_INFINITY);
+        assert_eq!(emu.options.clock_scale, 1.0);
+    }
+
+    #[test]
+    fn test_run_to_vblank_limited() {
+        // `B .`
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+
+        assert_eq!(emu.run_to_vblank_limited(100), None);
+        assert_eq!(emu.frame_count(), 0);
+        // The partial frame still counts towards reaching the next Vblank.
+        let executed = emu.run_to_vblank_limited(u64::MAX).unwrap();
+        assert_eq!(emu.frame_count(), 1);
+
+        emu.reset().unwrap();
+        assert_eq!(emu.run_to_vblank_limited(u64::MAX), Some(executed + 100));
+    }
+
+    #[test]
+    fn test_invalid_rom_and_bios_size() {
+        use crate::error::CoreError;
+
+        let emu = crate::headless::headless_emulator(vec![0; 0x20], None, EmuOptions::default());
+        assert_eq!(emu.err(), Some(CoreError::InvalidRomSize(0x20)));
+
+        let emu = crate::headless::headless_emulator(vec![0; 0x200], Some(vec![0; 0x100]), EmuOptions::default());
+        assert_eq!(emu.err(), Some(CoreError::InvalidBiosSize(0x100)));
+
+        // Resetting with an invalid BIOS shouldn't affect the running emulator.
+        let mut emu = crate::headless::headless_emulator(vec![0; 0x200], None, EmuOptions::default()).unwrap();
+        emu.options.bios = Some(vec![0; 0x100]);
+        assert_eq!(emu.reset(), Err(CoreError::InvalidBiosSize(0x100)));
+        assert_eq!(emu.cpu.registers.next_pc(), 0x0800_0000);
+    }
+
+    #[test]
+    fn test_interrupt_latency() {
+        // MOV r0, r0
+        let mut emu = crate::emulator::cpu::test_utils::emu_with_arm(&[0xE1A0_0000; 4]);
+        emu.bus.write_16(IE_START, Interrupts::Vblank as u16);
+        emu.bus.write_16(IME_START, 1);
+        // Nothing is pending yet, so this should just execute the first instruction.
+        emu.step_instruction();
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::System);
+
+        let requested_at = emu.bus.scheduler.current_time;
+        emu.bus
+            .interrupts
+            .request_interrupt(Interrupts::Vblank, &mut emu.bus.scheduler);
+        emu.step_instruction();
+
+        // The IRQ should be taken right after the instruction which was executing when it was requested, which only
+        // paid for its sequential fetch from ROM.
+        assert_eq!(emu.bus.scheduler.current_time - requested_at, EmuTime(2 + 4));
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(emu.cpu.registers.next_pc(), 0x18);
+        // `SUBS pc, lr, #4` returns to the instruction after the one that was interrupted.
+        assert_eq!(emu.cpu.registers.general_purpose[LINK_REG], 0x0800_000C);
+    }
+
+    #[test]
+    fn test_game_pak_interrupt() {
+        // B . (infinite loop)
+        let mut emu = crate::emulator::cpu::test_utils::emu_with_arm(&[0xEAFF_FFFE]);
+        emu.bus.write_16(IE_START, Interrupts::GamePak as u16);
+        emu.bus.write_16(IME_START, 1);
+
+        // Should never fire on its own.
+        emu.run_to_vblank();
+        assert!(!emu.bus.interrupts.flags.game_pak());
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::System);
+
+        emu.simulate_cartridge_removal();
+        emu.step_instruction();
+
+        assert!(emu.bus.interrupts.flags.game_pak());
+        assert_eq!(emu.cpu.registers.cpsr.mode(), Mode::IRQ);
+        assert_eq!(emu.cpu.registers.next_pc(), 0x18);
+    }
+
+    #[test]
+    fn test_co_scheduled_vblank_and_timer() {
+        // MOV r0, r0
+        let mut emu = crate::emulator::cpu::test_utils::emu_with_arm(&[0xE1A0_0000; 4]);
+        // Enable timer 0 with an IRQ on overflow.
+        emu.bus.write_16(TIMER_IO_START + 2, 0xC0);
+
+        emu.bus.scheduler.clear_events();
+        let timestamp = emu.bus.scheduler.current_time + EmuTime(2);
+        emu.bus.scheduler.schedule_event(EventTag::Timer0Irq, timestamp);
+        emu.bus.scheduler.schedule_event(EventTag::VBlank, timestamp);
+
+        assert!(emu.step_instruction());
+        // The timer shouldn't have to wait for the next instruction just because VBlank came first.
+        assert!(emu.bus.interrupts.flags.timer_0());
+        assert!(emu
+            .bus
+            .scheduler
+            .pending_events()
+            .all(|ev| ev.timestamp > emu.bus.scheduler.current_time));
+    }
+
+    #[test]
+    fn test_vblank_callback() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        let frames = Arc::new(AtomicUsize::new(0));
+        let counter = frames.clone();
+
+        emu.on_vblank(Box::new(move |frame| {
+            assert_eq!(frame.len(), crate::FRAMEBUFFER_SIZE);
+            counter.fetch_add(1, Ordering::Relaxed);
+        }));
+        emu.run_to_vblank();
+        emu.run_to_vblank();
+        assert_eq!(frames.load(Ordering::Relaxed), 2);
+
+        assert!(emu.clear_on_vblank().is_some());
+        emu.run_to_vblank();
+        assert_eq!(frames.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-functionality")]
+    fn test_memory_breakpoint_on_rising_edge() {
+        use crate::emulator::debug::{Breakpoint, DebugEmulator, MemoryCondition};
+
+        // STR r1, [r0]; B .
+        let mut rom = 0xE580_1000u32.to_le_bytes().to_vec();
+        rom.extend(0xEAFF_FFFEu32.to_le_bytes().repeat(0x80));
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        emu.cpu.registers.general_purpose[0] = 0x0300_0000;
+        emu.cpu.registers.general_purpose[1] = 0xABCD_1234;
+
+        let condition = MemoryCondition::new(0x0300_0000, 0xFFFF, 0x1234);
+        DebugEmulator(&mut emu).set_memory_breakpoints(vec![condition]);
+
+        assert!(emu.step_instruction_debug().1);
+        assert!(matches!(emu.debug.last_hit_breakpoint, Some(Breakpoint::Memory(hit)) if hit.address == 0x0300_0000));
+        // Still holds, but shouldn't break again until it has stopped holding.
+        assert!(!emu.step_instruction_debug().1);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-functionality")]
+    fn test_dma_watchpoint() {
+        use crate::emulator::debug::{Breakpoint, DebugEmulator, DmaAccess, DmaWatchHit, DmaWatchpoint};
+
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        DebugEmulator(&mut emu).set_dma_watchpoints(vec![DmaWatchpoint {
+            range: 0x0300_0014..=0x0300_0017,
+            access: DmaAccess::Write,
+        }]);
+        // DMA3 three words from IWRAM to IWRAM
+        emu.bus.write_32(0x0400_00D4, 0x0300_0000);
+        emu.bus.write_32(0x0400_00D8, 0x0300_0010);
+        emu.bus.write_32(0x0400_00DC, 0x8400_0003);
+
+        emu.bus.on_dma_start(&emu.cpu, 3);
+
+        assert!(emu.step_instruction_debug().1);
+        let expected = DmaWatchHit {
+            channel: 3,
+            offset: 4,
+            address: 0x0300_0014,
+            access: DmaAccess::Write,
+        };
+        assert!(matches!(emu.debug.last_hit_breakpoint, Some(Breakpoint::Dma(hit)) if hit == expected));
+        assert!(!emu.step_instruction_debug().1);
     }
 }
@@ -1,9 +1,47 @@
 //! All debug related functionality for the PPU
 use crate::emulator::bus::IO_START;
-use crate::emulator::ppu::PPU;
+use crate::emulator::ppu::{
+    get_vram_address, DebugLayerMask, PaletteIndex, ScanlineBuffer, DISPLAY_WIDTH, FRAMEBUFFER_SIZE, PPU,
+};
 use crate::emulator::MemoryAddress;
 
 impl PPU {
+    /// The current rendering overrides, see [DebugLayerMask].
+    pub fn debug_layer_mask(&self) -> DebugLayerMask {
+        self.debug_layers
+    }
+
+    /// Override which layers are rendered, and in which mode, without touching the game's `DISPCNT`.
+    pub fn set_debug_layer_mask(&mut self, mask: DebugLayerMask) {
+        self.debug_layers = mask;
+    }
+
+    /// The palette indexes of the entire frame, as rendered before being converted to colours.
+    ///
+    /// Index `0` is transparent, so any pixel which ends up as the backdrop is `0`.
+    /// Mode 3 bypasses palette indexes altogether, and therefore leaves its scanlines at `0`.
+    ///
+    /// Any deferred scanlines are rendered first, like [Self::frame_buffer].
+    pub fn palette_index_buffer(&mut self) -> &[PaletteIndex; FRAMEBUFFER_SIZE as usize] {
+        self.flush_scanlines();
+        &self.palette_index_buffer
+    }
+
+    /// The palette indexes of a single scanline, see [Self::palette_index_buffer].
+    pub fn scanline_palette_indices(&mut self, scanline: usize) -> &[PaletteIndex] {
+        self.flush_scanlines();
+        let start = scanline * DISPLAY_WIDTH as usize;
+
+        &self.palette_index_buffer[start..start + DISPLAY_WIDTH as usize]
+    }
+
+    /// Save the palette indexes of the given scanline, which was just rendered.
+    pub(super) fn capture_palette_indices(&mut self, line: u8, scanline: &ScanlineBuffer) {
+        let start = line as usize * DISPLAY_WIDTH as usize;
+
+        self.palette_index_buffer[start..start + DISPLAY_WIDTH as usize].copy_from_slice(scanline);
+    }
+
     /// Debug read from PPU Io memory, necessary due to the fact that most PPU registers are write only.
     #[inline]
     pub fn read_io_dbg(&mut self, address: MemoryAddress) -> u8 {
@@ -54,9 +92,26 @@ impl PPU {
         }
     }
 
+    /// Debug write to PPU Io memory, which unlike [Self::write_io] can also force the read-only `DISPSTAT` flags and
+    /// `VCOUNT`.
+    ///
+    /// Note that the PPU will simply continue from the forced scanline.
+    #[inline]
+    pub fn write_io_dbg(&mut self, address: MemoryAddress, value: u8) {
+        let addr = address as usize;
+
+        match address - IO_START {
+            0x4..=0x5 => self.disp_stat.update_byte_le(addr % 2, value),
+            0x6..=0x7 => self.vertical_counter.update_byte_le(addr % 2, value),
+            _ => self.write_io(address, value),
+        }
+    }
+
     #[inline]
     pub fn write_vram_dbg(&mut self, address: MemoryAddress, value: u8) {
-        let addr = (address & 0x1FFFF) as usize;
+        self.flush_scanlines();
+
+        let addr = get_vram_address(address);
 
         self.vram[addr] = value;
     }
@@ -1,4 +1,7 @@
 use crate::emulator::bus::IO_START;
+use crate::emulator::ppu::registers::{
+    AlphaBlendCoefficients, BgControl, ColorSpecialSelection, LcdControl, LcdStatus, WindowControl,
+};
 use crate::emulator::ppu::{OAM_RAM_SIZE, PPU, VRAM_SIZE};
 use crate::emulator::MemoryAddress;
 use crate::utils::BitOps;
@@ -11,6 +14,11 @@ pub const OAM_START: MemoryAddress = 0x0700_0000;
 pub const OAM_END: MemoryAddress = 0x0700_03FF;
 pub const LCD_IO_END: MemoryAddress = 0x4000056;
 
+/// The (exclusive) end of BG VRAM in the tile modes (0..=2), everything after is OBJ VRAM.
+const TILE_BG_VRAM_END: usize = 0x1_0000;
+/// The (exclusive) end of BG VRAM in the bitmap modes (3..=5), everything after is OBJ VRAM.
+const BITMAP_BG_VRAM_END: usize = 0x1_4000;
+
 impl PPU {
     #[inline]
     pub fn read_io(&mut self, address: MemoryAddress) -> u8 {
@@ -36,7 +44,10 @@ impl PPU {
             0x4A..=0x4B => self.window_control_outside.to_le_bytes()[addr % 2],
             0x50..=0x51 => self.bld_cnt.to_le_bytes()[addr % 2],
             0x52..=0x53 => self.alpha.to_le_bytes()[addr % 2],
-            0x54..=0x55 => self.brightness.to_le_bytes()[addr % 2],
+            0x54..=0x55 => {
+                // Brightness is write-only TODO: Open bus read
+                0xFF
+            }
             _ => {
                 // TODO: Open bus read
                 crate::cpu_log!("ppu-logging"; "Unimplemented IO read at {:08X}", address);
@@ -51,14 +62,24 @@ impl PPU {
         // Note that IO is not mirrored, therefore a subtract instead of a modulo
         let address = address - IO_START;
         match address {
-            0x0..=0x1 => self.disp_cnt.update_byte_le(addr % 2, value),
+            0x0..=0x1 => write_masked(&mut self.disp_cnt, addr % 2, value, LcdControl::WRITABLE_BITS),
             0x2..=0x3 => self.green_swap = self.green_swap.change_byte_le(addr % 2, value),
-            0x4..=0x5 => self.disp_stat.update_byte_le(addr % 2, value),
+            0x4..=0x5 => write_masked(&mut self.disp_stat, addr % 2, value, LcdStatus::WRITABLE_BITS),
             0x6..=0x7 => {
-                // Vertical counter is read only
+                // Vertical counter is read only, the debugger can still force it through `write_io_dbg`.
             }
-            0x8..=0x9 => self.bg_control[0].update_byte_le(addr % 2, value),
-            0xA..=0xB => self.bg_control[1].update_byte_le(addr % 2, value),
+            0x8..=0x9 => write_masked(
+                &mut self.bg_control[0],
+                addr % 2,
+                value,
+                BgControl::WRITABLE_BITS_BG0_BG1,
+            ),
+            0xA..=0xB => write_masked(
+                &mut self.bg_control[1],
+                addr % 2,
+                value,
+                BgControl::WRITABLE_BITS_BG0_BG1,
+            ),
             0xC..=0xD => self.bg_control[2].update_byte_le(addr % 2, value),
             0xE..=0xF => self.bg_control[3].update_byte_le(addr % 2, value),
             0x10..=0x11 => self.bg_scrolling[0].x.update_byte_le(addr % 2, value),
@@ -73,23 +94,45 @@ impl PPU {
             0x22..=0x23 => self.bg_rotation_reference_bg2[1].update_byte_le(addr % 2, value),
             0x24..=0x25 => self.bg_rotation_reference_bg2[2].update_byte_le(addr % 2, value),
             0x26..=0x27 => self.bg_rotation_reference_bg2[3].update_byte_le(addr % 2, value),
-            0x28..=0x2B => self.bg_rotation_x[0].update_byte_le(addr % 4, value),
-            0x2C..=0x2F => self.bg_rotation_y[0].update_byte_le(addr % 4, value),
+            0x28..=0x2B => {
+                self.bg_rotation_x[0].update_byte_le(addr % 4, value);
+                self.bg_internal_x[0] = self.bg_rotation_x[0].fixed_point();
+            }
+            0x2C..=0x2F => {
+                self.bg_rotation_y[0].update_byte_le(addr % 4, value);
+                self.bg_internal_y[0] = self.bg_rotation_y[0].fixed_point();
+            }
             0x30..=0x31 => self.bg_rotation_reference_bg3[0].update_byte_le(addr % 2, value),
             0x32..=0x33 => self.bg_rotation_reference_bg3[1].update_byte_le(addr % 2, value),
             0x34..=0x35 => self.bg_rotation_reference_bg3[2].update_byte_le(addr % 2, value),
             0x36..=0x37 => self.bg_rotation_reference_bg3[3].update_byte_le(addr % 2, value),
-            0x38..=0x3B => self.bg_rotation_x[1].update_byte_le(addr % 4, value),
-            0x3C..=0x3F => self.bg_rotation_y[1].update_byte_le(addr % 4, value),
+            0x38..=0x3B => {
+                self.bg_rotation_x[1].update_byte_le(addr % 4, value);
+                self.bg_internal_x[1] = self.bg_rotation_x[1].fixed_point();
+            }
+            0x3C..=0x3F => {
+                self.bg_rotation_y[1].update_byte_le(addr % 4, value);
+                self.bg_internal_y[1] = self.bg_rotation_y[1].fixed_point();
+            }
             0x40..=0x41 => self.window_horizontal[0].update_byte_le(addr % 2, value),
             0x42..=0x43 => self.window_horizontal[1].update_byte_le(addr % 2, value),
             0x44..=0x45 => self.window_vertical[0].update_byte_le(addr % 2, value),
             0x46..=0x47 => self.window_vertical[1].update_byte_le(addr % 2, value),
-            0x48..=0x49 => self.window_control_inside.update_byte_le(addr % 2, value),
-            0x4A..=0x4B => self.window_control_outside.update_byte_le(addr % 2, value),
+            0x48..=0x49 => write_masked(
+                &mut self.window_control_inside,
+                addr % 2,
+                value,
+                WindowControl::WRITABLE_BITS,
+            ),
+            0x4A..=0x4B => write_masked(
+                &mut self.window_control_outside,
+                addr % 2,
+                value,
+                WindowControl::WRITABLE_BITS,
+            ),
             0x4C..=0x4F => self.mosaic_function.update_byte_le(addr % 4, value),
-            0x50..=0x51 => self.bld_cnt.update_byte_le(addr % 2, value),
-            0x52..=0x53 => self.alpha.update_byte_le(addr % 2, value),
+            0x50..=0x51 => write_masked(&mut self.bld_cnt, addr % 2, value, ColorSpecialSelection::WRITABLE_BITS),
+            0x52..=0x53 => write_masked(&mut self.alpha, addr % 2, value, AlphaBlendCoefficients::WRITABLE_BITS),
             0x54..=0x55 => self.brightness.update_byte_le(addr % 2, value),
             0x56 => {
                 // not used
@@ -105,11 +148,13 @@ impl PPU {
 
     #[inline]
     pub fn write_palette(&mut self, address: MemoryAddress, value: u8) {
+        self.flush_scanlines();
         self.palette.write_palette(address, value);
     }
 
     #[inline]
     pub fn write_palette_16(&mut self, address: MemoryAddress, value: u16) {
+        self.flush_scanlines();
         self.palette.write_palette_16(address, value);
     }
 
@@ -122,8 +167,14 @@ impl PPU {
 
     #[inline]
     pub fn write_vram(&mut self, address: MemoryAddress, value: u8) {
-        // When writing to vram ram with only a u8 the value is written to both the upper and lower bytes.
-        //TODO: Potentially ignore 8 bit writes to OBJ (6010000h-6017FFFh) (or 6014000h-6017FFFh in Bitmap mode)
+        // 8 bit writes to OBJ VRAM are ignored, where OBJ VRAM starts later in the bitmap modes.
+        let bg_vram_end = if self.is_bitmap_mode() { BITMAP_BG_VRAM_END } else { TILE_BG_VRAM_END };
+
+        if get_vram_address(address) >= bg_vram_end {
+            return;
+        }
+
+        // When writing to BG VRAM with only a u8 the value is written to both the upper and lower bytes.
         let final_value = ((value as u16) << 8) | value as u16;
 
         self.write_vram_16(address, final_value);
@@ -131,7 +182,10 @@ impl PPU {
 
     #[inline]
     pub fn write_vram_16(&mut self, address: MemoryAddress, value: u16) {
-        let addr = get_vram_address(address);
+        self.flush_scanlines();
+
+        // Not every caller aligns the address, masking it ensures the second byte can't go past the end of VRAM.
+        let addr = get_vram_address(address & !1);
         let data = value.to_le_bytes();
         // Better assembly
         assert!(addr < (VRAM_SIZE - 1));
@@ -151,8 +205,19 @@ impl PPU {
     }
 }
 
+/// Write `value` to the little endian `byte` of the given `register`, leaving all bits not set in `writable` untouched.
 #[inline(always)]
-fn get_vram_address(address: MemoryAddress) -> usize {
+fn write_masked<R: Copy + From<u16> + Into<u16>>(register: &mut R, byte: usize, value: u8, writable: u16) {
+    let current: u16 = (*register).into();
+
+    *register = R::from(current.change_byte_le_masked(byte, value, writable));
+}
+
+/// Translate the given `address` to an index into VRAM, taking into account all mirrors.
+///
+/// The result is always a valid index for VRAM.
+#[inline(always)]
+pub(crate) fn get_vram_address(address: MemoryAddress) -> usize {
     // VRAM mirroring is awkward at 64KB + 32KB + 32KB, where the 32KB are mirrors of each other.
     let mut addr = (address & 0x1FFFF) as usize;
 
@@ -162,3 +227,174 @@ fn get_vram_address(address: MemoryAddress) -> usize {
 
     addr
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::IO_START;
+    use crate::emulator::ppu::{get_vram_address, PPU, VRAM_SIZE};
+
+    #[test]
+    fn test_vram_mirroring() {
+        let mut ppu = PPU::new();
+
+        // The upper 32KB (OBJ VRAM) is mirrored within the upper 64KB of each 128KB block.
+        assert_eq!(get_vram_address(0x0601_7FFF), 0x1_7FFF);
+        assert_eq!(get_vram_address(0x0601_8000), 0x1_0000);
+        assert_eq!(get_vram_address(0x0601_FFFF), 0x1_7FFF);
+        // The full 128KB block is mirrored throughout the memory region.
+        assert_eq!(get_vram_address(0x0602_0000), 0x0);
+        assert_eq!(get_vram_address(0x06FF_FFFF), 0x1_7FFF);
+
+        ppu.write_vram_16(0x0601_8000, 0xBEEF);
+        assert_eq!(ppu.read_vram(0x0601_0000), 0xEF);
+        assert_eq!(ppu.read_vram(0x0601_0001), 0xBE);
+
+        // Unaligned writes at the very end of VRAM shouldn't write out of bounds.
+        ppu.write_vram_16(0x0601_FFFF, 0xFEED);
+        assert_eq!(ppu.read_vram(0x0601_7FFE), 0xED);
+        assert_eq!(ppu.read_vram(0x0601_7FFF), 0xFE);
+        assert_eq!(ppu.read_vram((0x0600_0000 + VRAM_SIZE - 1) as u32), 0xFE);
+    }
+
+    #[test]
+    fn test_palette_mirroring() {
+        let mut ppu = PPU::new();
+
+        ppu.write_palette_16(0x0500_0400, 0x7FFF);
+        assert_eq!(ppu.read_palette(0x0500_0000), 0xFF);
+        assert_eq!(ppu.read_palette(0x0500_0001), 0x7F);
+
+        // Unaligned writes at the very end of palette RAM shouldn't write out of bounds.
+        ppu.write_palette_16(0x0500_07FF, 0x1234);
+        assert_eq!(ppu.read_palette(0x0500_03FE), 0x34);
+        assert_eq!(ppu.read_palette(0x0500_03FF), 0x12);
+        assert_eq!(ppu.read_palette(0x05FF_FFFF), 0x12);
+    }
+
+    #[test]
+    fn test_oam_mirroring() {
+        let mut ppu = PPU::new();
+
+        ppu.write_oam_16(0x0700_0400, 0xBEEF);
+        assert_eq!(ppu.read_oam(0x0700_0000), 0xEF);
+        assert_eq!(ppu.read_oam(0x0700_0001), 0xBE);
+
+        // Unaligned writes at the very end of OAM shouldn't write out of bounds.
+        ppu.write_oam_16(0x0700_07FF, 0x1234);
+        assert_eq!(ppu.read_oam(0x0700_03FE), 0x34);
+        assert_eq!(ppu.read_oam(0x0700_03FF), 0x12);
+        assert_eq!(ppu.read_oam(0x07FF_FFFF), 0x12);
+    }
+
+    #[test]
+    fn test_vram_byte_writes() {
+        let mut ppu = PPU::new();
+
+        // Byte writes to BG VRAM write the value to both bytes of the halfword.
+        ppu.write_vram(0x0600_0001, 0xAB);
+        assert_eq!(ppu.read_vram(0x0600_0000), 0xAB);
+        assert_eq!(ppu.read_vram(0x0600_0001), 0xAB);
+
+        // Byte writes to OBJ VRAM are ignored.
+        ppu.write_vram(0x0601_0000, 0xAB);
+        assert_eq!(ppu.read_vram(0x0601_0000), 0x00);
+        assert_eq!(ppu.read_vram(0x0601_0001), 0x00);
+
+        // Including when accessed through the mirror
+        ppu.write_vram(0x0601_8000, 0xAB);
+        assert_eq!(ppu.read_vram(0x0601_0000), 0x00);
+
+        // In bitmap modes BG VRAM extends to 0x0601_3FFF
+        ppu.write_io(0x0400_0000, 0x3);
+        ppu.write_vram(0x0601_3FFE, 0xCD);
+        assert_eq!(ppu.read_vram(0x0601_3FFE), 0xCD);
+        assert_eq!(ppu.read_vram(0x0601_3FFF), 0xCD);
+
+        ppu.write_vram(0x0601_4000, 0xCD);
+        assert_eq!(ppu.read_vram(0x0601_4000), 0x00);
+    }
+
+    #[test]
+    fn test_palette_byte_writes() {
+        let mut ppu = PPU::new();
+
+        // Byte writes write the value to both bytes of the halfword, regardless of which byte was addressed.
+        ppu.write_palette(0x0500_0003, 0x1F);
+        assert_eq!(ppu.read_palette(0x0500_0002), 0x1F);
+        assert_eq!(ppu.read_palette(0x0500_0003), 0x1F);
+
+        // The colour cache should reflect the full halfword (0x1F1F).
+        let palette = ppu.palette_cache().get_palette(1);
+        assert_eq!((palette.red, palette.green, palette.blue), (255, 198, 57));
+        // The neighbouring entries should be unaffected.
+        assert_eq!(ppu.read_palette(0x0500_0001), 0x00);
+        assert_eq!(ppu.read_palette(0x0500_0004), 0x00);
+    }
+
+    #[test]
+    fn test_lcd_status_read_only_bits() {
+        let mut ppu = PPU::new();
+        ppu.disp_stat.set_v_blank_flag(true);
+
+        // Attempting to clear the VBlank flag, or setting the unused bits, should be ignored.
+        ppu.write_io(0x0400_0004, 0b1100_0000);
+        assert_eq!(ppu.read_io(0x0400_0004), 0b0000_0001);
+
+        // Attempting to set the HBlank and V-Counter flags should be ignored, but the IRQ enables are writable.
+        ppu.write_io(0x0400_0004, 0b0011_1110);
+        assert_eq!(ppu.read_io(0x0400_0004), 0b0011_1001);
+
+        // LYC is fully writable
+        ppu.write_io(0x0400_0005, 0xAB);
+        assert_eq!(ppu.read_io(0x0400_0005), 0xAB);
+        assert!(ppu.disp_stat.v_blank_flag());
+        assert!(!ppu.disp_stat.h_blank_flag());
+        assert!(!ppu.disp_stat.v_counter_flag());
+    }
+
+    #[test]
+    fn test_unused_bits_read_as_zero() {
+        let mut ppu = PPU::new();
+
+        // Bit 13 of BG0CNT/BG1CNT is unused, while it's used for BG2CNT/BG3CNT
+        ppu.write_io(0x0400_0009, 0xFF);
+        ppu.write_io(0x0400_000D, 0xFF);
+        assert_eq!(ppu.read_io(0x0400_0009), 0xDF);
+        assert_eq!(ppu.read_io(0x0400_000D), 0xFF);
+
+        ppu.write_io(0x0400_0048, 0xFF);
+        ppu.write_io(0x0400_0051, 0xFF);
+        ppu.write_io(0x0400_0052, 0xFF);
+        assert_eq!(ppu.read_io(0x0400_0048), 0x3F);
+        assert_eq!(ppu.read_io(0x0400_0051), 0x3F);
+        assert_eq!(ppu.read_io(0x0400_0052), 0x1F);
+    }
+
+    #[test]
+    fn test_read_only_lcd_writes() {
+        let mut ppu = PPU::new();
+        ppu.vertical_counter.set_current_scanline(12);
+
+        // DISPSTAT, where only the IRQ enables and the LYC setting are writable.
+        ppu.write_io(IO_START + 4, 0xFF);
+        ppu.write_io(IO_START + 5, 0x20);
+        // VCOUNT
+        ppu.write_io(IO_START + 6, 0x50);
+        ppu.write_io(IO_START + 7, 0x01);
+
+        assert_eq!(ppu.read_io(IO_START + 4), 0x38);
+        assert_eq!(ppu.read_io(IO_START + 5), 0x20);
+        assert_eq!(ppu.read_io(IO_START + 6), 12);
+        assert_eq!(ppu.read_io(IO_START + 7), 0);
+
+        // The debugger is allowed to override both.
+        #[cfg(feature = "debug-functionality")]
+        {
+            ppu.write_io_dbg(IO_START + 4, 0x07);
+            ppu.write_io_dbg(IO_START + 6, 0x50);
+
+            assert_eq!(ppu.read_io(IO_START + 4), 0x07);
+            assert_eq!(ppu.read_io(IO_START + 6), 0x50);
+        }
+    }
+}
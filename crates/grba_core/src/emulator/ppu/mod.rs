@@ -24,6 +24,10 @@ pub const HBLANK_CYCLES: u32 = 272;
 pub const SCANLINE_CYCLES: u32 = HDRAW_CYCLES + HBLANK_CYCLES;
 pub const VBLANK_CYCLES: u32 = 83776;
 pub const FRAME_CYCLES: u32 = 280896;
+/// The amount of scanlines in a frame, `VCOUNT` wraps back to `0` after the last one.
+pub const TOTAL_SCANLINES: u32 = 228;
+/// The `VBLANK` flag is already cleared on the final scanline, even though no drawing happens until the wrap.
+pub const VBLANK_END_SCANLINE: u32 = TOTAL_SCANLINES - 1;
 
 // 15 bit colour
 // 96KB of VRAM
@@ -54,11 +58,67 @@ pub struct BgScrollingCollection {
     pub y: BgScrolling,
 }
 
+/// Rendering overrides applied on top of `DISPCNT` for debugging purposes.
+///
+/// These are only consulted during rendering, and thus never change the registers the game sees.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DebugLayerMask {
+    /// Backgrounds `0..=3` which should not be rendered, regardless of `DISPCNT`.
+    pub hidden_bgs: [bool; 4],
+    /// Whether objects should not be rendered.
+    ///
+    /// Objects aren't rendered yet, so this currently has no effect.
+    pub hide_obj: bool,
+    /// Render with the given mode instead of the one set in `DISPCNT`.
+    pub forced_bg_mode: Option<BgMode>,
+}
+
+/// The palette indexes of a single scanline, before they're converted to colours.
+pub(crate) type ScanlineBuffer = [PaletteIndex; DISPLAY_WIDTH as usize];
+
+/// A snapshot of the registers which determine how a single scanline is rendered.
+///
+/// Allows a scanline to be rendered after the fact, while the registers might already have been changed for the next.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ScanlineState {
+    pub line: u8,
+    pub disp_cnt: LcdControl,
+    pub bg_control: [BgControl; 4],
+    pub bg_scrolling: [BgScrollingCollection; 4],
+    /// The internal reference point of `BG2` at the start of this scanline, as `19.8` fixed point `(x, y)`.
+    pub bg2_reference: (i32, i32),
+    /// `PA` and `PC` of `BG2`, the step through the background for every pixel to the right.
+    pub bg2_pixel_step: (i32, i32),
+    pub debug_layers: DebugLayerMask,
+}
+
+impl ScanlineState {
+    /// The mode to render with, taking into account [DebugLayerMask::forced_bg_mode].
+    #[inline]
+    fn bg_mode(&self) -> BgMode {
+        self.debug_layers
+            .forced_bg_mode
+            .unwrap_or_else(|| self.disp_cnt.bg_mode())
+    }
+
+    /// Whether the given background should be rendered, taking into account both `DISPCNT` and the [DebugLayerMask].
+    #[inline]
+    fn bg_enabled(&self, bg: usize) -> bool {
+        let enabled = match bg {
+            0 => self.disp_cnt.screen_display_bg0(),
+            1 => self.disp_cnt.screen_display_bg1(),
+            2 => self.disp_cnt.screen_display_bg2(),
+            _ => self.disp_cnt.screen_display_bg3(),
+        };
+
+        enabled && !self.debug_layers.hidden_bgs[bg]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PPU {
     // Ram
     frame_buffer: RgbaFrame,
-    current_scanline: Box<[PaletteIndex; DISPLAY_WIDTH as usize]>,
     palette: PaletteRam,
     oam_ram: OamRam,
     vram: Box<[u8; VRAM_SIZE]>,
@@ -76,6 +136,12 @@ pub struct PPU {
     /// The background rotation references, where `[0]` is `BG2`, and `[1]` is `BG3`
     bg_rotation_x: [BgRotationParam; 2],
     bg_rotation_y: [BgRotationParam; 2],
+    /// The internal reference points used while drawing, where `[0]` is `BG2`, and `[1]` is `BG3`.
+    ///
+    /// Reloaded from `bg_rotation_x`/`bg_rotation_y` at VBlank, or immediately when either is written mid-frame.
+    /// After every drawn scanline they're advanced by `PB` and `PD` respectively.
+    bg_internal_x: [i32; 2],
+    bg_internal_y: [i32; 2],
     /// Internal background rotation/scaling for `BG2`
     ///
     /// Where the indexes correspond to the registers in the following way:
@@ -102,13 +168,24 @@ pub struct PPU {
     bld_cnt: ColorSpecialSelection,
     alpha: AlphaBlendCoefficients,
     brightness: BrightnessCoefficients,
+
+    debug_layers: DebugLayerMask,
+    /// Whether CPU accesses to PPU memory are stalled while a scanline is being drawn, see [PPU::access_stall_cycles].
+    pub access_stalls: bool,
+    /// The palette indexes of every scanline of the current frame, captured right before conversion to colours.
+    #[cfg(feature = "debug-functionality")]
+    palette_index_buffer: Box<[PaletteIndex; FRAMEBUFFER_SIZE as usize]>,
+    /// Whether scanlines are deferred until they can be rendered in parallel, see [PPU::render_pending_scanlines].
+    #[cfg(feature = "parallel-rendering")]
+    pub parallel_rendering: bool,
+    #[cfg(feature = "parallel-rendering")]
+    pending_scanlines: Vec<ScanlineState>,
 }
 
 impl PPU {
     pub fn new() -> Self {
         PPU {
             frame_buffer: RgbaFrame::default(),
-            current_scanline: crate::box_array![0; DISPLAY_WIDTH as usize],
             palette: PaletteRam::default(),
             oam_ram: OamRam::default(),
             vram: crate::box_array![0; VRAM_SIZE],
@@ -120,8 +197,10 @@ impl PPU {
             bg_scrolling: [BgScrollingCollection::default(); 4],
             bg_rotation_x: [BgRotationParam::new(); 2],
             bg_rotation_y: [BgRotationParam::new(); 2],
-            bg_rotation_reference_bg2: [BgRotationRef::new(); 4],
-            bg_rotation_reference_bg3: [BgRotationRef::new(); 4],
+            bg_internal_x: [0; 2],
+            bg_internal_y: [0; 2],
+            bg_rotation_reference_bg2: identity_rotation(),
+            bg_rotation_reference_bg3: identity_rotation(),
             window_horizontal: [WindowDimensions::new(); 2],
             window_vertical: [WindowDimensions::new(); 2],
             window_control_inside: WindowControl::new(),
@@ -130,6 +209,14 @@ impl PPU {
             bld_cnt: ColorSpecialSelection::new(),
             alpha: AlphaBlendCoefficients::new(),
             brightness: BrightnessCoefficients::new(),
+            debug_layers: DebugLayerMask::default(),
+            access_stalls: true,
+            #[cfg(feature = "debug-functionality")]
+            palette_index_buffer: crate::box_array![0; FRAMEBUFFER_SIZE as usize],
+            #[cfg(feature = "parallel-rendering")]
+            parallel_rendering: true,
+            #[cfg(feature = "parallel-rendering")]
+            pending_scanlines: Vec::with_capacity(DISPLAY_HEIGHT as usize),
         }
     }
 
@@ -150,6 +237,7 @@ impl PPU {
         // Render a scanline if we're not yet at the final line
         if self.vertical_counter.current_scanline() < DISPLAY_HEIGHT as u8 {
             self.render_scanline();
+            self.advance_affine_references();
         }
 
         scheduler.schedule_relative(EventTag::HBlankEnd, EmuTime::from(HBLANK_CYCLES));
@@ -159,24 +247,16 @@ impl PPU {
         crate::cpu_log!("ppu-logging"; "HBlankEnd fired!");
         self.disp_stat.set_h_blank_flag(false);
 
-        self.vertical_counter
-            .set_current_scanline(self.vertical_counter.current_scanline() + 1);
-
-        // For handling VBlank ending (due to the fact that we increment the vertical_counter here putting this on the
-        // scheduler is more difficult.
-        if self.vertical_counter.current_scanline() == 227 {
-            // Vblank is no longer set one hblank before the wrap-around
-            self.disp_stat.set_v_blank_flag(false);
-        } else if self.vertical_counter.current_scanline() == 228 {
-            // Reached the end of vblank, time to reset the scanline counter
-            self.vertical_counter.set_current_scanline(0);
-        }
+        let next_scanline = (self.vertical_counter.current_scanline() as u32 + 1) % TOTAL_SCANLINES;
+        self.vertical_counter.set_current_scanline(next_scanline as u8);
 
         self.check_vertical_counter_interrupt(scheduler, interrupts);
 
-        if self.vertical_counter.current_scanline() == DISPLAY_HEIGHT as u8 {
-            // Next up is vblank
-            scheduler.schedule_relative(EventTag::VBlank, EmuTime::from(0u32));
+        // Both VBlank edges happen at the very start of their scanline, at the same time as the `VCOUNT` update.
+        match next_scanline {
+            DISPLAY_HEIGHT => scheduler.schedule_relative(EventTag::VBlank, EmuTime::from(0u32)),
+            VBLANK_END_SCANLINE => scheduler.schedule_relative(EventTag::VBlankEnd, EmuTime::from(0u32)),
+            _ => {}
         }
 
         // HBlank continues on even during VBlank
@@ -184,13 +264,20 @@ impl PPU {
     }
 
     pub fn vblank(&mut self, scheduler: &mut Scheduler, interrupts: &mut InterruptManager) {
+        self.flush_scanlines();
         self.disp_stat.set_v_blank_flag(true);
+        self.reload_affine_references();
 
         if self.disp_stat.v_blank_irq_enable() {
             interrupts.request_interrupt(Interrupts::Vblank, scheduler);
         }
     }
 
+    /// Executed one scanline before `VCOUNT` wraps around to `0`.
+    pub fn vblank_end(&mut self) {
+        self.disp_stat.set_v_blank_flag(false);
+    }
+
     fn check_vertical_counter_interrupt(&mut self, scheduler: &mut Scheduler, interrupts: &mut InterruptManager) {
         if self.vertical_counter.current_scanline() == self.disp_stat.v_count_setting_lyc() {
             self.disp_stat.set_v_counter_flag(true);
@@ -203,108 +290,247 @@ impl PPU {
         }
     }
 
+    /// Latch the `BG2X/Y` and `BG3X/Y` registers into the internal reference points.
+    fn reload_affine_references(&mut self) {
+        for bg in 0..2 {
+            self.bg_internal_x[bg] = self.bg_rotation_x[bg].fixed_point();
+            self.bg_internal_y[bg] = self.bg_rotation_y[bg].fixed_point();
+        }
+    }
+
+    /// Move the internal reference points to the next scanline, by adding `PB` to X and `PD` to Y.
+    fn advance_affine_references(&mut self) {
+        let params = [&self.bg_rotation_reference_bg2, &self.bg_rotation_reference_bg3];
+
+        for (bg, param) in params.into_iter().enumerate() {
+            self.bg_internal_x[bg] += param[1].fixed_point();
+            self.bg_internal_y[bg] += param[3].fixed_point();
+        }
+    }
+
+    /// Snapshot the registers which determine how the current scanline is rendered.
+    fn scanline_state(&self) -> ScanlineState {
+        ScanlineState {
+            line: self.vertical_counter.current_scanline(),
+            disp_cnt: self.disp_cnt,
+            bg_control: self.bg_control,
+            bg_scrolling: self.bg_scrolling,
+            bg2_reference: (self.bg_internal_x[0], self.bg_internal_y[0]),
+            bg2_pixel_step: (
+                self.bg_rotation_reference_bg2[0].fixed_point(),
+                self.bg_rotation_reference_bg2[2].fixed_point(),
+            ),
+            debug_layers: self.debug_layers,
+        }
+    }
+
     fn render_scanline(&mut self) {
-        match self.disp_cnt.bg_mode() {
-            BgMode::Mode0 => render_scanline_mode0(self),
-            BgMode::Mode1 => render_scanline_mode1(self),
-            BgMode::Mode2 => render_scanline_mode2(self),
-            BgMode::Mode3 => {
-                // Due to how we implement rendering we rely on palette indexes in the `current_scanline`.
-                // For mode 3 we therefore render directly to the framebuffer, but because of that we need to do an early return.
-                render_scanline_mode3(self);
-                return;
+        let state = self.scanline_state();
+
+        #[cfg(feature = "parallel-rendering")]
+        if self.parallel_rendering {
+            // Batches have to consist of consecutive lines, which a debugger forcing `VCOUNT` could break.
+            if self
+                .pending_scanlines
+                .last()
+                .map_or(false, |last| last.line + 1 != state.line)
+            {
+                self.render_pending_scanlines();
             }
-            BgMode::Mode4 => render_scanline_mode4(self),
-            BgMode::Mode5 => {}
+
+            self.pending_scanlines.push(state);
+            return;
         }
 
-        // May want to do this during HBlank if games use mid-scanline writes like in the GB
-        self.push_current_scanline_to_framebuffer();
+        let current_address = state.line as usize * DISPLAY_WIDTH as usize;
+        let framebuffer_row = &mut self.frame_buffer[current_address..current_address + DISPLAY_WIDTH as usize];
+        #[cfg_attr(not(feature = "debug-functionality"), allow(unused_variables))]
+        let scanline = draw_scanline(&state, &self.vram, &self.palette, framebuffer_row);
+
+        #[cfg(feature = "debug-functionality")]
+        self.capture_palette_indices(state.line, &scanline);
     }
 
-    #[inline]
-    fn push_current_scanline_to_framebuffer(&mut self) {
-        let current_address: usize = self.vertical_counter.current_scanline() as usize * DISPLAY_WIDTH as usize;
-        let framebuffer_slice = &mut self.frame_buffer[current_address..current_address + DISPLAY_WIDTH as usize];
-
-        // Copy the values of the current scanline to the framebuffer.
-        //TODO: Should backdrop color (palette index 0) be based on the highest-priority BG or the absolute palette 0?
-        for (i, pixel) in self.current_scanline.iter().enumerate() {
-            framebuffer_slice[i] = self.palette.get_palette(*pixel as usize).to_rgba(255);
+    /// Render all scanlines deferred by [Self::render_scanline] at once, spread over the `rayon` thread pool.
+    ///
+    /// Every deferred scanline has its own register snapshot, but shares the current VRAM and palette RAM. This therefore
+    /// has to be called before either of those is modified, see [Self::flush_scanlines].
+    #[cfg(feature = "parallel-rendering")]
+    fn render_pending_scanlines(&mut self) {
+        use rayon::prelude::*;
+
+        if self.pending_scanlines.is_empty() {
+            return;
+        }
+
+        let start = self.pending_scanlines[0].line as usize * DISPLAY_WIDTH as usize;
+        let end = start + self.pending_scanlines.len() * DISPLAY_WIDTH as usize;
+        let vram: &[u8; VRAM_SIZE] = &self.vram;
+        let palette = &self.palette;
+
+        let rendered = self
+            .pending_scanlines
+            .par_iter()
+            .zip(self.frame_buffer[start..end].par_chunks_mut(DISPLAY_WIDTH as usize))
+            .map(|(state, framebuffer_row)| (state.line, draw_scanline(state, vram, palette, framebuffer_row)));
+
+        #[cfg(feature = "debug-functionality")]
+        for (line, scanline) in rendered.collect::<Vec<_>>() {
+            self.capture_palette_indices(line, &scanline);
         }
+        #[cfg(not(feature = "debug-functionality"))]
+        rendered.for_each(drop);
 
-        self.current_scanline.fill(0);
+        self.pending_scanlines.clear();
+    }
+
+    /// Render any scanlines which are still deferred, needs to happen before VRAM or palette RAM change underneath them.
+    ///
+    /// A no-op without the `parallel-rendering` feature, as scanlines are then rendered immediately.
+    #[inline(always)]
+    fn flush_scanlines(&mut self) {
+        #[cfg(feature = "parallel-rendering")]
+        if !self.pending_scanlines.is_empty() {
+            self.render_pending_scanlines();
+        }
     }
 
     pub fn frame_buffer(&mut self) -> &mut RgbaFrame {
+        self.flush_scanlines();
         &mut self.frame_buffer
     }
 
     pub fn palette_cache(&self) -> &PaletteRam {
         &self.palette
     }
+
+    /// The raw contents of VRAM.
+    pub fn vram(&self) -> &[u8; VRAM_SIZE] {
+        &self.vram
+    }
+
+    /// The raw contents of OAM.
+    pub fn oam(&self) -> &[u8; OAM_RAM_SIZE] {
+        self.oam_ram.ram()
+    }
+
+    /// Whether the PPU is currently in one of the bitmap modes (3..=5).
+    #[inline]
+    pub fn is_bitmap_mode(&self) -> bool {
+        matches!(self.disp_cnt.bg_mode(), BgMode::Mode3 | BgMode::Mode4 | BgMode::Mode5)
+    }
+
+    /// The extra cycles a CPU access to palette RAM, VRAM, or OAM currently takes.
+    ///
+    /// While drawing a visible scanline the PPU has priority over these, stalling the CPU for a cycle. During blanking,
+    /// including forced blank, the memory is free.
+    #[inline]
+    pub fn access_stall_cycles(&self) -> u8 {
+        let drawing = !self.disp_stat.h_blank_flag()
+            && self.vertical_counter.current_scanline() < DISPLAY_HEIGHT as u8
+            && !self.disp_cnt.forced_blank();
+
+        (self.access_stalls && drawing) as u8
+    }
 }
 
-fn render_scanline_mode0(ppu: &mut PPU) {
+/// Render the scanline described by `state` into `framebuffer_row`, returning the palette indexes it consists of.
+///
+/// Modes 3 and 5 don't use palette indexes, and are therefore rendered straight to the `framebuffer_row` instead, in
+/// which case the returned indexes are all `0`.
+fn draw_scanline(
+    state: &ScanlineState,
+    vram: &[u8; VRAM_SIZE],
+    palette: &PaletteRam,
+    framebuffer_row: &mut [RGBA],
+) -> ScanlineBuffer {
+    let mut scanline = [0; DISPLAY_WIDTH as usize];
+
+    match state.bg_mode() {
+        BgMode::Mode0 => render_scanline_mode0(state, vram, &mut scanline),
+        BgMode::Mode1 => render_scanline_mode1(state, vram, &mut scanline),
+        BgMode::Mode2 => render_scanline_mode2(state, vram, &mut scanline),
+        // The bitmap modes only have BG2, which can still be hidden for debugging.
+        BgMode::Mode3 if !state.debug_layers.hidden_bgs[2] => {
+            render_scanline_mode3(state, vram, framebuffer_row);
+            return scanline;
+        }
+        BgMode::Mode4 if !state.debug_layers.hidden_bgs[2] => render_scanline_mode4(state, vram, &mut scanline),
+        BgMode::Mode5 if !state.debug_layers.hidden_bgs[2] => {
+            render_scanline_mode5(state, vram, palette, framebuffer_row);
+            return scanline;
+        }
+        BgMode::Mode3 | BgMode::Mode4 | BgMode::Mode5 => {}
+    }
+
+    //TODO: Should backdrop color (palette index 0) be based on the highest-priority BG or the absolute palette 0?
+    for (pixel, index) in framebuffer_row.iter_mut().zip(scanline.iter()) {
+        *pixel = palette.get_palette(*index as usize).to_rgba(255);
+    }
+
+    scanline
+}
+
+fn render_scanline_mode0(state: &ScanlineState, vram: &[u8; VRAM_SIZE], scanline: &mut ScanlineBuffer) {
     for priority in 0..4 {
-        if ppu.disp_cnt.screen_display_bg0() {
-            if ppu.bg_control[0].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 0);
+        if state.bg_enabled(0) {
+            if state.bg_control[0].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 0);
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg1() {
-            if ppu.bg_control[1].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 1);
+        if state.bg_enabled(1) {
+            if state.bg_control[1].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 1);
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg2() {
-            if ppu.bg_control[2].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 2);
+        if state.bg_enabled(2) {
+            if state.bg_control[2].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 2);
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg3() {
-            if ppu.bg_control[3].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 3);
+        if state.bg_enabled(3) {
+            if state.bg_control[3].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 3);
             }
         }
     }
 }
 
-fn render_scanline_mode1(ppu: &mut PPU) {
+fn render_scanline_mode1(state: &ScanlineState, vram: &[u8; VRAM_SIZE], scanline: &mut ScanlineBuffer) {
     for priority in 0..4 {
-        if ppu.disp_cnt.screen_display_bg0() {
-            if ppu.bg_control[0].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 0);
+        if state.bg_enabled(0) {
+            if state.bg_control[0].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 0);
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg1() {
-            if ppu.bg_control[1].bg_priority() == priority {
-                tile_rendering::render_scanline_regular_bg_pixel(ppu, 1);
+        if state.bg_enabled(1) {
+            if state.bg_control[1].bg_priority() == priority {
+                tile_rendering::render_scanline_regular_bg_pixel(state, vram, scanline, 1);
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg2() {
-            if ppu.bg_control[2].bg_priority() == priority {
+        if state.bg_enabled(2) {
+            if state.bg_control[2].bg_priority() == priority {
                 // TODO: Affine BG
             }
         }
     }
 }
 
-fn render_scanline_mode2(ppu: &mut PPU) {
+fn render_scanline_mode2(state: &ScanlineState, _vram: &[u8; VRAM_SIZE], _scanline: &mut ScanlineBuffer) {
     for priority in 0..4 {
-        if ppu.disp_cnt.screen_display_bg2() {
-            if ppu.bg_control[2].bg_priority() == priority {
+        if state.bg_enabled(2) {
+            if state.bg_control[2].bg_priority() == priority {
                 // TODO: Affine BG
             }
         }
 
-        if ppu.disp_cnt.screen_display_bg3() {
-            if ppu.bg_control[3].bg_priority() == priority {
+        if state.bg_enabled(3) {
+            if state.bg_control[3].bg_priority() == priority {
                 // TODO: Affine BG
             }
         }
@@ -312,41 +538,463 @@ fn render_scanline_mode2(ppu: &mut PPU) {
 }
 
 #[profiling::function]
-fn render_scanline_mode3(ppu: &mut PPU) {
-    let vram_index = ppu.vertical_counter.current_scanline() as usize * DISPLAY_WIDTH as usize;
+fn render_scanline_mode3(state: &ScanlineState, vram: &[u8; VRAM_SIZE], framebuffer_row: &mut [RGBA]) {
+    let vram_index = state.line as usize * DISPLAY_WIDTH as usize;
 
-    let cur_frame_addr: usize = ppu.vertical_counter.current_scanline() as usize * DISPLAY_WIDTH as usize;
-    // Since this mode is a little special we directly render to the framebuffer, as we don't get paletted to index.
-    let framebuffer_slice = &mut ppu.frame_buffer[cur_frame_addr..cur_frame_addr + DISPLAY_WIDTH as usize];
-    for i in 0..DISPLAY_WIDTH as usize {
+    for (i, pixel_out) in framebuffer_row.iter_mut().enumerate() {
         // * 2 since we're rendering one pixel per two bytes
         let index = (vram_index + i) * 2;
-        let pixel = u16::from_le_bytes(ppu.vram[index..=index + 1].try_into().unwrap());
+        let pixel = u16::from_le_bytes(vram[index..=index + 1].try_into().unwrap());
 
-        framebuffer_slice[i] = RGBA {
-            red: palette::convert_5_to_8_bit_color(pixel.get_bits(0, 4) as u8),
-            green: palette::convert_5_to_8_bit_color(pixel.get_bits(5, 9) as u8),
-            blue: palette::convert_5_to_8_bit_color(pixel.get_bits(10, 14) as u8),
-            alpha: 255,
-        };
+        *pixel_out = direct_color_to_rgba(pixel);
     }
 }
 
 /// Render a full scanline of mode 4.
 #[profiling::function]
-fn render_scanline_mode4(ppu: &mut PPU) {
+fn render_scanline_mode4(state: &ScanlineState, vram: &[u8; VRAM_SIZE], scanline: &mut ScanlineBuffer) {
     const FRAME_0_ADDR: usize = 0x0;
     const FRAME_1_ADDR: usize = 0xA000;
 
     // If Frame 1 is selected (`display_frame_select` is true) then the frame buffer is located at 0xA000, otherwise
     // it will point to 0x0 for FRAME_0 due to the multiplication.
-    let vram_index_base = ppu.disp_cnt.display_frame_select() as usize * FRAME_1_ADDR;
+    let vram_index_base = state.disp_cnt.display_frame_select() as usize * FRAME_1_ADDR;
 
-    let vram_index = vram_index_base + (ppu.vertical_counter.current_scanline() as usize * DISPLAY_WIDTH as usize);
+    let vram_index = vram_index_base + (state.line as usize * DISPLAY_WIDTH as usize);
 
     for i in 0..DISPLAY_WIDTH as usize {
-        let palette_index = ppu.vram[vram_index + i];
+        let palette_index = vram[vram_index + i];
         // Background palettes are always located in the first 256 bytes of the palette ram.
-        ppu.current_scanline[i] = palette::convert_bg_to_absolute_palette(palette_index);
+        scanline[i] = palette::convert_bg_to_absolute_palette(palette_index);
+    }
+}
+
+/// Render a full scanline of mode 5, a `160x128` bitmap which is sampled through the `BG2` affine transform.
+///
+/// Anything outside the bitmap shows the backdrop instead.
+#[profiling::function]
+fn render_scanline_mode5(
+    state: &ScanlineState,
+    vram: &[u8; VRAM_SIZE],
+    palette: &PaletteRam,
+    framebuffer_row: &mut [RGBA],
+) {
+    const FRAME_1_ADDR: usize = 0xA000;
+    const BITMAP_WIDTH: i32 = 160;
+    const BITMAP_HEIGHT: i32 = 128;
+
+    let vram_index_base = state.disp_cnt.display_frame_select() as usize * FRAME_1_ADDR;
+    let backdrop = palette.get_palette(0).to_rgba(255);
+    let (mut x, mut y) = state.bg2_reference;
+    let (pa, pc) = state.bg2_pixel_step;
+
+    for pixel_out in framebuffer_row.iter_mut() {
+        // Drop the fractional part of the `19.8` fixed point coordinates.
+        let (bitmap_x, bitmap_y) = (x >> 8, y >> 8);
+
+        *pixel_out = if (0..BITMAP_WIDTH).contains(&bitmap_x) && (0..BITMAP_HEIGHT).contains(&bitmap_y) {
+            let index = vram_index_base + (bitmap_y * BITMAP_WIDTH + bitmap_x) as usize * 2;
+
+            direct_color_to_rgba(u16::from_le_bytes([vram[index], vram[index + 1]]))
+        } else {
+            backdrop
+        };
+
+        x += pa;
+        y += pc;
+    }
+}
+
+/// Convert a `BGR555` colour, as used by the direct colour bitmap modes, to [RGBA].
+#[inline]
+fn direct_color_to_rgba(pixel: u16) -> RGBA {
+    RGBA {
+        red: palette::convert_5_to_8_bit_color(pixel.get_bits(0, 4) as u8),
+        green: palette::convert_5_to_8_bit_color(pixel.get_bits(5, 9) as u8),
+        blue: palette::convert_5_to_8_bit_color(pixel.get_bits(10, 14) as u8),
+        alpha: 255,
+    }
+}
+
+/// The power-on value of the `PA`, `PB`, `PC`, and `PD` registers, the identity matrix.
+fn identity_rotation() -> [BgRotationRef; 4] {
+    let one = BgRotationRef::from(0x0100u16);
+
+    [one, BgRotationRef::new(), BgRotationRef::new(), one]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::emulator::bus::interrupts::Interrupts;
+    use crate::emulator::ppu::registers::{BgControl, BgMode, BgRotationRef, BgScrolling, LcdControl};
+    use crate::emulator::ppu::*;
+    use crate::emulator::EmuOptions;
+
+    #[test]
+    fn test_vram_access_stall() {
+        fn vram_write_cycles(emu: &mut crate::emulator::GBAEmulator) -> u64 {
+            let start = emu.bus.scheduler.current_time;
+            emu.bus.write_16(0x0600_0000, 0x1234);
+            emu.bus.scheduler.current_time.0 - start.0
+        }
+
+        fn vram_write_32_cycles(emu: &mut crate::emulator::GBAEmulator) -> u64 {
+            let start = emu.bus.scheduler.current_time;
+            emu.bus.write_32(0x0600_0000, 0x1234_5678);
+            emu.bus.scheduler.current_time.0 - start.0
+        }
+
+        // `B .` to keep the CPU busy without executing garbage.
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom.clone(), None, EmuOptions::default()).unwrap();
+        // Leave forced blank, so that the PPU actually draws.
+        emu.bus.write_16(0x0400_0000, 0);
+
+        assert!(!emu.bus.ppu.disp_stat.h_blank_flag());
+        assert_eq!(vram_write_cycles(&mut emu), 1);
+        // A 32 bit access is still a single access, and is therefore only stalled once.
+        assert_eq!(vram_write_32_cycles(&mut emu), 1);
+
+        while !emu.bus.ppu.disp_stat.h_blank_flag() {
+            emu.step_instruction();
+        }
+
+        assert_eq!(vram_write_cycles(&mut emu), 0);
+        assert_eq!(vram_write_32_cycles(&mut emu), 0);
+
+        let options = EmuOptions {
+            ppu_access_stalls: false,
+            ..Default::default()
+        };
+        let mut emu = crate::headless::headless_emulator(rom, None, options).unwrap();
+        emu.bus.write_16(0x0400_0000, 0);
+
+        assert_eq!(vram_write_cycles(&mut emu), 0);
+    }
+
+    #[test]
+    fn test_vcounter_irq_during_vblank() {
+        // `B .` to keep the CPU busy without executing garbage.
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        // Enable the V-Counter IRQ with LYC = 200
+        emu.bus.write(0x0400_0004, 0b0010_0000);
+        emu.bus.write(0x0400_0005, 200);
+
+        emu.run_to_vblank();
+
+        let vcounter_requested =
+            |emu: &crate::emulator::GBAEmulator| u16::from(emu.bus.interrupts.flags) & Interrupts::VCounter as u16 != 0;
+        assert!(!vcounter_requested(&emu));
+
+        while !vcounter_requested(&emu) {
+            emu.step_instruction();
+        }
+
+        let ppu = &emu.bus.ppu;
+        assert_eq!(ppu.vertical_counter.current_scanline(), 200);
+        assert!(ppu.disp_stat.v_counter_flag());
+        assert!(ppu.disp_stat.v_blank_flag());
+        // The match should happen at the start of the line, before HBlank.
+        assert!(!ppu.disp_stat.h_blank_flag());
+    }
+
+    #[test]
+    fn test_affine_reference_mid_frame_write() {
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+        // BG2 PB = 1.0, PD = -0.5
+        emu.bus.write_16(0x0400_0022, 0x0100);
+        emu.bus.write_16(0x0400_0026, 0xFF80);
+        // BG2X = 2.0, BG2Y = -1.0
+        emu.bus.write_32(0x0400_0028, 0x0000_0200);
+        emu.bus.write_32(0x0400_002C, 0x0FFF_FF00);
+
+        emu.run_to_vblank();
+
+        // Latched at VBlank
+        assert_eq!(emu.bus.ppu.bg_internal_x[0], 0x200);
+        assert_eq!(emu.bus.ppu.bg_internal_y[0], -0x100);
+
+        let step_to_scanline = |emu: &mut crate::emulator::GBAEmulator, line: u8| {
+            while emu.bus.ppu.vertical_counter.current_scanline() != line {
+                emu.step_instruction();
+            }
+        };
+
+        step_to_scanline(&mut emu, 10);
+
+        // Ten scanlines have been drawn, each advancing the reference point.
+        assert_eq!(emu.bus.ppu.bg_internal_x[0], 0x200 + 10 * 0x100);
+        assert_eq!(emu.bus.ppu.bg_internal_y[0], -0x100 - 10 * 0x80);
+
+        // A mid-frame write should apply to the very next scanline, without affecting the other reference point.
+        emu.bus.write_32(0x0400_0028, 0x0000_5000);
+        assert_eq!(emu.bus.ppu.bg_internal_x[0], 0x5000);
+        assert_eq!(emu.bus.ppu.bg_internal_y[0], -0x100 - 10 * 0x80);
+
+        step_to_scanline(&mut emu, 12);
+
+        assert_eq!(emu.bus.ppu.bg_internal_x[0], 0x5000 + 2 * 0x100);
+
+        // The next frame starts from the register values again.
+        emu.run_to_vblank();
+
+        assert_eq!(emu.bus.ppu.bg_internal_x[0], 0x5000);
+        assert_eq!(emu.bus.ppu.bg_internal_y[0], -0x100);
+    }
+
+    #[test]
+    fn test_vblank_flag_around_scanline_wrap() {
+        let rom = 0xEAFF_FFFEu32.to_le_bytes().repeat(0x80);
+        let mut emu = crate::headless::headless_emulator(rom, None, EmuOptions::default()).unwrap();
+
+        // Sample `DISPSTAT` and `VCOUNT` the way a game would
+        let sample = |emu: &mut crate::emulator::GBAEmulator| {
+            let disp_stat = emu.bus.read(0x0400_0004, &emu.cpu);
+            let v_count = emu.bus.read(0x0400_0006, &emu.cpu);
+            (disp_stat & 1 != 0, v_count)
+        };
+        let step_to_scanline = |emu: &mut crate::emulator::GBAEmulator, line: u8| {
+            while emu.bus.ppu.vertical_counter.current_scanline() != line {
+                emu.step_instruction();
+            }
+        };
+
+        emu.run_to_vblank();
+        assert_eq!(sample(&mut emu), (true, 160));
+
+        step_to_scanline(&mut emu, 226);
+        assert_eq!(sample(&mut emu), (true, 226));
+
+        // Cleared one scanline before the wrap-around.
+        step_to_scanline(&mut emu, 227);
+        assert_eq!(sample(&mut emu), (false, 227));
+
+        step_to_scanline(&mut emu, 0);
+        assert_eq!(sample(&mut emu), (false, 0));
+
+        step_to_scanline(&mut emu, 159);
+        assert_eq!(sample(&mut emu), (false, 159));
+
+        step_to_scanline(&mut emu, 160);
+        assert_eq!(sample(&mut emu), (true, 160));
+    }
+
+    /// Render `line` with the current register state, returning the palette indexes of the scanline.
+    ///
+    /// Like [draw_scanline], the bitmap modes 3 and 5 always return `0` indexes.
+    fn render_line(ppu: &mut PPU, line: u8) -> ScanlineBuffer {
+        ppu.vertical_counter.set_current_scanline(line);
+        let mut row = [RGBA::default(); DISPLAY_WIDTH as usize];
+
+        draw_scanline(&ppu.scanline_state(), &ppu.vram, &ppu.palette, &mut row)
+    }
+
+    #[test]
+    fn test_mode5_affine_rotation() {
+        let mut ppu = PPU::new();
+        ppu.disp_cnt = LcdControl::new()
+            .with_bg_mode(BgMode::Mode5)
+            .with_screen_display_bg2(true);
+        // A white backdrop, to tell it apart from the black bitmap.
+        ppu.palette.write_palette_16(0, 0x7FFF);
+        // Bitmap pixel `(5, 3)` is red, `(3, 0)` is blue.
+        write_vram(&mut ppu, (3 * 160 + 5) * 2, &0x001Fu16.to_le_bytes());
+        write_vram(&mut ppu, 3 * 2, &0x7C00u16.to_le_bytes());
+
+        let render = |ppu: &PPU| {
+            let mut row = [RGBA::default(); DISPLAY_WIDTH as usize];
+            draw_scanline(&ppu.scanline_state(), &ppu.vram, &ppu.palette, &mut row);
+            row.map(|pixel| (pixel.red, pixel.green, pixel.blue))
+        };
+        let (red, blue, black, backdrop) = ((255, 0, 0), (0, 0, 255), (0, 0, 0), (255, 255, 255));
+
+        // The identity matrix draws the bitmap 1:1, with the backdrop beyond its 160 pixel width.
+        let row = render(&ppu);
+        assert_eq!(row[3], blue);
+        assert_eq!(row[5], black);
+        assert_eq!(row[159], black);
+        assert_eq!(row[160], backdrop);
+
+        // Rotated by 90 degrees (`PA = 0`, `PC = 1.0`), a scanline walks down a column of the bitmap instead.
+        ppu.bg_rotation_reference_bg2[0] = BgRotationRef::new();
+        ppu.bg_rotation_reference_bg2[2] = BgRotationRef::from(0x0100u16);
+        ppu.bg_internal_x[0] = 5 << 8;
+        ppu.bg_internal_y[0] = 0;
+
+        let row = render(&ppu);
+        assert_eq!(row[0], black);
+        assert_eq!(row[3], red);
+        assert_eq!(row[127], black);
+        // Past the 128 pixel height of the bitmap.
+        assert_eq!(row[128], backdrop);
+    }
+
+    fn write_vram(ppu: &mut PPU, address: usize, data: &[u8]) {
+        ppu.vram[address..address + data.len()].copy_from_slice(data);
+    }
+
+    fn write_map_entry(ppu: &mut PPU, address: usize, entry: u16) {
+        write_vram(ppu, address, &entry.to_le_bytes());
+    }
+
+    /// Set up `BG0` with its tile data at `0x0` and its map at `0x4000`, where tile `1` is a gradient.
+    fn mode0_ppu(is_8bpp: bool) -> PPU {
+        let mut ppu = PPU::new();
+        ppu.disp_cnt = LcdControl::new()
+            .with_bg_mode(BgMode::Mode0)
+            .with_screen_display_bg0(true);
+        ppu.bg_control[0] = BgControl::new().with_tile_map_base(8).with_colors_palettes(is_8bpp);
+
+        for y in 0..8u8 {
+            if is_8bpp {
+                // Every pixel is unique: `y * 8 + x + 1`
+                let row = (0..8).map(|x| y * 8 + x + 1).collect::<Vec<_>>();
+                write_vram(&mut ppu, 0x40 + y as usize * 8, &row);
+            } else {
+                // Pixels `1..=8` on every row, with the low nibble being the left pixel.
+                write_vram(&mut ppu, 0x20 + y as usize * 4, &[0x21, 0x43, 0x65, 0x87]);
+            }
+        }
+
+        ppu
+    }
+
+    #[test]
+    fn test_render_mode0_4bpp() {
+        let mut ppu = mode0_ppu(false);
+        // Tile 1 with palette 2, followed by tile 1 flipped horizontally with palette 0.
+        write_map_entry(&mut ppu, 0x4000, 0x2001);
+        write_map_entry(&mut ppu, 0x4002, 0x0401);
+
+        let line = render_line(&mut ppu, 3);
+
+        assert_eq!(line[0..8], [33, 34, 35, 36, 37, 38, 39, 40]);
+        assert_eq!(line[8..16], [8, 7, 6, 5, 4, 3, 2, 1]);
+        assert!(line[16..].iter().all(|&pixel| pixel == 0));
+
+        ppu.bg_scrolling[0].x = BgScrolling::new().with_offset(4);
+
+        let line = render_line(&mut ppu, 3);
+
+        assert_eq!(line[0..12], [37, 38, 39, 40, 8, 7, 6, 5, 4, 3, 2, 1]);
+        assert!(line[12..].iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn test_render_mode0_8bpp() {
+        let mut ppu = mode0_ppu(true);
+        // Tile 1 flipped vertically, the palette number should be ignored.
+        write_map_entry(&mut ppu, 0x4000, 0xF801);
+        // The last tile of the map line, which should be skipped when scrolling past it.
+        write_map_entry(&mut ppu, 0x4000 + 31 * 2, 0x0001);
+
+        let line = render_line(&mut ppu, 2);
+
+        assert_eq!(line[0..8], [41, 42, 43, 44, 45, 46, 47, 48]);
+        assert!(line[8..].iter().all(|&pixel| pixel == 0));
+
+        // Wrap around to the start of the map.
+        ppu.bg_scrolling[0].x = BgScrolling::new().with_offset(256 - 2);
+
+        let line = render_line(&mut ppu, 2);
+
+        assert_eq!(line[0..10], [23, 24, 41, 42, 43, 44, 45, 46, 47, 48]);
+        assert!(line[10..].iter().all(|&pixel| pixel == 0));
+    }
+
+    #[test]
+    fn test_render_mode3() {
+        let mut ppu = PPU::new();
+        ppu.disp_cnt = LcdControl::new()
+            .with_bg_mode(BgMode::Mode3)
+            .with_screen_display_bg2(true);
+        let line_start = 5 * DISPLAY_WIDTH as usize;
+        // Red, followed by blue
+        write_vram(&mut ppu, line_start * 2, &[0x1F, 0x00, 0x00, 0x7C]);
+
+        ppu.vertical_counter.set_current_scanline(5);
+        let state = ppu.scanline_state();
+        render_scanline_mode3(
+            &state,
+            &ppu.vram,
+            &mut ppu.frame_buffer[line_start..line_start + DISPLAY_WIDTH as usize],
+        );
+
+        let rgb = |pixel: RGBA| (pixel.red, pixel.green, pixel.blue, pixel.alpha);
+        assert_eq!(rgb(ppu.frame_buffer[line_start]), (255, 0, 0, 255));
+        assert_eq!(rgb(ppu.frame_buffer[line_start + 1]), (0, 0, 255, 255));
+        assert_eq!(rgb(ppu.frame_buffer[line_start + 2]), (0, 0, 0, 255));
+    }
+
+    #[test]
+    fn test_render_mode4_frame_select() {
+        let mut ppu = PPU::new();
+        ppu.disp_cnt = LcdControl::new()
+            .with_bg_mode(BgMode::Mode4)
+            .with_screen_display_bg2(true);
+        let line_start = 7 * DISPLAY_WIDTH as usize;
+        write_vram(&mut ppu, line_start, &[1, 2, 3]);
+        write_vram(&mut ppu, 0xA000 + line_start, &[0xFF, 0xFE]);
+
+        let line = render_line(&mut ppu, 7);
+
+        assert_eq!(line[0..4], [1, 2, 3, 0]);
+
+        ppu.disp_cnt.set_display_frame_select(true);
+
+        let line = render_line(&mut ppu, 7);
+
+        assert_eq!(line[0..3], [0xFF, 0xFE, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-functionality")]
+    fn test_palette_index_capture() {
+        let mut ppu = PPU::new();
+        ppu.disp_cnt = LcdControl::new()
+            .with_bg_mode(BgMode::Mode4)
+            .with_screen_display_bg2(true);
+        write_vram(&mut ppu, 9 * DISPLAY_WIDTH as usize, &[1, 2, 3]);
+
+        ppu.vertical_counter.set_current_scanline(9);
+        ppu.render_scanline();
+
+        // Deferred scanlines should be rendered before the indexes are read.
+        assert_eq!(ppu.scanline_palette_indices(9)[0..4], [1, 2, 3, 0]);
+    }
+
+    #[test]
+    #[cfg(feature = "parallel-rendering")]
+    fn test_parallel_rendering_matches_serial() {
+        let render_frame = |parallel: bool| {
+            let mut ppu = mode0_ppu(false);
+            ppu.parallel_rendering = parallel;
+
+            for i in 0..64 {
+                ppu.write_palette_16(0x0500_0000 + i * 2, (i as u16) * 0x0421);
+            }
+            for col in 0..32 {
+                write_map_entry(&mut ppu, 0x4000 + col * 64, 0x0001 | ((col as u16 % 4) << 12));
+                write_map_entry(&mut ppu, 0x4000 + col * 2, 0x0001 | ((col as u16 % 4) << 12));
+            }
+
+            for line in 0..DISPLAY_HEIGHT as u8 {
+                // Every deferred scanline should render with the registers as they were at the time.
+                ppu.bg_scrolling[0].x = BgScrolling::new().with_offset(line as u16);
+                ppu.vertical_counter.set_current_scanline(line);
+                ppu.render_scanline();
+
+                // Whereas a VRAM write should first render all scanlines that are still pending.
+                if line == 80 {
+                    ppu.write_vram_16(0x0600_4000, 0);
+                }
+            }
+
+            ppu.frame_buffer().as_bytes().to_vec()
+        };
+
+        assert_eq!(render_frame(false), render_frame(true));
     }
 }
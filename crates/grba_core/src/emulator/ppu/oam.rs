@@ -27,7 +27,7 @@ impl OamRam {
 
     #[inline]
     pub fn write_oam_16(&mut self, address: MemoryAddress, value: u16) {
-        let addr = address as usize % OAM_RAM_SIZE;
+        let addr = (address & !1) as usize % OAM_RAM_SIZE;
         let data = value.to_le_bytes();
         // Better assembly
         assert!(addr < (OAM_RAM_SIZE - 1));
@@ -59,7 +59,7 @@ impl PaletteRam {
 
     #[inline]
     pub fn write_palette_16(&mut self, address: MemoryAddress, value: u16) {
-        let addr = address as usize % PALETTE_RAM_SIZE;
+        let addr = (address & !1) as usize % PALETTE_RAM_SIZE;
         let data = value.to_le_bytes();
         // Better assembly
         assert!(addr < (PALETTE_RAM_SIZE - 1));
@@ -52,6 +52,11 @@ pub struct LcdControl {
     pub obj_window_display: bool,
 }
 
+impl LcdControl {
+    /// Bit 3 (CGB Mode) can only be set by BIOS opcodes.
+    pub const WRITABLE_BITS: u16 = 0xFFF7;
+}
+
 /// | Mode | Rot/Scal | Layers | Size                                           | Tiles | Colours       | Features |
 /// |------|----------|--------|------------------------------------------------|-------|---------------|----------|
 /// | 0    | No       | 0123   | 256x256..512x515                               | 1024  | 16/16..256/1  | SFMABP   |
@@ -97,9 +102,17 @@ pub struct LcdStatus {
     ///
     /// When its value is identical to the content of the VCOUNT register then the V-Counter flag is set (Bit 2), and (if enabled in Bit 5) an interrupt is requested.
     /// Although the drawing time is only 960 cycles (240*4), the H-Blank flag is "0" for a total of 1006 cycles.
+    ///
+    /// The comparison happens at the start of each scanline, including those in VBlank (160..=227).
+    /// Values above 227 will therefore never match.
     pub v_count_setting_lyc: u8,
 }
 
+impl LcdStatus {
+    /// The flags in bits 0..=2 are read-only, and bits 6..=7 are unused.
+    pub const WRITABLE_BITS: u16 = 0xFF38;
+}
+
 /// Indicates the currently drawn scanline
 #[bitfield(bits = 16, packed = false)]
 #[repr(u16)]
@@ -159,6 +172,11 @@ pub struct BgControl {
     pub screen_size: B2,
 }
 
+impl BgControl {
+    /// Bit 13 (Display Area Overflow) is unused for Bg0/Bg1.
+    pub const WRITABLE_BITS_BG0_BG1: u16 = 0xDFFF;
+}
+
 #[bitfield(bits = 16)]
 #[repr(u16)]
 #[derive(Debug, Copy, Clone, Default)]
@@ -189,6 +207,21 @@ pub struct BgRotationRef {
     pub sign: bool,
 }
 
+impl BgRotationParam {
+    /// The reference point as a signed `19.8` fixed point number.
+    pub fn fixed_point(&self) -> i32 {
+        // Sign extend from the 28th bit.
+        ((u32::from(*self) << 4) as i32) >> 4
+    }
+}
+
+impl BgRotationRef {
+    /// The parameter as a signed `7.8` fixed point number.
+    pub fn fixed_point(&self) -> i32 {
+        u16::from(*self) as i16 as i32
+    }
+}
+
 #[bitfield(bits = 16)]
 #[repr(u16)]
 #[derive(Debug, Copy, Clone)]
@@ -226,6 +259,10 @@ pub struct WindowControl {
     unused_1: B2,
 }
 
+impl WindowControl {
+    pub const WRITABLE_BITS: u16 = 0x3F3F;
+}
+
 #[bitfield(bits = 32)]
 #[repr(u32)]
 #[derive(Debug, Copy, Clone)]
@@ -259,6 +296,10 @@ pub struct ColorSpecialSelection {
     unused: B2,
 }
 
+impl ColorSpecialSelection {
+    pub const WRITABLE_BITS: u16 = 0x3FFF;
+}
+
 #[derive(Debug, BitfieldSpecifier)]
 #[bits = 2]
 pub enum ColorSpecialEffect {
@@ -281,6 +322,10 @@ pub struct AlphaBlendCoefficients {
     unused: B3,
 }
 
+impl AlphaBlendCoefficients {
+    pub const WRITABLE_BITS: u16 = 0x1F1F;
+}
+
 #[bitfield(bits = 32)]
 #[repr(u32)]
 #[derive(Debug, Copy, Clone)]
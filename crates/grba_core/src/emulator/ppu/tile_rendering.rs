@@ -1,7 +1,7 @@
 use modular_bitfield::bitfield;
 use modular_bitfield::prelude::{B10, B4};
 
-use crate::emulator::ppu::{palette, PPU};
+use crate::emulator::ppu::{palette, ScanlineBuffer, ScanlineState, VRAM_SIZE};
 use crate::utils::BitOps;
 use crate::DISPLAY_WIDTH;
 
@@ -76,9 +76,14 @@ impl RegularScreenSize {
 }
 
 #[inline]
-pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
-    let cnt = &ppu.bg_control[bg];
-    let scrolling = &ppu.bg_scrolling[bg];
+pub fn render_scanline_regular_bg_pixel(
+    state: &ScanlineState,
+    vram: &[u8; VRAM_SIZE],
+    scanline: &mut ScanlineBuffer,
+    bg: usize,
+) {
+    let cnt = &state.bg_control[bg];
+    let scrolling = &state.bg_scrolling[bg];
     let (x_scroll, y_scroll) = (scrolling.x.offset(), scrolling.y.offset());
     let screen_size = RegularScreenSize::from_u8(cnt.screen_size());
     let (x_max_px, y_max_px) = (
@@ -90,7 +95,7 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
     let map_base = cnt.tile_map_base() as usize * BG_MAP_TEXT_SIZE;
     let is_8bpp = cnt.colors_palettes();
 
-    let scanline_to_draw = (ppu.vertical_counter.current_scanline() as u16).wrapping_add(y_scroll) % y_max_px;
+    let scanline_to_draw = (state.line as u16).wrapping_add(y_scroll) % y_max_px;
 
     let tile_line_y = scanline_to_draw % TILE_HEIGHT_PIXELS;
     let map_base = {
@@ -111,7 +116,7 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
 
     for i in 0..DISPLAY_WIDTH as usize {
         // If the current pixel has already been written to by a higher-priority background/sprite, skip it.
-        if ppu.current_scanline[i] != 0 {
+        if scanline[i] != 0 {
             continue;
         }
 
@@ -130,7 +135,7 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
             }
         };
 
-        let map_item: BgMapTextData = u16::from_le_bytes(ppu.vram[map_coord..map_coord + 2].try_into().unwrap()).into();
+        let map_item: BgMapTextData = u16::from_le_bytes(vram[map_coord..map_coord + 2].try_into().unwrap()).into();
 
         // For tile flipping
         let tile_y_coord = tile_line_y ^ (0b111 * map_item.vertical_flip() as u16);
@@ -142,7 +147,7 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
             let tile_line_addr =
                 tile_base + (tile_num * TILE_SIZE_8BPP + (tile_y_coord * TILE_WIDTH_8BPP) as u32) as usize;
             let tile_pixel_addr = tile_line_addr + tile_x_coord;
-            let palette_index = ppu.vram[tile_pixel_addr];
+            let palette_index = vram[tile_pixel_addr];
 
             palette_index
         } else {
@@ -150,7 +155,7 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
             let tile_line_addr =
                 tile_base + (tile_num * TILE_SIZE_4BPP + (tile_y_coord * TILE_WIDTH_4BPP) as u32) as usize;
             let tile_pixel_addr = tile_line_addr + (tile_x_coord / 2);
-            let two_palette_indexes = ppu.vram[tile_pixel_addr];
+            let two_palette_indexes = vram[tile_pixel_addr];
             let palette_index = (two_palette_indexes >> ((tile_x_coord % 2) * 4)) & 0x0F;
 
             if palette_index != 0 {
@@ -160,14 +165,19 @@ pub fn render_scanline_regular_bg_pixel(ppu: &mut PPU, bg: usize) {
             }
         };
 
-        ppu.current_scanline[i] = palette::convert_bg_to_absolute_palette(palette_index);
+        scanline[i] = palette::convert_bg_to_absolute_palette(palette_index);
     }
 }
 
 #[inline]
-pub fn render_scanline_regular_bg(ppu: &mut PPU, bg: usize) {
-    let cnt = &ppu.bg_control[bg];
-    let scrolling = &ppu.bg_scrolling[bg];
+pub fn render_scanline_regular_bg(
+    state: &ScanlineState,
+    vram: &[u8; VRAM_SIZE],
+    scanline: &mut ScanlineBuffer,
+    bg: usize,
+) {
+    let cnt = &state.bg_control[bg];
+    let scrolling = &state.bg_scrolling[bg];
     let (x_scroll, y_scroll) = (scrolling.x.offset(), scrolling.y.offset());
     let screen_size = RegularScreenSize::from_u8(cnt.screen_size());
 
@@ -175,7 +185,7 @@ pub fn render_scanline_regular_bg(ppu: &mut PPU, bg: usize) {
     let map_base = cnt.tile_map_base() as usize * BG_MAP_TEXT_SIZE;
     let is_8bpp = cnt.colors_palettes();
 
-    let scanline_to_be_rendered = (ppu.vertical_counter.current_scanline() as u16).wrapping_add(y_scroll);
+    let scanline_to_be_rendered = (state.line as u16).wrapping_add(y_scroll);
     // * 2 as each tile map entry is 2 bytes. TODO: Verify map layout for larger (32x64, 64x32, 64x64) screens.
     let tile_map_entries = screen_size.tiles_wide() * 2;
     let tile_lower_bound: u16 =
@@ -205,10 +215,11 @@ pub fn render_scanline_regular_bg(ppu: &mut PPU, bg: usize) {
         }
 
         let addr = map_base + (i % total_tile_map_size) as usize;
-        let tile_map_item: BgMapTextData = u16::from_le_bytes(ppu.vram[addr..=(addr + 1)].try_into().unwrap()).into();
+        let tile_map_item: BgMapTextData = u16::from_le_bytes(vram[addr..=(addr + 1)].try_into().unwrap()).into();
 
         draw_bg_line(
-            ppu,
+            vram,
+            scanline,
             &mut pixels_drawn,
             &mut pixels_to_skip,
             tile_data_base,
@@ -221,7 +232,8 @@ pub fn render_scanline_regular_bg(ppu: &mut PPU, bg: usize) {
 
 #[inline(always)]
 fn draw_bg_line(
-    ppu: &mut PPU,
+    vram: &[u8; VRAM_SIZE],
+    scanline: &mut ScanlineBuffer,
     pixels_drawn: &mut i16,
     pixels_to_skip: &mut u16,
     tile_base_addr: usize,
@@ -235,7 +247,7 @@ fn draw_bg_line(
     //TODO: Do full pixel line in one go if pixels_to_skip == 0 && pixels_drawn < DISPLAY_WIDTH - 8
     if is_8bpp {
         let addr = tile_base_addr + (tile_num * TILE_SIZE_8BPP + (tile_line_y * TILE_WIDTH_8BPP) as u32) as usize;
-        let data = &ppu.vram[addr..(addr + TILE_WIDTH_8BPP as usize)];
+        let data = &vram[addr..(addr + TILE_WIDTH_8BPP as usize)];
 
         if item.horizontal_flip() {
             for palette_index in data.into_iter().copied().rev() {
@@ -249,7 +261,7 @@ fn draw_bg_line(
                     break;
                 }
 
-                ppu.current_scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
+                scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                 *pixels_drawn += 1;
             }
         } else {
@@ -264,13 +276,13 @@ fn draw_bg_line(
                     break;
                 }
 
-                ppu.current_scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
+                scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                 *pixels_drawn += 1;
             }
         }
     } else {
         let addr = tile_base_addr + (tile_num * TILE_SIZE_4BPP + (tile_line_y * TILE_WIDTH_4BPP) as u32) as usize;
-        let data = &ppu.vram[addr..(addr + TILE_WIDTH_4BPP as usize)];
+        let data = &vram[addr..(addr + TILE_WIDTH_4BPP as usize)];
         // Since we're in 4BPP mode we have 16x16 palettes, this determines the first.
         let palette_base = item.palette_number() * 16;
 
@@ -285,8 +297,7 @@ fn draw_bg_line(
                         // We want to just set 0
                         let palette_index = if last_pixel != 0 { palette_base + last_pixel } else { 0 };
 
-                        ppu.current_scanline[*pixels_drawn as usize] =
-                            palette::convert_bg_to_absolute_palette(palette_index);
+                        scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                         *pixels_drawn += 1;
                     } else {
                         *pixels_to_skip -= 2;
@@ -300,8 +311,7 @@ fn draw_bg_line(
                             // Draw one last pixel
                             //TODO: Verify if 0..=3 or 4..=7
                             let palette_index = palette_base + two_pixels.get_bits(0, 3);
-                            ppu.current_scanline[*pixels_drawn as usize] =
-                                palette::convert_bg_to_absolute_palette(palette_index);
+                            scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                             *pixels_drawn += 1;
                             break;
                         }
@@ -312,8 +322,8 @@ fn draw_bg_line(
                         palette_base + two_pixels.get_bits(4, 7),
                     );
 
-                    ppu.current_scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(pal_1);
-                    ppu.current_scanline[*pixels_drawn as usize + 1] = palette::convert_bg_to_absolute_palette(pal_2);
+                    scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(pal_1);
+                    scanline[*pixels_drawn as usize + 1] = palette::convert_bg_to_absolute_palette(pal_2);
                     *pixels_drawn += 2;
                 }
             }
@@ -324,8 +334,7 @@ fn draw_bg_line(
                         // We should skip the first pixel in the pair
                         *pixels_to_skip = 0;
                         let palette_index = palette_base + two_pixels.get_bits(4, 7);
-                        ppu.current_scanline[*pixels_drawn as usize] =
-                            palette::convert_bg_to_absolute_palette(palette_index);
+                        scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                         *pixels_drawn += 1;
                     } else {
                         *pixels_to_skip -= 2;
@@ -339,8 +348,7 @@ fn draw_bg_line(
                             // Draw one last pixel
                             //TODO: Verify if 0..=3 or 4..=7
                             let palette_index = palette_base + two_pixels.get_bits(0, 3);
-                            ppu.current_scanline[*pixels_drawn as usize] =
-                                palette::convert_bg_to_absolute_palette(palette_index);
+                            scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(palette_index);
                             *pixels_drawn += 1;
                             break;
                         }
@@ -351,8 +359,8 @@ fn draw_bg_line(
                         palette_base + two_pixels.get_bits(4, 7),
                     );
 
-                    ppu.current_scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(pal_1);
-                    ppu.current_scanline[*pixels_drawn as usize + 1] = palette::convert_bg_to_absolute_palette(pal_2);
+                    scanline[*pixels_drawn as usize] = palette::convert_bg_to_absolute_palette(pal_1);
+                    scanline[*pixels_drawn as usize + 1] = palette::convert_bg_to_absolute_palette(pal_2);
                     *pixels_drawn += 2;
                 }
             }
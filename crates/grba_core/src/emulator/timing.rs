@@ -0,0 +1,37 @@
+use std::time::Duration;
+
+use crate::scheduler::EventTag;
+
+/// Wall-clock time spent handling scheduled events, grouped by the subsystem they belong to.
+///
+/// Anything not accounted for here is spent executing instructions, which is too fine-grained to time individually.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SubsystemTimings {
+    /// Scanline rendering and the (V/H)Blank bookkeeping.
+    pub ppu: Duration,
+    pub dma: Duration,
+    pub timers: Duration,
+    /// Polling for, and entering, interrupts.
+    pub interrupts: Duration,
+}
+
+impl SubsystemTimings {
+    /// Attribute the `elapsed` time spent handling an event with the given `tag` to its subsystem.
+    pub fn record(&mut self, tag: EventTag, elapsed: Duration) {
+        let subsystem = match tag {
+            EventTag::VBlank | EventTag::VBlankEnd | EventTag::HBlank | EventTag::HBlankEnd => &mut self.ppu,
+            EventTag::DmaStart(_) => &mut self.dma,
+            EventTag::Timer0Irq | EventTag::Timer1Irq | EventTag::Timer2Irq | EventTag::Timer3Irq => &mut self.timers,
+            EventTag::PollInterrupt => &mut self.interrupts,
+            // The events handled while halted are already recorded individually.
+            EventTag::Halt | EventTag::Exit => return,
+        };
+
+        *subsystem += elapsed;
+    }
+
+    /// The total time spent across all subsystems.
+    pub fn total(&self) -> Duration {
+        self.ppu + self.dma + self.timers + self.interrupts
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt::{Display, Formatter};
+
+use crate::emulator::bus::BIOS_SIZE;
+use crate::emulator::cartridge::header::HEADER_SIZE;
+use crate::emulator::cartridge::{CARTRIDGE_RAM_SIZE, MAX_MULTIBOOT_SIZE, MAX_ROM_SIZE};
+
+/// Errors caused by invalid data being provided to the emulator, such as a truncated ROM or BIOS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreError {
+    /// The ROM (of the given size) is either too small to contain a cartridge header, or larger than [MAX_ROM_SIZE].
+    InvalidRomSize(usize),
+    /// The BIOS (of the given size) isn't exactly [BIOS_SIZE] bytes.
+    InvalidBiosSize(usize),
+    /// The save storage (of the given size) is smaller than [CARTRIDGE_RAM_SIZE].
+    InvalidSaveSize(usize),
+    /// The ELF file couldn't be parsed, or contains segments outside of ROM and work RAM.
+    InvalidElf(String),
+    /// The multiboot image (of the given size) is larger than [MAX_MULTIBOOT_SIZE].
+    InvalidMultibootSize(usize),
+}
+
+impl Display for CoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CoreError::InvalidRomSize(size) => write!(
+                f,
+                "ROM is {} bytes, expected between {} and {} bytes",
+                size, HEADER_SIZE, MAX_ROM_SIZE
+            ),
+            CoreError::InvalidBiosSize(size) => write!(f, "BIOS is {} bytes, expected {} bytes", size, BIOS_SIZE),
+            CoreError::InvalidSaveSize(size) => write!(
+                f,
+                "Save storage is {} bytes, expected at least {} bytes",
+                size, CARTRIDGE_RAM_SIZE
+            ),
+            CoreError::InvalidElf(reason) => write!(f, "Invalid ELF file: {}", reason),
+            CoreError::InvalidMultibootSize(size) => write!(
+                f,
+                "Multiboot image is {} bytes, expected at most {} bytes",
+                size, MAX_MULTIBOOT_SIZE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CoreError {}
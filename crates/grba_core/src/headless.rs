@@ -0,0 +1,54 @@
+//! Shared helpers for running the emulator without a front end, such as in tests, benchmarks, or other tooling.
+use std::ops::{Deref, DerefMut};
+
+use crate::emulator::cartridge::{Cartridge, SaveStorage, CARTRIDGE_RAM_SIZE};
+use crate::emulator::{EmuOptions, GBAEmulator};
+use crate::error::CoreError;
+
+/// Create a new [GBAEmulator] for the given `rom`, with the cartridge save stored purely in memory.
+///
+/// The provided `bios` will override whatever was set in `options`.
+///
+/// # Errors
+///
+/// If either the `rom` or `bios` is invalid, see [Cartridge::new] and [GBAEmulator::new].
+pub fn headless_emulator(
+    rom: Vec<u8>,
+    bios: Option<Vec<u8>>,
+    mut options: EmuOptions,
+) -> Result<GBAEmulator, CoreError> {
+    options.bios = bios;
+
+    let cartridge = Cartridge::new(rom, Box::new(MemoryRam::default()))?;
+
+    GBAEmulator::new(cartridge, options)
+}
+
+/// An in-memory save for a [Cartridge], which is discarded once the emulator is dropped.
+pub struct MemoryRam {
+    data: Box<[u8; CARTRIDGE_RAM_SIZE]>,
+}
+
+impl Default for MemoryRam {
+    fn default() -> Self {
+        Self {
+            data: crate::box_array![0; CARTRIDGE_RAM_SIZE],
+        }
+    }
+}
+
+impl Deref for MemoryRam {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        &*self.data
+    }
+}
+
+impl DerefMut for MemoryRam {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut *self.data
+    }
+}
+
+impl SaveStorage for MemoryRam {}
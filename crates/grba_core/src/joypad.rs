@@ -11,3 +11,39 @@ pub enum InputKeys {
     ShoulderLeft,
     ShoulderRight,
 }
+
+bitflags::bitflags! {
+    /// The state of all buttons at once, where a set bit means the button is held.
+    ///
+    /// The bit layout matches the `KEYINPUT` register, apart from the inversion.
+    #[derive(Default)]
+    pub struct InputState: u16 {
+        const A = 1 << 0;
+        const B = 1 << 1;
+        const SELECT = 1 << 2;
+        const START = 1 << 3;
+        const RIGHT = 1 << 4;
+        const LEFT = 1 << 5;
+        const UP = 1 << 6;
+        const DOWN = 1 << 7;
+        const SHOULDER_RIGHT = 1 << 8;
+        const SHOULDER_LEFT = 1 << 9;
+    }
+}
+
+impl From<InputKeys> for InputState {
+    fn from(key: InputKeys) -> Self {
+        match key {
+            InputKeys::Start => InputState::START,
+            InputKeys::Select => InputState::SELECT,
+            InputKeys::A => InputState::A,
+            InputKeys::B => InputState::B,
+            InputKeys::Up => InputState::UP,
+            InputKeys::Down => InputState::DOWN,
+            InputKeys::Left => InputState::LEFT,
+            InputKeys::Right => InputState::RIGHT,
+            InputKeys::ShoulderLeft => InputState::SHOULDER_LEFT,
+            InputKeys::ShoulderRight => InputState::SHOULDER_RIGHT,
+        }
+    }
+}
@@ -1,11 +1,13 @@
 pub mod emulator;
+pub mod error;
+pub mod headless;
 mod joypad;
 pub mod logging;
 pub mod scheduler;
 pub mod utils;
 
 pub use emulator::ppu::{DISPLAY_HEIGHT, DISPLAY_WIDTH};
-pub use joypad::InputKeys;
+pub use joypad::{InputKeys, InputState};
 /// The total framebuffer size that would be returned each frame.
 /// Format is [crate::emulator::ppu::RGBA].
 pub const FRAMEBUFFER_SIZE: usize = (DISPLAY_WIDTH * DISPLAY_HEIGHT) as usize;
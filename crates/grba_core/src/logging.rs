@@ -1,14 +1,35 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+
 use crate::emulator::cpu::registers::{Mode, Registers};
 
 pub const BIN_TARGET_FRAME: &str = "frame";
 pub const BIN_TARGET_DEFAULT: &str = "default";
 
+/// The environment variable used for the default [crate::emulator::EmuOptions::log_targets].
+///
+/// Expects a comma separated list of target names, e.g. `GRBA_LOG=bus,ppu`.
+pub const LOG_TARGETS_ENV: &str = "GRBA_LOG";
+
+/// The [LogTargets] enabled at runtime, in addition to the ones enabled at compile time through features.
+static ENABLED_LOG_TARGETS: AtomicU8 = AtomicU8::new(0);
+
+/// Log the given message if the target is enabled.
+///
+/// A target is enabled if either its feature (e.g. `bus-logging`) is enabled, or if it was enabled at runtime through
+/// [set_log_targets].
 #[macro_export]
 macro_rules! cpu_log {
     ($feature:expr; $($arg:tt)*) => {
-        #[cfg(feature = $feature)]
         {
-            println!($($arg)*);
+            #[cfg(feature = $feature)]
+            const COMPILED_IN: bool = true;
+            #[cfg(not(feature = $feature))]
+            const COMPILED_IN: bool = false;
+            const TARGET: $crate::logging::LogTargets = $crate::logging::LogTargets::from_feature($feature);
+
+            if COMPILED_IN || $crate::logging::is_target_enabled(TARGET) {
+                println!($($arg)*);
+            }
         }
     };
     ($($arg:tt)*) => {
@@ -16,6 +37,94 @@ macro_rules! cpu_log {
     };
 }
 
+bitflags::bitflags! {
+    /// The subsystems which can be logged through [cpu_log].
+    #[derive(Default)]
+    pub struct LogTargets: u8 {
+        const CPU = 1 << 0;
+        const BUS = 1 << 1;
+        const DMA = 1 << 2;
+        const PPU = 1 << 3;
+    }
+}
+
+impl LogTargets {
+    /// Get the target belonging to the given logging feature, like `"bus-logging"`.
+    ///
+    /// Panics (at compile time when used in a `const`) if the feature isn't a known target.
+    pub const fn from_feature(feature: &str) -> LogTargets {
+        if str_eq(feature, "cpu-logging") {
+            LogTargets::CPU
+        } else if str_eq(feature, "bus-logging") {
+            LogTargets::BUS
+        } else if str_eq(feature, "dma-logging") {
+            LogTargets::DMA
+        } else if str_eq(feature, "ppu-logging") {
+            LogTargets::PPU
+        } else {
+            panic!("Unknown logging feature")
+        }
+    }
+
+    /// Parse a comma separated list of target names, like `"bus,ppu"`.
+    ///
+    /// Unknown names are ignored, and `"all"` enables every target.
+    pub fn parse(targets: &str) -> LogTargets {
+        targets
+            .split(',')
+            .map(|target| match target.trim().to_ascii_lowercase().as_str() {
+                "cpu" => LogTargets::CPU,
+                "bus" => LogTargets::BUS,
+                "dma" => LogTargets::DMA,
+                "ppu" => LogTargets::PPU,
+                "all" => LogTargets::all(),
+                "" => LogTargets::empty(),
+                other => {
+                    log::warn!("Unknown log target: `{}`", other);
+                    LogTargets::empty()
+                }
+            })
+            .fold(LogTargets::empty(), |acc, target| acc | target)
+    }
+
+    /// The targets set in the [LOG_TARGETS_ENV] environment variable, if any.
+    pub fn from_env() -> LogTargets {
+        std::env::var(LOG_TARGETS_ENV)
+            .map(|targets| LogTargets::parse(&targets))
+            .unwrap_or_default()
+    }
+}
+
+/// Set the targets which should be logged at runtime, replacing the previously enabled targets.
+///
+/// Targets whose feature was enabled at compile time are always logged.
+pub fn set_log_targets(targets: LogTargets) {
+    ENABLED_LOG_TARGETS.store(targets.bits(), Ordering::Relaxed);
+}
+
+#[inline(always)]
+pub fn is_target_enabled(target: LogTargets) -> bool {
+    ENABLED_LOG_TARGETS.load(Ordering::Relaxed) & target.bits() != 0
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+
+    if a.len() != b.len() {
+        return false;
+    }
+
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+
+    true
+}
+
 #[macro_export]
 macro_rules! bin_log {
     ($arg:expr) => {
@@ -38,8 +147,31 @@ pub fn set_logger(logger: &'static dyn BinaryLogger) {
     crate::logging::bin_logging::set_logger(logger);
 }
 
+/// Check whether the current logger requested emulation to halt.
+///
+/// If the `bin-logging` feature is not enabled this always returns `false`.
+#[inline(always)]
+pub fn should_break() -> bool {
+    #[cfg(feature = "bin-logging")]
+    {
+        crate::logging::bin_logging::should_break()
+    }
+    #[cfg(not(feature = "bin-logging"))]
+    {
+        false
+    }
+}
+
 pub trait BinaryLogger: Send + Sync {
     fn log_binary(&self, target: &str, data: &[u8]);
+
+    /// Checked right after an [InstructionFrame] has been logged, if `true` is returned the instruction will *not* be
+    /// executed and a debugging emulator will stop as if it hit a breakpoint.
+    ///
+    /// Should only return `true` once per requested break.
+    fn should_break(&self) -> bool {
+        false
+    }
 }
 
 impl BinaryLogger for () {
@@ -143,4 +275,23 @@ pub mod bin_logging {
             BIN_LOG.log_binary(target, data);
         }
     }
+
+    pub fn should_break() -> bool {
+        // Safety is to be upheld by the underlying logger.
+        unsafe { BIN_LOG.should_break() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::logging::LogTargets;
+
+    #[test]
+    fn test_parse_log_targets() {
+        assert_eq!(LogTargets::parse("bus, PPU"), LogTargets::BUS | LogTargets::PPU);
+        assert_eq!(LogTargets::parse("all"), LogTargets::all());
+        assert_eq!(LogTargets::parse("unknown,dma,"), LogTargets::DMA);
+        assert_eq!(LogTargets::parse(""), LogTargets::empty());
+        assert_eq!(LogTargets::from_feature("cpu-logging"), LogTargets::CPU);
+    }
 }
@@ -23,9 +23,9 @@ impl Scheduler {
         }
     }
 
-    /// For debug purposes one can clone the current event queue.
-    pub fn event_queue(&self) -> Vec<Event> {
-        self.event_queue.clone().into_sorted_vec()
+    /// Iterate over all currently scheduled events, in no particular order.
+    pub fn pending_events(&self) -> impl Iterator<Item = &Event> + '_ {
+        self.event_queue.iter()
     }
 
     /// Set the current time to the next closest event.
@@ -40,6 +40,8 @@ impl Scheduler {
 
     /// Returns any event that should be executed at less than or equal to the current time.
     ///
+    /// Events sharing a timestamp are returned in the order of their [EventTag] declaration.
+    ///
     /// The event is removed from the scheduler.
     #[inline]
     pub fn pop_current(&mut self) -> Option<Event> {
@@ -100,11 +102,17 @@ impl Default for Scheduler {
     }
 }
 
+/// The kind of event which can be scheduled.
+///
+/// The declaration order doubles as the priority for events scheduled at the same timestamp, with earlier variants
+/// being handled first.
 #[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
 pub enum EventTag {
     /// Shouldn't ever be reached, but will ensure the emulator will exit correctly.
     Exit,
     VBlank,
+    /// Clears the `VBLANK` flag on the last scanline of the frame.
+    VBlankEnd,
     HBlank,
     HBlankEnd,
     /// Check for interrupts, will be set whenever an interrupt write happens to ensure the CPU can handle it.
@@ -136,19 +144,22 @@ impl Default for Event {
 
 impl core::cmp::PartialEq for Event {
     fn eq(&self, other: &Self) -> bool {
-        self.timestamp == other.timestamp
+        self.timestamp == other.timestamp && self.tag == other.tag
     }
 }
 
 impl core::cmp::PartialOrd for Event {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.timestamp.partial_cmp(&other.timestamp)
+        Some(self.cmp(other))
     }
 }
 
 impl core::cmp::Ord for Event {
+    /// Orders by timestamp first, and by [EventTag] for events at the same timestamp to keep the order deterministic.
     fn cmp(&self, other: &Self) -> Ordering {
-        self.timestamp.cmp(&other.timestamp)
+        self.timestamp
+            .cmp(&other.timestamp)
+            .then_with(|| self.tag.cmp(&other.tag))
     }
 }
 
@@ -271,4 +282,38 @@ mod tests {
         scheduler.skip_to_next_event();
         assert!(scheduler.pop_current().is_none());
     }
+
+    #[test]
+    fn test_scheduler_pending_events() {
+        let mut scheduler = Scheduler::new();
+
+        scheduler.schedule_event(EventTag::HBlank, EmuTime(10));
+        scheduler.schedule_event(EventTag::VBlank, EmuTime(5));
+
+        let mut pending = scheduler
+            .pending_events()
+            .map(|ev| (ev.tag, ev.timestamp))
+            .collect::<Vec<_>>();
+        pending.sort_by_key(|(_, timestamp)| *timestamp);
+
+        assert_eq!(pending, [(VBlank, EmuTime(5)), (HBlank, EmuTime(10))]);
+        // Inspecting the queue shouldn't consume anything.
+        assert_eq!(scheduler.pending_events().count(), 2);
+    }
+
+    #[test]
+    fn test_scheduler_same_timestamp_order() {
+        let mut scheduler = Scheduler::new();
+
+        scheduler.schedule_event(EventTag::Timer0Irq, EmuTime(10));
+        scheduler.schedule_event(EventTag::HBlank, EmuTime(10));
+        scheduler.schedule_event(EventTag::VBlank, EmuTime(10));
+        scheduler.skip_to_next_event();
+
+        let popped = std::iter::from_fn(|| scheduler.pop_current())
+            .map(|ev| ev.tag)
+            .collect::<Vec<_>>();
+
+        assert_eq!(popped, [VBlank, HBlank, EventTag::Timer0Irq]);
+    }
 }
@@ -61,6 +61,21 @@ pub trait BitOps {
     /// ```
     fn set_byte_le(&mut self, byte: usize, value: u8);
 
+    /// Change the provided `byte` in `self` to the specified `value`, only modifying the bits which are set in `writable`.
+    ///
+    /// Useful for IO registers which contain read-only or unused bits.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use grba_core::utils::BitOps;
+    /// let value: u16 = 0xBEEF;
+    /// let new_value = value.change_byte_le_masked(1, 0x00, 0x0F00);
+    ///
+    /// assert_eq!(new_value, 0xB0EF);
+    /// ```
+    fn change_byte_le_masked(self, byte: usize, value: u8, writable: Self) -> Self;
+
     /// Update the provided range `begin..=end_inclusive` in `self` to the specified `value`.
     fn change_bits(self, begin: u8, end_inclusive: u8, value: Self) -> Self;
 }
@@ -92,6 +107,12 @@ macro_rules! impl_bitops {
                     *self = self.change_byte_le(byte, value)
                 }
 
+                #[inline(always)]
+                fn change_byte_le_masked(self, byte: usize, value: u8, writable: Self) -> $t {
+                    let new_value = self.change_byte_le(byte, value);
+                    (self & !writable) | (new_value & writable)
+                }
+
                 #[inline(always)]
                 fn change_bits(self, begin: u8, end_inclusive: u8, value: Self) -> $t {
                     let mask = ((1 << (end_inclusive - begin + 1)) - 1) << begin;
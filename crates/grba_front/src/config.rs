@@ -1,15 +1,19 @@
+use std::collections::HashMap;
 use std::fs::File;
 use std::path::PathBuf;
 
+use grba_core::emulator::cartridge::header::CartBackupId;
+use grba_core::emulator::EmuOptions;
 use platform_dirs::AppDirs;
+use serde::{Deserialize, Serialize};
 
 use crate::gui::AppUiState;
-use crate::EguiFramework;
+use crate::{EguiFramework, State};
 
 pub const CONFIG_FILE: &str = "config.toml";
 pub const GUI_STATE_FILE: &str = "gui_state.bin";
 
-pub fn save_state_and_config(gui: &EguiFramework) -> anyhow::Result<()> {
+pub fn save_state_and_config(gui: &EguiFramework, state: &State) -> anyhow::Result<()> {
     let persistence = get_persistences_dir();
 
     std::fs::create_dir_all(&persistence)?;
@@ -23,15 +27,169 @@ pub fn save_state_and_config(gui: &EguiFramework) -> anyhow::Result<()> {
 
     bincode::serialize_into(file, &ui_state)?;
 
+    let config = Config {
+        recent_roms: state.recent_roms.clone(),
+        settings: state.settings.clone(),
+    };
+    let config_path = get_full_config_path();
+
+    if let Some(config_dir) = config_path.parent() {
+        std::fs::create_dir_all(config_dir)?;
+    }
+
+    std::fs::write(config_path, toml::to_string_pretty(&config)?)?;
+
     Ok(())
 }
 
+/// Everything persisted in the [CONFIG_FILE].
+///
+/// Unlike the disposable [AppUiState] this is stored as TOML, where missing fields fall back to their default. Adding
+/// a setting therefore doesn't discard the rest of the user's configuration.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// The most recently loaded ROMs, with the most recent one first.
+    pub recent_roms: Vec<PathBuf>,
+    pub settings: Settings,
+}
+
+/// User settings which are persisted between sessions, configured in the settings window.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct Settings {
+    /// The BIOS to use when no `--bios` argument was provided.
+    pub bios_path: Option<PathBuf>,
+    /// The directory the `Open ROM` dialog starts in.
+    pub rom_directory: Option<PathBuf>,
+    /// What paces the emulator when it's not fast forwarding.
+    pub frame_pacing: FramePacing,
+    /// How the fast forward key (`LShift`) behaves.
+    pub fast_forward_mode: SpeedKeyMode,
+    /// How many times faster than normal the emulator runs while fast forwarding.
+    pub fast_forward_multiplier: u8,
+    /// How the unbounded speed key (`U`) behaves.
+    pub unbounded_mode: SpeedKeyMode,
+    /// Whether the FPS and emulation speed are drawn on top of the game.
+    pub show_speed_overlay: bool,
+    /// Whether emulation is paused while the window isn't focused.
+    pub pause_on_focus_loss: bool,
+    /// How the frame is scaled to fit the window.
+    pub scaling_mode: ScalingMode,
+    /// Whether to restore the fullscreen state of the previous session on startup.
+    pub remember_fullscreen: bool,
+    /// Whether the window was fullscreen when last toggled, see [Self::remember_fullscreen].
+    pub fullscreen: bool,
+    /// Whether every frame is blended with the previous one, approximating the ghosting of the original LCD.
+    ///
+    /// Some games rely on that ghosting to make flickering sprites look transparent.
+    pub frame_blending: bool,
+    /// How many times faster the emulated CPU runs relative to the rest of the system, see
+    /// [grba_core::emulator::EmuOptions::clock_scale]. Only takes effect once a ROM is (re)loaded.
+    pub clock_scale: f32,
+    /// Backup types forced by the user for games where autodetection fails, keyed by game code.
+    pub backup_overrides: HashMap<String, CartBackupId>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            bios_path: None,
+            rom_directory: None,
+            frame_pacing: FramePacing::default(),
+            fast_forward_mode: SpeedKeyMode::Held,
+            fast_forward_multiplier: 4,
+            unbounded_mode: SpeedKeyMode::Toggle,
+            show_speed_overlay: false,
+            pause_on_focus_loss: false,
+            scaling_mode: ScalingMode::default(),
+            remember_fullscreen: false,
+            fullscreen: false,
+            frame_blending: false,
+            clock_scale: 1.0,
+            backup_overrides: HashMap::new(),
+        }
+    }
+}
+
+/// How the emulator frame is scaled to the window, or screen when fullscreen.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// Scale by the largest whole number which fits, keeping every pixel the same size.
+    #[default]
+    Integer,
+    /// Scale as large as possible while keeping the `3:2` aspect ratio, with black bars filling the rest.
+    AspectFit,
+    /// Fill the entire window, ignoring the aspect ratio.
+    Stretch,
+}
+
+/// Whether a speed control key only applies while held down, or toggles on every press.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpeedKeyMode {
+    Held,
+    Toggle,
+}
+
+/// The clock source used to pace emulated frames.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Wait for the GBA's refresh rate (~59.73 Hz) between frames, independent of the monitor.
+    #[default]
+    Fixed,
+    /// Present frames in sync with the monitor's refresh rate (`PresentMode::Fifo`).
+    ///
+    /// Emulation speed will match the monitor's refresh rate instead, which also caps fast forward.
+    /// Only takes effect after a restart.
+    VSync,
+}
+
+impl FramePacing {
+    pub fn present_mode(self) -> wgpu::PresentMode {
+        match self {
+            FramePacing::Fixed => wgpu::PresentMode::Immediate,
+            FramePacing::VSync => wgpu::PresentMode::Fifo,
+        }
+    }
+}
+
 pub fn deserialise_state_and_config() -> Option<AppUiState> {
     let file = File::open(get_persistences_dir().join(GUI_STATE_FILE)).ok()?;
 
     bincode::deserialize_from(file).ok()
 }
 
+/// Load the [Config] from the [CONFIG_FILE], or the default if there is none yet.
+///
+/// A file which can't be parsed is copied to `config.toml.bak` first, as the next save will overwrite it.
+pub fn load_config() -> Config {
+    let path = get_full_config_path();
+
+    let mut config = match std::fs::read_to_string(&path) {
+        Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+            let backup_path = path.with_extension("toml.bak");
+            log::warn!(
+                "Failed to parse config at {}, backing it up to {}: {}",
+                path.display(),
+                backup_path.display(),
+                e
+            );
+
+            if let Err(e) = std::fs::copy(&path, &backup_path) {
+                log::error!("Failed to back up config to {}: {}", backup_path.display(), e);
+            }
+
+            Config::default()
+        }),
+        Err(_) => Config::default(),
+    };
+
+    // The file may have been edited by hand, while the settings window only allows valid values.
+    config.settings.clock_scale = EmuOptions::clamp_clock_scale(config.settings.clock_scale);
+
+    config
+}
+
 pub fn get_full_config_path() -> PathBuf {
     get_app_dirs().config_dir.join(CONFIG_FILE)
 }
@@ -1,10 +1,16 @@
 //! This is purely used to efficiently log the state of the emulator after every instruction
 //! This is why this uses a lot of dangerous unsafe (aka, it will go wrong in any scenario other than the current one!)
-use grba_core::logging::BinaryLogger;
+use grba_core::logging::{BinaryLogger, InstructionFrame, InstructionSnapshot};
+use memmap2::Mmap;
+use once_cell::sync::OnceCell;
 use std::cell::UnsafeCell;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static TRACE_COMPARER: OnceCell<&'static TraceComparer> = OnceCell::new();
 
 /// Create a [DebugLogger] with the output being written to the given `log_path`.
 pub fn setup_emulator_logger(log_path: impl AsRef<Path>) -> anyhow::Result<&'static DebugLogger> {
@@ -40,3 +46,145 @@ impl BinaryLogger for DebugLogger {
         }
     }
 }
+
+/// Create a [TraceComparer] for the reference trace at `reference_path`, and use it as the emulator's logger.
+#[cfg(feature = "bin-logging")]
+pub fn setup_trace_comparer(reference_path: impl AsRef<Path>) -> anyhow::Result<&'static TraceComparer> {
+    let comparer = TraceComparer::new(reference_path)?;
+    let leaked_comparer: &'static TraceComparer = Box::leak(Box::new(comparer));
+    grba_core::logging::set_logger(leaked_comparer);
+    let _ = TRACE_COMPARER.set(leaked_comparer);
+    Ok(leaked_comparer)
+}
+
+/// The active [TraceComparer], if the emulator is being compared against a reference trace.
+pub fn trace_comparer() -> Option<&'static TraceComparer> {
+    TRACE_COMPARER.get().copied()
+}
+
+/// Compares every executed instruction against a reference trace in lockstep, requesting a break at the first
+/// divergence.
+///
+/// The reference trace has the same format as the output of [DebugLogger] (and the logs `grba_log` consumes).
+pub struct TraceComparer {
+    reference: Mmap,
+    /// The index of the next entry in the reference trace.
+    index: AtomicUsize,
+    /// Set once a divergence was found, or the end of the reference trace was reached.
+    finished: AtomicBool,
+    break_requested: AtomicBool,
+    divergence: Mutex<Option<TraceDivergence>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TraceDivergence {
+    /// The index of the first differing entry in the reference trace.
+    pub index: usize,
+    pub emulator: [u32; TraceDivergence::REGISTER_COUNT],
+    pub reference: [u32; TraceDivergence::REGISTER_COUNT],
+}
+
+impl TraceDivergence {
+    const REGISTER_COUNT: usize = std::mem::size_of::<InstructionSnapshot>() / 4;
+    const REGISTER_NAMES: [&'static str; Self::REGISTER_COUNT] = [
+        "R0", "R1", "R2", "R3", "R4", "R5", "R6", "R7", "R8", "R9", "R10", "R11", "R12", "SP", "LR", "PC", "CPSR",
+        "SPSR",
+    ];
+
+    /// Returns `(register_name, emulator_value, reference_value)` for all registers which differ.
+    pub fn differing_registers(&self) -> impl Iterator<Item = (&'static str, u32, u32)> + '_ {
+        Self::REGISTER_NAMES
+            .iter()
+            .zip(self.emulator.iter().zip(self.reference.iter()))
+            .filter(|(_, (emu, reference))| emu != reference)
+            .map(|(name, (emu, reference))| (*name, *emu, *reference))
+    }
+
+    fn to_registers(bytes: &[u8]) -> [u32; Self::REGISTER_COUNT] {
+        let mut result = [0; Self::REGISTER_COUNT];
+
+        for (reg, chunk) in result.iter_mut().zip(bytes.chunks_exact(4)) {
+            *reg = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        result
+    }
+}
+
+impl TraceComparer {
+    /// Due to the way we do pipelining in the beginning (we just fill the pipeline up immediately) we need to skip
+    /// the first two entries of the reference trace, same as `grba_log`.
+    const SKIPPED_ENTRIES: usize = 2;
+    const ENTRY_SIZE: usize = std::mem::size_of::<InstructionSnapshot>();
+
+    pub fn new(reference_path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let file = File::open(reference_path)?;
+        // Safety: Nobody should be modifying the reference trace while we're running.
+        let reference = unsafe { Mmap::map(&file)? };
+
+        Ok(Self {
+            reference,
+            index: AtomicUsize::new(0),
+            finished: AtomicBool::new(false),
+            break_requested: AtomicBool::new(false),
+            divergence: Mutex::new(None),
+        })
+    }
+
+    /// The first divergence between the emulator and the reference trace, if one has been found.
+    pub fn divergence(&self) -> Option<TraceDivergence> {
+        self.divergence.lock().unwrap().clone()
+    }
+
+    /// Restart the comparison from the start of the reference trace, should be called when the emulator is reset.
+    pub fn reset(&self) {
+        self.index.store(0, Ordering::Relaxed);
+        self.finished.store(false, Ordering::Relaxed);
+        self.break_requested.store(false, Ordering::Relaxed);
+        *self.divergence.lock().unwrap() = None;
+    }
+
+    fn compare(&self, frame: &InstructionFrame) {
+        let index = self.index.fetch_add(1, Ordering::Relaxed);
+        let start = (index + Self::SKIPPED_ENTRIES) * Self::ENTRY_SIZE;
+
+        let reference = match self.reference.get(start..start + Self::ENTRY_SIZE) {
+            Some(reference) => reference,
+            None => {
+                log::info!("Reached the end of the reference trace after {index} instructions without differences");
+                self.finished.store(true, Ordering::Relaxed);
+                return;
+            }
+        };
+        let emulator = frame.registers.as_ref();
+
+        if emulator != reference {
+            let divergence = TraceDivergence {
+                index,
+                emulator: TraceDivergence::to_registers(emulator),
+                reference: TraceDivergence::to_registers(reference),
+            };
+
+            log::warn!(
+                "Diverged from the reference trace at instruction {index} (PC: {:#010X})",
+                frame.registers.r15
+            );
+
+            *self.divergence.lock().unwrap() = Some(divergence);
+            self.finished.store(true, Ordering::Relaxed);
+            self.break_requested.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+impl BinaryLogger for TraceComparer {
+    fn log_binary(&self, target: &str, data: &[u8]) {
+        if target == grba_core::logging::BIN_TARGET_FRAME && !self.finished.load(Ordering::Relaxed) {
+            self.compare(InstructionFrame::from_bytes(data));
+        }
+    }
+
+    fn should_break(&self) -> bool {
+        self.break_requested.swap(false, Ordering::Relaxed)
+    }
+}
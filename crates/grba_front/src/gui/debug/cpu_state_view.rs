@@ -55,13 +55,14 @@ impl DebugView for CpuStateView {
 
     fn draw(&mut self, ctx: &Context, open: &mut bool) -> Option<Self::EmuUpdate> {
         let state = &self.cpu_state;
+        let selected_banked = &mut self.selected_banked;
 
         egui::containers::Window::new("ARM7 State")
             .resizable(true)
             .vscroll(true)
             .open(open)
             .show(ctx, |ui| {
-                state.draw(ui);
+                state.draw(ui, selected_banked);
             });
 
         None
@@ -69,7 +70,7 @@ impl DebugView for CpuStateView {
 }
 
 impl CpuState {
-    pub fn draw(&self, ui: &mut Ui) {
+    pub fn draw(&self, ui: &mut Ui, selected_banked: &mut Mode) {
         ui.style_mut().override_text_style = Some(TextStyle::Monospace);
         // Registers
         egui::Grid::new("CPU State Registers Grid")
@@ -120,6 +121,69 @@ impl CpuState {
         } else {
             render_psr(ui, &self.registers.spsr);
         }
+
+        ui.separator();
+
+        // Banked registers
+        egui::ComboBox::new("BankedRegistersMode", "Banked Registers")
+            .selected_text(format!("{:?}", selected_banked))
+            .show_ui(ui, |ui| {
+                // System shares its bank with User, no need to list it twice.
+                for mode in enum_iterator::all::<Mode>().filter(|mode| *mode != Mode::System) {
+                    ui.selectable_value(selected_banked, mode, format!("{:?}", mode));
+                }
+            })
+            .response
+            .on_hover_text("Show the `r8-r14` registers and SPSR as seen from the given mode");
+
+        let suffix = bank_suffix(*selected_banked);
+
+        egui::Grid::new("CPU State Banked Registers Grid")
+            .striped(true)
+            .show(ui, |ui| {
+                for (i, reg) in self
+                    .registers
+                    .banked_registers(*selected_banked)
+                    .into_iter()
+                    .enumerate()
+                {
+                    let text = RichText::new(format!("r{}_{}:", i + 8, suffix)).color(colors::DARK_PURPLE);
+                    ui.label(text);
+
+                    let value = RichText::new(format!("{:08X}", reg)).background_color(colors::LIGHT_GREY);
+
+                    if ui.label(value).on_hover_cursor(CursorIcon::Copy).clicked() {
+                        ui.output_mut(|o| {
+                            o.copied_text = format!("0x{:08X}", reg);
+                        })
+                    };
+
+                    if i % 2 == 1 {
+                        ui.end_row();
+                    }
+                }
+            });
+
+        ui.label(format!("SPSR_{}:", suffix));
+
+        match self.registers.banked_spsr(*selected_banked) {
+            Some(spsr) => render_psr(ui, &spsr),
+            None => {
+                ui.label("None");
+            }
+        }
+    }
+}
+
+/// The conventional suffix for registers of the given `mode`, e.g. `r13_irq`.
+fn bank_suffix(mode: Mode) -> &'static str {
+    match mode {
+        Mode::User | Mode::System => "usr",
+        Mode::FIQ => "fiq",
+        Mode::IRQ => "irq",
+        Mode::Supervisor => "svc",
+        Mode::Abort => "abt",
+        Mode::Undefined => "und",
     }
 }
 
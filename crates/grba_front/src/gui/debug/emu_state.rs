@@ -2,17 +2,25 @@ use egui::{Context, TextStyle, Ui};
 
 use grba_core::emulator::debug::DebugEmulator;
 use grba_core::scheduler::{EmuTime, Event};
+use itertools::Itertools;
 
 use crate::gui::debug::DebugView;
 
 pub struct EmuStateView {
     emu_state: EmuState,
+    ewram_wait_states_override: Option<u8>,
 }
 
 #[derive(Debug, Default)]
 pub struct EmuState {
     current_timestamp: EmuTime,
     scheduler_events: Vec<Event>,
+    ewram_wait_states: u8,
+}
+
+#[derive(Debug)]
+pub struct EmuStateUpdate {
+    ewram_wait_states_override: Option<u8>,
 }
 
 #[derive(Debug)]
@@ -22,6 +30,7 @@ impl EmuStateView {
     pub fn new() -> Self {
         Self {
             emu_state: Default::default(),
+            ewram_wait_states_override: None,
         }
     }
 }
@@ -30,16 +39,20 @@ impl DebugView for EmuStateView {
     const NAME: &'static str = "Emulator State";
     type RequestedData = EmuState;
     type RequestInformation = EmuStateRequest;
-    type EmuUpdate = ();
+    type EmuUpdate = EmuStateUpdate;
 
     fn prepare_frame(emu: &mut DebugEmulator, _request_information: Self::RequestInformation) -> Self::RequestedData {
         EmuState {
             current_timestamp: emu.bus().scheduler.current_time,
-            scheduler_events: emu.bus().scheduler.event_queue(),
+            scheduler_events: emu.bus().scheduler.pending_events().copied().sorted().collect(),
+            ewram_wait_states: emu.bus().ewram_wait_states(),
         }
     }
 
-    fn update_emu(_emu: &mut DebugEmulator, _update: Self::EmuUpdate) {}
+    fn update_emu(emu: &mut DebugEmulator, update: Self::EmuUpdate) {
+        emu.bus()
+            .set_ewram_wait_states_override(update.ewram_wait_states_override);
+    }
 
     fn request_information(&mut self) -> Self::RequestInformation {
         EmuStateRequest
@@ -51,6 +64,8 @@ impl DebugView for EmuStateView {
 
     fn draw(&mut self, ctx: &Context, open: &mut bool) -> Option<Self::EmuUpdate> {
         let state = &self.emu_state;
+        let wait_states_override = &mut self.ewram_wait_states_override;
+        let mut changed = false;
 
         egui::containers::Window::new("Emulator State")
             .resizable(true)
@@ -58,9 +73,13 @@ impl DebugView for EmuStateView {
             .open(open)
             .show(ctx, |ui| {
                 state.draw(ui);
+
+                changed = draw_timing_overrides(ui, state, wait_states_override);
             });
 
-        None
+        changed.then_some(EmuStateUpdate {
+            ewram_wait_states_override: self.ewram_wait_states_override,
+        })
     }
 }
 
@@ -76,21 +95,64 @@ impl EmuState {
             ui.end_row();
 
             ui.label("Events:");
-            ui.vertical(|ui| {
-                ui.style_mut().wrap = Some(false);
-                for event in &self.scheduler_events {
-                    let text = if event.timestamp < self.current_timestamp {
-                        // Hard-coded event time such as Interrupt/Halt
-                        format!("{:?}({})", event.tag, event.timestamp.0)
-                    } else {
-                        format!("{:?}({})", event.tag, event.timestamp.0 - self.current_timestamp.0)
-                    };
-
-                    ui.label(text);
+            ui.label(format!("{}", self.scheduler_events.len()));
+
+            ui.end_row();
+        });
+
+        ui.separator();
+
+        egui::Grid::new("Scheduler Events").striped(true).show(ui, |ui| {
+            ui.label("Event");
+            ui.label("Timestamp");
+            ui.label("Cycles Left");
+            ui.end_row();
+
+            for event in &self.scheduler_events {
+                ui.label(format!("{:?}", event.tag));
+                ui.label(format!("{}", event.timestamp.0));
+
+                if event.timestamp <= self.current_timestamp {
+                    // Hard-coded event time such as Interrupt/Halt, will be handled after the current instruction.
+                    ui.label("Now");
+                } else {
+                    ui.label(format!("{}", (event.timestamp - self.current_timestamp).0));
                 }
-            });
+
+                ui.end_row();
+            }
         });
 
         ui.separator();
     }
 }
+
+/// Draw the memory timing overrides.
+///
+/// # Returns
+///
+/// `true` if the override was changed.
+fn draw_timing_overrides(ui: &mut Ui, state: &EmuState, wait_states_override: &mut Option<u8>) -> bool {
+    let mut changed = false;
+
+    ui.horizontal(|ui| {
+        ui.label(format!("EWRAM Wait States: {}", state.ewram_wait_states));
+
+        let mut enabled = wait_states_override.is_some();
+
+        if ui
+            .checkbox(&mut enabled, "Override")
+            .on_hover_text("Ignore the wait states the game configured through the internal memory control register")
+            .changed()
+        {
+            *wait_states_override = enabled.then_some(state.ewram_wait_states);
+            changed = true;
+        }
+
+        if let Some(wait_states) = wait_states_override {
+            changed |= ui.add(egui::DragValue::new(wait_states).clamp_range(0..=15)).changed();
+        }
+    });
+
+    changed
+}
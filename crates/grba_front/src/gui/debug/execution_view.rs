@@ -1,12 +1,15 @@
+use std::fmt::Write;
 use std::ops::Range;
+use std::path::PathBuf;
 
 use capstone::prelude::{BuildsCapstone, BuildsCapstoneSyntax};
 use capstone::{arch, Capstone};
 use egui::{Context, Key, RichText, ScrollArea, Sense, TextStyle, Ui, Vec2};
 use egui_memory_editor::Address;
 
+use grba_core::emulator::cpu::decode::decode_instructions;
 use grba_core::emulator::cpu::registers::{Registers, State};
-use grba_core::emulator::debug::{Breakpoint, DebugEmulator};
+use grba_core::emulator::debug::{Breakpoint, DebugEmulator, DmaAccess, DmaWatchpoint, MemoryCondition};
 use grba_core::emulator::MemoryAddress;
 
 use crate::gui::debug::{colors, DebugView};
@@ -16,17 +19,32 @@ pub struct CpuExecutionView {
     last_visible_address: Range<Address>,
     break_points: Vec<MemoryAddress>,
     cycle_break: Option<u64>,
+    memory_breaks: Vec<MemoryCondition>,
+    dma_watchpoints: Vec<DmaWatchpoint>,
     // Display
     capstone: Capstone,
     debug_enabled: bool,
     selected_address: Option<Address>,
     force_state: Option<State>,
     frame_state: FrameState,
+    /// The range to export, sent along with the next request.
+    export_request: Option<Range<Address>>,
+    /// Where to write the disassembly of the exported range once it has been received.
+    export_path: Option<PathBuf>,
 }
 
 pub struct FrameState {
     break_cycle_input: String,
+    step_count_input: String,
     add_breakpoint_input: String,
+    memory_break_address_input: String,
+    memory_break_mask_input: String,
+    memory_break_value_input: String,
+    dma_watch_start_input: String,
+    dma_watch_end_input: String,
+    dma_watch_access: DmaAccess,
+    export_start_input: String,
+    export_end_input: String,
     jump_to_pc: bool,
 }
 
@@ -36,11 +54,16 @@ pub struct CpuState {
     visible_address_range: Range<Address>,
     data: Vec<u8>,
     last_hit_breakpoint: Option<Breakpoint>,
+    /// The start address and contents of the requested export range, if any.
+    export_data: Option<(Address, Vec<u8>)>,
+    /// The ELF symbols within the visible range, if the cartridge was loaded from an ELF file.
+    symbols: Vec<(MemoryAddress, String)>,
 }
 
 #[derive(Debug)]
 pub struct CpuStateRequest {
     visible_address_range: Range<Address>,
+    export_range: Option<Range<Address>>,
 }
 
 impl CpuExecutionView {
@@ -63,10 +86,23 @@ impl CpuExecutionView {
             break_points: vec![],
             frame_state: FrameState {
                 break_cycle_input: String::new(),
+                step_count_input: "10".to_string(),
                 add_breakpoint_input: String::new(),
+                memory_break_address_input: String::new(),
+                memory_break_mask_input: "FFFFFFFF".to_string(),
+                memory_break_value_input: String::new(),
+                dma_watch_start_input: String::new(),
+                dma_watch_end_input: String::new(),
+                dma_watch_access: DmaAccess::Write,
+                export_start_input: String::new(),
+                export_end_input: String::new(),
                 jump_to_pc: false,
             },
             cycle_break: None,
+            memory_breaks: vec![],
+            dma_watchpoints: vec![],
+            export_request: None,
+            export_path: None,
         }
     }
 }
@@ -74,6 +110,10 @@ impl CpuExecutionView {
 #[derive(Debug)]
 pub enum CpuExecutionUpdate {
     StepInstruction,
+    /// Step the given amount of instructions, stopping early if a breakpoint is hit.
+    StepInstructions(u32),
+    /// Step until the current function returns.
+    StepOut,
     StepFrame,
     SetDebug(bool),
     SetBreakpoints(Vec<MemoryAddress>),
@@ -81,6 +121,10 @@ pub enum CpuExecutionUpdate {
     ///
     /// Otherwise interpret the `u64` as a relative clock.
     SetBreakCycle(Option<(bool, u64)>),
+    /// Break whenever one of the conditions starts to hold.
+    SetMemoryBreakpoints(Vec<MemoryCondition>),
+    /// Break whenever a DMA accesses one of the watched ranges.
+    SetDmaWatchpoints(Vec<DmaWatchpoint>),
 }
 
 impl DebugView for CpuExecutionView {
@@ -90,11 +134,27 @@ impl DebugView for CpuExecutionView {
     type EmuUpdate = Vec<CpuExecutionUpdate>;
 
     fn prepare_frame(emu: &mut DebugEmulator, request_information: Self::RequestInformation) -> Self::RequestedData {
+        let visible_range = request_information.visible_address_range.start as MemoryAddress
+            ..request_information.visible_address_range.end as MemoryAddress;
+        let symbols = emu
+            .bus()
+            .rom
+            .elf()
+            .map(|elf| {
+                elf.symbols
+                    .range(visible_range)
+                    .map(|(address, name)| (*address, name.clone()))
+                    .collect()
+            })
+            .unwrap_or_default();
+
         let mut result = CpuState {
             registers: emu.cpu().registers.clone(),
             visible_address_range: request_information.visible_address_range.clone(),
             data: Vec::with_capacity(request_information.visible_address_range.len()),
             last_hit_breakpoint: emu.debug_info().last_hit_breakpoint.clone(),
+            export_data: None,
+            symbols,
         };
 
         let (bus, cpu) = emu.bus_and_cpu();
@@ -103,6 +163,11 @@ impl DebugView for CpuExecutionView {
             result.data.push(bus.read_dbg(i as u32, cpu));
         }
 
+        if let Some(export_range) = request_information.export_range {
+            let data = export_range.clone().map(|i| bus.read_dbg(i as u32, cpu)).collect();
+            result.export_data = Some((export_range.start, data));
+        }
+
         result
     }
 
@@ -116,6 +181,17 @@ impl DebugView for CpuExecutionView {
                         log::debug!("Breakpoint hit");
                     }
                 }
+                CpuExecutionUpdate::StepInstructions(count) => {
+                    for _ in 0..count {
+                        let (_, breakpoint_hit) = emu.0.step_instruction_debug();
+
+                        if breakpoint_hit {
+                            log::debug!("Breakpoint hit");
+                            break;
+                        }
+                    }
+                }
+                CpuExecutionUpdate::StepOut => step_out(emu),
                 CpuExecutionUpdate::StepFrame => {
                     let breakpoint = emu.0.run_to_vblank_debug();
 
@@ -135,6 +211,20 @@ impl DebugView for CpuExecutionView {
                         emu.debug_info().break_at_cycle = Some(cycle);
                     }
                 }
+                CpuExecutionUpdate::SetMemoryBreakpoints(conditions) => {
+                    if matches!(emu.debug_info().last_hit_breakpoint, Some(Breakpoint::Memory(_))) {
+                        emu.debug_info().last_hit_breakpoint = None;
+                    }
+
+                    emu.set_memory_breakpoints(conditions);
+                }
+                CpuExecutionUpdate::SetDmaWatchpoints(watchpoints) => {
+                    if matches!(emu.debug_info().last_hit_breakpoint, Some(Breakpoint::Dma(_))) {
+                        emu.debug_info().last_hit_breakpoint = None;
+                    }
+
+                    emu.set_dma_watchpoints(watchpoints);
+                }
                 CpuExecutionUpdate::SetBreakCycle(None) => {
                     emu.debug_info().break_at_cycle = None;
                     if matches!(emu.debug_info().last_hit_breakpoint, Some(Breakpoint::Cycle(_))) {
@@ -148,10 +238,12 @@ impl DebugView for CpuExecutionView {
     fn request_information(&mut self) -> Self::RequestInformation {
         CpuStateRequest {
             visible_address_range: self.last_visible_address.clone(),
+            export_range: self.export_request.take(),
         }
     }
 
-    fn update_requested_data(&mut self, data: Self::RequestedData) {
+    fn update_requested_data(&mut self, mut data: Self::RequestedData) {
+        let export_data = data.export_data.take();
         self.cpu_state = data;
 
         let state = self
@@ -164,6 +256,15 @@ impl DebugView for CpuExecutionView {
         };
         // Won't fail
         let _ = self.capstone.set_mode(current_mode);
+
+        if let Some(((start, data), path)) = export_data.zip(self.export_path.take()) {
+            let disassembly = disassemble_to_string(&self.capstone, state, start, &data);
+
+            match std::fs::write(&path, disassembly) {
+                Ok(_) => log::info!("Exported disassembly to: {:?}", path),
+                Err(e) => log::error!("Failed to export disassembly to {:?} due to: {}", path, e),
+            }
+        }
     }
 
     fn draw(&mut self, ctx: &Context, open: &mut bool) -> Option<Self::EmuUpdate> {
@@ -336,8 +437,37 @@ impl CpuExecutionView {
                         updates.push(CpuExecutionUpdate::SetBreakCycle(Some((mode.is_relative(), cycle))));
                     }
 
+                    self.draw_memory_break_input(ui, updates);
+                    self.draw_dma_watch_input(ui, updates);
+
                     ui.separator();
 
+                    if let Some(Breakpoint::Logger(pc)) = &self.cpu_state.last_hit_breakpoint {
+                        ui.label(RichText::new(format!("Trace divergence at {pc:#X}")).color(colors::DARK_RED));
+
+                        if let Some(divergence) = crate::debug::trace_comparer().and_then(|c| c.divergence()) {
+                            ui.label(format!("Instruction: {}", divergence.index));
+
+                            for (name, emu, reference) in divergence.differing_registers() {
+                                ui.label(format!("{name}: {emu:#010X} (Ref: {reference:#010X})"));
+                            }
+                        }
+
+                        ui.separator();
+                    }
+
+                    if let Some(Breakpoint::Dma(hit)) = &self.cpu_state.last_hit_breakpoint {
+                        ui.label(
+                            RichText::new(format!(
+                                "DMA{} {:?} at {:#X} (Offset: {:#X})",
+                                hit.channel, hit.access, hit.address, hit.offset
+                            ))
+                            .color(colors::DARK_RED),
+                        );
+
+                        ui.separator();
+                    }
+
                     if let Some(cycle) = self.cycle_break {
                         let mut text = RichText::new(format!("Cycle({cycle})"));
 
@@ -354,6 +484,54 @@ impl CpuExecutionView {
                         });
                     }
 
+                    let mut memory_to_delete = None;
+
+                    for (i, condition) in self.memory_breaks.iter().enumerate() {
+                        let mut text = RichText::new(format!(
+                            "[{:#X}] & {:#X} == {:#X}",
+                            condition.address, condition.mask, condition.value
+                        ));
+
+                        let last_hit = &self.cpu_state.last_hit_breakpoint;
+
+                        if matches!(last_hit, Some(Breakpoint::Memory(hit)) if hit.is_same_condition(condition)) {
+                            text = text.color(colors::DARK_RED);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label(text);
+                            if ui.button("🗑").clicked() {
+                                memory_to_delete = Some(i);
+                            }
+                        });
+                    }
+
+                    if let Some(delete) = memory_to_delete {
+                        self.memory_breaks.remove(delete);
+                        updates.push(CpuExecutionUpdate::SetMemoryBreakpoints(self.memory_breaks.clone()));
+                    }
+
+                    let mut dma_to_delete = None;
+
+                    for (i, watchpoint) in self.dma_watchpoints.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "DMA {:?} {:#X}..={:#X}",
+                                watchpoint.access,
+                                watchpoint.range.start(),
+                                watchpoint.range.end()
+                            ));
+                            if ui.button("🗑").clicked() {
+                                dma_to_delete = Some(i);
+                            }
+                        });
+                    }
+
+                    if let Some(delete) = dma_to_delete {
+                        self.dma_watchpoints.remove(delete);
+                        updates.push(CpuExecutionUpdate::SetDmaWatchpoints(self.dma_watchpoints.clone()));
+                    }
+
                     let mut to_delete = None;
 
                     for (i, addr) in self.break_points.iter().enumerate() {
@@ -375,6 +553,101 @@ impl CpuExecutionView {
             });
     }
 
+    fn draw_memory_break_input(&mut self, ui: &mut Ui, updates: &mut Vec<CpuExecutionUpdate>) {
+        ui.label("Memory Break");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.memory_break_address_input)
+                    .hint_text("Address")
+                    .desired_width(70.),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.memory_break_mask_input)
+                    .hint_text("Mask")
+                    .desired_width(70.),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.memory_break_value_input)
+                    .hint_text("Value")
+                    .desired_width(70.),
+            );
+
+            let add = ui
+                .button("Add")
+                .on_hover_text("Break once `[Address] & Mask == Value` starts to hold");
+
+            if add.clicked() {
+                let address = parse_hex_address(&self.frame_state.memory_break_address_input);
+                let mask = parse_hex_address(&self.frame_state.memory_break_mask_input);
+                let value = parse_hex_address(&self.frame_state.memory_break_value_input);
+
+                match (address, mask, value) {
+                    (Some(address), Some(mask), Some(value)) => {
+                        self.memory_breaks.push(MemoryCondition::new(
+                            address as MemoryAddress,
+                            mask as u32,
+                            value as u32,
+                        ));
+                        updates.push(CpuExecutionUpdate::SetMemoryBreakpoints(self.memory_breaks.clone()));
+                    }
+                    _ => log::warn!(
+                        "Invalid memory break: `[{}] & {} == {}`",
+                        self.frame_state.memory_break_address_input,
+                        self.frame_state.memory_break_mask_input,
+                        self.frame_state.memory_break_value_input
+                    ),
+                }
+            }
+        });
+    }
+
+    fn draw_dma_watch_input(&mut self, ui: &mut Ui, updates: &mut Vec<CpuExecutionUpdate>) {
+        ui.label("DMA Watch");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.dma_watch_start_input)
+                    .hint_text("Start")
+                    .desired_width(70.),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.dma_watch_end_input)
+                    .hint_text("End")
+                    .desired_width(70.),
+            );
+            ui.selectable_value(&mut self.frame_state.dma_watch_access, DmaAccess::Read, "Read");
+            ui.selectable_value(&mut self.frame_state.dma_watch_access, DmaAccess::Write, "Write");
+
+            let add = ui
+                .button("Add")
+                .on_hover_text("Break once a DMA accesses an address within `Start..=End`");
+
+            if add.clicked() {
+                let start = parse_hex_address(&self.frame_state.dma_watch_start_input);
+                // A single address can be watched by leaving out the end.
+                let end = if self.frame_state.dma_watch_end_input.trim().is_empty() {
+                    start
+                } else {
+                    parse_hex_address(&self.frame_state.dma_watch_end_input)
+                };
+
+                match (start, end) {
+                    (Some(start), Some(end)) if start <= end => {
+                        self.dma_watchpoints.push(DmaWatchpoint {
+                            range: start as MemoryAddress..=end as MemoryAddress,
+                            access: self.frame_state.dma_watch_access,
+                        });
+                        updates.push(CpuExecutionUpdate::SetDmaWatchpoints(self.dma_watchpoints.clone()));
+                    }
+                    _ => log::warn!(
+                        "Invalid DMA watch range: `{}..={}`",
+                        self.frame_state.dma_watch_start_input,
+                        self.frame_state.dma_watch_end_input
+                    ),
+                }
+            }
+        });
+    }
+
     fn draw_actions(&mut self, ui: &mut Ui, updates: &mut Vec<CpuExecutionUpdate>) {
         ui.horizontal(|ui| {
             if ui
@@ -389,6 +662,23 @@ impl CpuExecutionView {
                 updates.push(CpuExecutionUpdate::StepInstruction);
             }
 
+            ui.add(egui::TextEdit::singleline(&mut self.frame_state.step_count_input).desired_width(50.));
+
+            if ui.button("Step N").clicked() {
+                match self.frame_state.step_count_input.parse() {
+                    Ok(count) => updates.push(CpuExecutionUpdate::StepInstructions(count)),
+                    Err(e) => log::warn!("Invalid step count: {}", e),
+                }
+            }
+
+            if ui
+                .button("Step Out")
+                .on_hover_text("Run until the current function returns")
+                .clicked()
+            {
+                updates.push(CpuExecutionUpdate::StepOut);
+            }
+
             if ui.button("Step Frame").clicked() {
                 updates.push(CpuExecutionUpdate::StepFrame);
             }
@@ -426,6 +716,51 @@ impl CpuExecutionView {
                 updates.push(CpuExecutionUpdate::SetDebug(self.debug_enabled));
             }
         });
+
+        self.draw_export(ui);
+    }
+
+    fn draw_export(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.export_start_input)
+                    .hint_text("Start")
+                    .desired_width(80.),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.frame_state.export_end_input)
+                    .hint_text("End")
+                    .desired_width(80.),
+            );
+
+            if ui
+                .button("Export disassembly")
+                .on_hover_text("Disassemble the hexadecimal range [Start, End) to a text file, following `Force Mode`")
+                .clicked()
+            {
+                let start = parse_hex_address(&self.frame_state.export_start_input);
+                let end = parse_hex_address(&self.frame_state.export_end_input);
+
+                match start.zip(end).filter(|(start, end)| start < end) {
+                    Some((start, end)) => {
+                        let path = rfd::FileDialog::new()
+                            .set_title("Export Disassembly")
+                            .set_file_name(&format!("disassembly_{start:08X}_{end:08X}.txt"))
+                            .save_file();
+
+                        if let Some(path) = path {
+                            self.export_request = Some(start..end);
+                            self.export_path = Some(path);
+                        }
+                    }
+                    None => log::warn!(
+                        "Invalid export range: `{}`..`{}`",
+                        self.frame_state.export_start_input,
+                        self.frame_state.export_end_input
+                    ),
+                }
+            }
+        });
     }
 
     fn draw_instruction(&self, ui: &mut Ui, state: State, address: usize) {
@@ -451,11 +786,25 @@ impl CpuExecutionView {
 
             if let Some(instr) = disassembled.get(0) {
                 let text = RichText::new(format!("{} {}", instr.mnemonic().unwrap(), instr.op_str().unwrap()));
+                let kind = decode_instructions(data, address as MemoryAddress, state)
+                    .next()
+                    .map(|decoded| format!("{:?}", decoded.kind))
+                    .unwrap_or_default();
 
-                ui.label(text);
+                ui.label(text).on_hover_text(kind);
             } else {
                 ui.label("ERROR");
             }
+
+            let symbol = self
+                .cpu_state
+                .symbols
+                .iter()
+                .find(|(symbol_address, _)| *symbol_address as usize == address);
+
+            if let Some((_, name)) = symbol {
+                ui.colored_label(colors::PURPLE, format!("<{name}>"));
+            }
         }
     }
 
@@ -468,3 +817,128 @@ impl CpuExecutionView {
         self.break_points.retain(|item| *item != address)
     }
 }
+
+/// Parse a hexadecimal address, with or without a `0x` prefix.
+fn parse_hex_address(input: &str) -> Option<Address> {
+    let trimmed = input.trim();
+
+    Address::from_str_radix(trimmed.strip_prefix("0x").unwrap_or(trimmed), 16).ok()
+}
+
+/// Disassemble `data`, located at `start`, with one line per instruction containing its address, raw encoding, and
+/// mnemonic.
+fn disassemble_to_string(capstone: &Capstone, state: State, start: Address, data: &[u8]) -> String {
+    let instruction_size = match state {
+        State::Arm => 4,
+        State::Thumb => 2,
+    };
+    let mut result = String::new();
+    let mut offset = 0;
+
+    while offset + instruction_size <= data.len() {
+        let address = start + offset;
+        // We take 4 bytes, even in Thumb mode (because we want to disassemble the `bl` instruction correctly)
+        let bytes = &data[offset..data.len().min(offset + 4)];
+        let disassembled = capstone.disasm_count(bytes, address as u64, 1).ok();
+
+        let (size, text) = match disassembled.as_ref().and_then(|instrs| instrs.first()) {
+            Some(instr) => (
+                instr.bytes().len(),
+                format!(
+                    "{} {}",
+                    instr.mnemonic().unwrap_or_default(),
+                    instr.op_str().unwrap_or_default()
+                ),
+            ),
+            None => (instruction_size, "<invalid>".to_string()),
+        };
+
+        let raw = data[offset..offset + size]
+            .chunks(instruction_size)
+            .map(|chunk| match state {
+                State::Arm => format!("{:08X}", u32::from_le_bytes(chunk.try_into().unwrap())),
+                State::Thumb => format!("{:04X}", u16::from_le_bytes(chunk.try_into().unwrap())),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let _ = writeln!(result, "0x{address:08X}: {raw:<13} {}", text.trim_end());
+
+        offset += size;
+    }
+
+    result
+}
+
+/// Step until the current function returns, which is when either the stack pointer rises above its value at the start,
+/// or a return instruction (`BX LR`, `MOV PC, LR`, or a pop into `PC`) has been taken.
+///
+/// Both only count in the mode we started in, so interrupts are stepped through. A return also has to leave the stack
+/// at least as high as it was at the start, which skips returns from nested calls, as those run on a deeper stack.
+fn step_out(emu: &mut DebugEmulator) {
+    /// Prevent hanging the UI if the function never returns, roughly seven frames of emulation.
+    const MAX_INSTRUCTIONS: u32 = 1_000_000;
+
+    let registers = &emu.cpu().registers;
+    let entry_mode = registers.cpsr.mode();
+    let entry_sp = registers.general_purpose[13];
+
+    for _ in 0..MAX_INSTRUCTIONS {
+        let address = emu.cpu().registers.next_pc();
+        let is_return = is_return_instruction(emu);
+        let (_, breakpoint_hit) = emu.0.step_instruction_debug();
+
+        if breakpoint_hit {
+            log::debug!("Breakpoint hit");
+            return;
+        }
+
+        let registers = &emu.cpu().registers;
+
+        if registers.cpsr.mode() != entry_mode {
+            continue;
+        }
+
+        let sp = registers.general_purpose[13];
+        // A conditional return which wasn't taken just continues with the next instruction.
+        let return_taken = is_return && !matches!(registers.next_pc().wrapping_sub(address), 2 | 4);
+
+        if sp > entry_sp || (return_taken && sp >= entry_sp) {
+            return;
+        }
+    }
+
+    log::warn!("Step out didn't return within {} instructions", MAX_INSTRUCTIONS);
+}
+
+/// Whether the instruction at the current PC is a return (`BX LR`, `MOV PC, LR`, or a pop into `PC`).
+fn is_return_instruction(emu: &mut DebugEmulator) -> bool {
+    let (bus, cpu) = emu.bus_and_cpu();
+    let address = cpu.registers.next_pc();
+
+    match cpu.registers.cpsr.state() {
+        State::Arm => {
+            let instruction = u32::from_le_bytes([
+                bus.read_dbg(address, cpu),
+                bus.read_dbg(address + 1, cpu),
+                bus.read_dbg(address + 2, cpu),
+                bus.read_dbg(address + 3, cpu),
+            ]) & 0x0FFF_FFFF;
+
+            let is_bx_lr = instruction == 0x012F_FF1E;
+            let is_mov_pc_lr = instruction == 0x01A0_F00E;
+            // LDM with PC in the register list
+            let is_pop_pc = instruction & 0x0E10_8000 == 0x0810_8000;
+
+            is_bx_lr || is_mov_pc_lr || is_pop_pc
+        }
+        State::Thumb => {
+            let instruction = u16::from_le_bytes([bus.read_dbg(address, cpu), bus.read_dbg(address + 1, cpu)]);
+
+            let is_bx_lr = instruction == 0x4770;
+            let is_pop_pc = instruction & 0xFF00 == 0xBD00;
+
+            is_bx_lr || is_pop_pc
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use egui::{Color32, Context, TextStyle, Ui};
+
+use grba_core::emulator::bus::heatmap::{AccessHeatmap, RegionAccesses, REGION_COUNT, REGION_NAMES};
+use grba_core::emulator::debug::DebugEmulator;
+
+use crate::gui::debug::{colors, DebugView};
+
+pub struct HeatmapView {
+    accesses: RegionAccesses,
+    mode: HeatmapMode,
+    frames: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapMode {
+    /// Only show the accesses of the last few frames.
+    LastFrames,
+    /// Show all accesses since the last reset.
+    Cumulative,
+}
+
+#[derive(Debug)]
+pub struct HeatmapRequest {
+    mode: HeatmapMode,
+    frames: usize,
+}
+
+#[derive(Debug)]
+pub struct HeatmapReset;
+
+impl HeatmapView {
+    pub fn new() -> Self {
+        Self {
+            accesses: Default::default(),
+            mode: HeatmapMode::LastFrames,
+            frames: 1,
+        }
+    }
+}
+
+impl DebugView for HeatmapView {
+    const NAME: &'static str = "Memory Heatmap";
+    type RequestedData = RegionAccesses;
+    type RequestInformation = HeatmapRequest;
+    type EmuUpdate = HeatmapReset;
+
+    fn prepare_frame(emu: &mut DebugEmulator, request_information: Self::RequestInformation) -> Self::RequestedData {
+        let heatmap = &emu.bus().access_heatmap;
+
+        match request_information.mode {
+            HeatmapMode::LastFrames => heatmap.last_frames(request_information.frames),
+            HeatmapMode::Cumulative => heatmap.cumulative(),
+        }
+    }
+
+    fn update_emu(emu: &mut DebugEmulator, _update: Self::EmuUpdate) {
+        emu.bus().access_heatmap.reset();
+    }
+
+    fn request_information(&mut self) -> Self::RequestInformation {
+        HeatmapRequest {
+            mode: self.mode,
+            frames: self.frames,
+        }
+    }
+
+    fn update_requested_data(&mut self, data: Self::RequestedData) {
+        self.accesses = data;
+    }
+
+    fn draw(&mut self, ctx: &Context, open: &mut bool) -> Option<Self::EmuUpdate> {
+        let mut reset = false;
+
+        egui::containers::Window::new(Self::NAME)
+            .resizable(true)
+            .vscroll(true)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.mode, HeatmapMode::LastFrames, "Last Frames");
+                    ui.radio_value(&mut self.mode, HeatmapMode::Cumulative, "Cumulative");
+
+                    if self.mode == HeatmapMode::LastFrames {
+                        ui.add(egui::DragValue::new(&mut self.frames).clamp_range(1..=AccessHeatmap::MAX_HISTORY))
+                            .on_hover_text("The amount of frames to sum the accesses over");
+                    }
+
+                    reset = ui.button("Reset").clicked();
+                });
+
+                ui.separator();
+
+                draw_accesses(ui, &self.accesses);
+            });
+
+        reset.then_some(HeatmapReset)
+    }
+}
+
+fn draw_accesses(ui: &mut Ui, accesses: &RegionAccesses) {
+    ui.style_mut().override_text_style = Some(TextStyle::Monospace);
+    // Scale all bars relative to the busiest region, so the relative bandwidth use is easy to compare.
+    let max = accesses
+        .reads
+        .iter()
+        .chain(accesses.writes.iter())
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    egui::Grid::new("heatmap-grid").striped(true).show(ui, |ui| {
+        ui.label("Region");
+        ui.label("Reads");
+        ui.label("Writes");
+        ui.end_row();
+
+        for region in 0..REGION_COUNT {
+            let (reads, writes) = (accesses.reads[region], accesses.writes[region]);
+
+            if reads == 0 && writes == 0 {
+                continue;
+            }
+
+            ui.colored_label(colors::DARK_PURPLE, REGION_NAMES[region]);
+            access_bar(ui, reads, max, colors::HIGHLIGHT);
+            access_bar(ui, writes, max, colors::DARK_RED);
+            ui.end_row();
+        }
+    });
+}
+
+fn access_bar(ui: &mut Ui, count: u64, max: u64, color: Color32) {
+    ui.add(
+        egui::ProgressBar::new(count as f32 / max as f32)
+            .desired_width(150.0)
+            .fill(color)
+            .text(count.to_string()),
+    );
+}
@@ -1,10 +1,12 @@
-use std::ops::Range;
+use std::ops::{Range, RangeInclusive};
 
 use egui::{Context, Ui};
 use itertools::Itertools;
 
+use grba_core::emulator::bus::IO_START;
 use grba_core::emulator::cpu::registers::Registers;
 use grba_core::emulator::debug::DebugEmulator;
+use grba_core::emulator::ppu::LCD_IO_END;
 use grba_core::emulator::MemoryAddress;
 
 use crate::gui::debug::DebugView;
@@ -39,6 +41,8 @@ pub struct IoStateRequest {
 #[derive(Debug)]
 pub struct IoStateResponse {
     data: Vec<(MemoryAddress, u8)>,
+    /// The registers to restore to their power-on value, before `data` is written.
+    reset: Option<RangeInclusive<MemoryAddress>>,
 }
 
 impl IoView {
@@ -81,6 +85,10 @@ impl DebugView for IoView {
     fn update_emu(emu: &mut DebugEmulator, update: Self::EmuUpdate) {
         let (bus, _cpu) = emu.bus_and_cpu();
 
+        if let Some(range) = update.reset {
+            bus.reset_io_registers(range);
+        }
+
         for (address, value) in update.data {
             // TODO: Make a debug write function which ignores data bus shenanigans (like VRAM not being writable with u8)
             bus.write(address, value);
@@ -149,6 +157,7 @@ impl IoView {
 
         let selected_reg = &registers::IO_REGISTER_VIEWS[frame_data.selected_reg];
         let data_range = selected_reg.address.clone();
+        let mut reset = None;
 
         // Main panel
         let response = egui::ScrollArea::vertical()
@@ -174,20 +183,44 @@ impl IoView {
                     ui.separator();
 
                     ui.label(format!("Value: {}", (selected_reg.format)(data)));
+
+                    ui.separator();
+
+                    if ui
+                        .button("Reset")
+                        .on_hover_text("Restore the power-on value of this register")
+                        .clicked()
+                    {
+                        reset = Some(data_range.clone());
+                    }
+
+                    if ui
+                        .button("Reset PPU")
+                        .on_hover_text("Restore the power-on value of all PPU registers")
+                        .clicked()
+                    {
+                        reset = Some(IO_START..=LCD_IO_END);
+                    }
                 });
 
                 ui.separator();
 
                 (selected_reg.draw)(ui, data)
             })
-            .inner?;
+            .inner;
+
+        if response.is_none() && reset.is_none() {
+            return None;
+        }
 
         Some(IoStateResponse {
             data: response
                 .into_iter()
+                .flatten()
                 .zip(data_range)
                 .map(|(response, address)| (address, response))
                 .collect(),
+            reset,
         })
     }
 }
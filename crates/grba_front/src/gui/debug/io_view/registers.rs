@@ -24,7 +24,7 @@ macro_rules! offset {
 
 /// Ideally this would just be `const`, however, until `&mut` in `fn` is stable we can't have `draw` calls in the
 /// [IoView] object const fn.
-pub static IO_REGISTER_VIEWS: Lazy<[IoView; 45]> = Lazy::new(|| {
+pub static IO_REGISTER_VIEWS: Lazy<[IoView; 47]> = Lazy::new(|| {
     [
         IoView::new_16("IEnable", offset!(IO_START, 0x200), draw_ie_if_view),
         IoView::new_16("IFlags", offset!(IO_START, 0x202), draw_ie_if_view),
@@ -67,10 +67,12 @@ pub static IO_REGISTER_VIEWS: Lazy<[IoView; 45]> = Lazy::new(|| {
         IoView::new_16("BldCnt", offset!(IO_START, 0x50), draw_bldcnt_view),
         IoView::new_16("BldAlpha", offset!(IO_START, 0x52), draw_bldalpha_view),
         IoView::new_16("BldY", offset!(IO_START, 0x54), draw_bldy_view),
+        IoView::new_16("SoundBias", offset!(IO_START, 0x88), draw_sound_bias_view),
         IoView::new_16("DMA0Control", offset!(IO_START, 0xBA), draw_dma_control_view),
         IoView::new_16("DMA1Control", offset!(IO_START, 0xC6), draw_dma_control_view),
         IoView::new_16("DMA2Control", offset!(IO_START, 0xD2), draw_dma_control_view),
         IoView::new_16("DMA3Control", offset!(IO_START, 0xDE), draw_dma_control_view),
+        IoView::new_8("PostFlg", offset!(IO_START, 0x300, 1), draw_post_flag_view),
     ]
 });
 
@@ -109,6 +111,14 @@ impl IoView {
         }
     }
 
+    pub fn new_8(
+        name: &'static str,
+        address: RangeInclusive<MemoryAddress>,
+        draw: fn(ui: &mut Ui, reg_value: &[u8]) -> Option<Vec<u8>>,
+    ) -> Self {
+        IoView::new(name, address, format_u8, draw)
+    }
+
     pub fn new_16(
         name: &'static str,
         address: RangeInclusive<MemoryAddress>,
@@ -436,6 +446,41 @@ fn draw_bldy_view(ui: &mut Ui, reg_value: &[u8]) -> Option<Vec<u8>> {
     changed.then(|| reg_value.to_le_bytes().into())
 }
 
+fn draw_sound_bias_view(ui: &mut Ui, reg_value: &[u8]) -> Option<Vec<u8>> {
+    let mut changed = false;
+    let mut reg_value = u16::from_le_bytes(reg_value.try_into().unwrap()) as u32;
+
+    changed |= io_utils::io_slider(ui, &mut reg_value, 0x1..=0x9, "Bias Level", 0..=0x1FF);
+    changed |= io_utils::io_list(
+        ui,
+        &mut reg_value,
+        0xE..=0xF,
+        "Amplitude Resolution/Sampling Cycle",
+        &[
+            "9 bit, 32.768 kHz",
+            "8 bit, 65.536 kHz",
+            "7 bit, 131.072 kHz",
+            "6 bit, 262.144 kHz",
+        ],
+    );
+
+    changed.then(|| reg_value.to_le_bytes().into())
+}
+
+fn draw_post_flag_view(ui: &mut Ui, reg_value: &[u8]) -> Option<Vec<u8>> {
+    let mut changed = false;
+    let mut reg_value = reg_value[0] as u32;
+
+    changed |= io_utils::io_checkbox(
+        ui,
+        &mut reg_value,
+        0x0,
+        "Further Boot (set by the BIOS after the first boot)",
+    );
+
+    changed.then(|| vec![reg_value as u8])
+}
+
 pub fn unimplemented_view(ui: &mut Ui, _reg_value: &[u8]) -> Option<Vec<u8>> {
     ui.label("Unimplemented");
     None
@@ -490,6 +535,10 @@ fn draw_dma_control_view(ui: &mut Ui, reg_value: &[u8]) -> Option<Vec<u8>> {
     changed.then(|| reg_value.to_le_bytes().into())
 }
 
+fn format_u8(reg_value: &[u8]) -> String {
+    format!("{:#04X}", reg_value[0])
+}
+
 fn format_u16(reg_value: &[u8]) -> String {
     format!("{:#06X}", u16::from_le_bytes(reg_value.try_into().unwrap()))
 }
@@ -0,0 +1,98 @@
+use egui::{Context, TextStyle};
+
+use grba_core::emulator::debug::{BgMode, DebugEmulator};
+use grba_core::emulator::ppu::DebugLayerMask;
+
+use crate::gui::debug::DebugView;
+
+pub struct LayerView {
+    mask: DebugLayerMask,
+}
+
+#[derive(Debug)]
+pub struct LayerRequest;
+
+impl LayerView {
+    pub fn new() -> Self {
+        Self {
+            mask: DebugLayerMask::default(),
+        }
+    }
+}
+
+impl DebugView for LayerView {
+    const NAME: &'static str = "Layers";
+    type RequestedData = DebugLayerMask;
+    type RequestInformation = LayerRequest;
+    type EmuUpdate = DebugLayerMask;
+
+    fn prepare_frame(emu: &mut DebugEmulator, _request_information: Self::RequestInformation) -> Self::RequestedData {
+        emu.bus().ppu.debug_layer_mask()
+    }
+
+    fn update_emu(emu: &mut DebugEmulator, update: Self::EmuUpdate) {
+        emu.bus().ppu.set_debug_layer_mask(update);
+    }
+
+    fn request_information(&mut self) -> Self::RequestInformation {
+        LayerRequest
+    }
+
+    fn update_requested_data(&mut self, data: Self::RequestedData) {
+        self.mask = data;
+    }
+
+    fn draw(&mut self, ctx: &Context, open: &mut bool) -> Option<Self::EmuUpdate> {
+        let mut changed = false;
+
+        egui::containers::Window::new(Self::NAME)
+            .resizable(false)
+            .vscroll(false)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.style_mut().override_text_style = Some(TextStyle::Monospace);
+
+                ui.horizontal(|ui| {
+                    for (i, hidden) in self.mask.hidden_bgs.iter_mut().enumerate() {
+                        let mut visible = !*hidden;
+
+                        if ui.checkbox(&mut visible, format!("BG{}", i)).changed() {
+                            *hidden = !visible;
+                            changed = true;
+                        }
+                    }
+
+                    let mut obj_visible = !self.mask.hide_obj;
+
+                    if ui.checkbox(&mut obj_visible, "OBJ").changed() {
+                        self.mask.hide_obj = !obj_visible;
+                        changed = true;
+                    }
+                });
+
+                let current_mode = self
+                    .mask
+                    .forced_bg_mode
+                    .map(|mode| format!("{:?}", mode))
+                    .unwrap_or_else(|| "None".to_string());
+
+                egui::ComboBox::new("ForceBgMode", "Force Mode")
+                    .selected_text(current_mode)
+                    .show_ui(ui, |ui| {
+                        changed |= ui
+                            .selectable_value(&mut self.mask.forced_bg_mode, None, "None")
+                            .changed();
+
+                        for mode in enum_iterator::all::<BgMode>() {
+                            changed |= ui
+                                .selectable_value(&mut self.mask.forced_bg_mode, Some(mode), format!("{:?}", mode))
+                                .changed();
+                        }
+                    })
+                    .response
+                    .on_hover_text("Will render the game in the given mode, without changing `DISPCNT`");
+            });
+
+        changed.then_some(self.mask)
+    }
+}
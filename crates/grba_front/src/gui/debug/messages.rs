@@ -1,7 +1,9 @@
 use crate::gui::debug::cpu_state_view::CpuStateView;
 use crate::gui::debug::emu_state::EmuStateView;
 use crate::gui::debug::execution_view::CpuExecutionView;
+use crate::gui::debug::heatmap_view::HeatmapView;
 use crate::gui::debug::io_view::IoView;
+use crate::gui::debug::layer_view::LayerView;
 use crate::gui::debug::memory_view::MemoryEditorView;
 use crate::gui::debug::palette_view::PaletteView;
 use crate::gui::debug::DebugView;
@@ -25,6 +27,14 @@ pub enum DebugMessageUi {
         <IoView as DebugView>::RequestInformation,
         Option<<IoView as DebugView>::EmuUpdate>,
     ),
+    LayerRequest(
+        <LayerView as DebugView>::RequestInformation,
+        Option<<LayerView as DebugView>::EmuUpdate>,
+    ),
+    HeatmapRequest(
+        <HeatmapView as DebugView>::RequestInformation,
+        Option<<HeatmapView as DebugView>::EmuUpdate>,
+    ),
 }
 
 /// Represents the response to a [DebugMessageUi] request.
@@ -36,4 +46,6 @@ pub enum DebugMessageResponse {
     PaletteResponse(<PaletteView as DebugView>::RequestedData),
     CpuExecuteResponse(<CpuExecutionView as DebugView>::RequestedData),
     IoResponse(<IoView as DebugView>::RequestedData),
+    LayerResponse(<LayerView as DebugView>::RequestedData),
+    HeatmapResponse(<HeatmapView as DebugView>::RequestedData),
 }
@@ -6,7 +6,9 @@ use serde::{Deserialize, Serialize};
 use crate::gui::debug::cpu_state_view::CpuStateView;
 use crate::gui::debug::emu_state::EmuStateView;
 use crate::gui::debug::execution_view::CpuExecutionView;
+use crate::gui::debug::heatmap_view::HeatmapView;
 use crate::gui::debug::io_view::IoView;
+use crate::gui::debug::layer_view::LayerView;
 use grba_core::emulator::debug::DebugEmulator;
 
 use crate::gui::debug::memory_view::MemoryEditorView;
@@ -17,7 +19,9 @@ mod colors;
 pub mod cpu_state_view;
 pub mod emu_state;
 pub mod execution_view;
+pub mod heatmap_view;
 pub mod io_view;
+pub mod layer_view;
 pub mod memory_view;
 pub mod messages;
 pub mod palette_view;
@@ -67,6 +71,10 @@ pub struct UiState {
     pub palette_open: bool,
     pub cpu_execute_open: bool,
     pub io_open: bool,
+    pub layers_open: bool,
+    pub heatmap_open: bool,
+    /// Whether the 8x8 tile grid is drawn on top of the game.
+    pub tile_grid_overlay: bool,
 }
 
 pub struct DebugViewManager {
@@ -76,6 +84,8 @@ pub struct DebugViewManager {
     palette_viewer: PaletteView,
     cpu_execution: CpuExecutionView,
     io_viewer: IoView,
+    layer_viewer: LayerView,
+    heatmap_viewer: HeatmapView,
 
     pub state: UiState,
 }
@@ -133,6 +143,24 @@ impl DebugViewManager {
 
                 (DebugMessageResponse::IoResponse(result), false)
             }
+            DebugMessageUi::LayerRequest(request, update) => {
+                if let Some(update) = update {
+                    LayerView::update_emu(emu, update);
+                }
+
+                let result = LayerView::prepare_frame(emu, request);
+
+                (DebugMessageResponse::LayerResponse(result), false)
+            }
+            DebugMessageUi::HeatmapRequest(request, update) => {
+                if let Some(update) = update {
+                    HeatmapView::update_emu(emu, update);
+                }
+
+                let result = HeatmapView::prepare_frame(emu, request);
+
+                (DebugMessageResponse::HeatmapResponse(result), false)
+            }
         }
     }
 }
@@ -146,6 +174,8 @@ impl DebugViewManager {
             palette_viewer: PaletteView::new(),
             cpu_execution: CpuExecutionView::new(),
             io_viewer: IoView::new(),
+            layer_viewer: LayerView::new(),
+            heatmap_viewer: HeatmapView::new(),
             state: ui_state.unwrap_or_default(),
         }
     }
@@ -169,6 +199,12 @@ impl DebugViewManager {
             DebugMessageResponse::IoResponse(data) => {
                 self.io_viewer.update_requested_data(data);
             }
+            DebugMessageResponse::LayerResponse(data) => {
+                self.layer_viewer.update_requested_data(data);
+            }
+            DebugMessageResponse::HeatmapResponse(data) => {
+                self.heatmap_viewer.update_requested_data(data);
+            }
         }
     }
 
@@ -207,6 +243,24 @@ impl DebugViewManager {
             if ui.checkbox(&mut self.state.palette_open, PaletteView::NAME).clicked() {
                 ui.close_menu();
             }
+
+            if ui.checkbox(&mut self.state.layers_open, LayerView::NAME).clicked() {
+                ui.close_menu();
+            }
+
+            if ui.checkbox(&mut self.state.heatmap_open, HeatmapView::NAME).clicked() {
+                ui.close_menu();
+            }
+
+            ui.separator();
+
+            if ui
+                .checkbox(&mut self.state.tile_grid_overlay, "Tile Grid Overlay")
+                .on_hover_text("Draw the boundaries of every 8x8 tile on top of the game")
+                .clicked()
+            {
+                ui.close_menu();
+            }
         });
     }
 
@@ -260,6 +314,20 @@ impl DebugViewManager {
             result.push(DebugMessageUi::PaletteRequest(request));
         }
 
+        if self.state.layers_open {
+            let response = self.layer_viewer.draw(ctx, &mut self.state.layers_open);
+            let request = self.layer_viewer.request_information();
+
+            result.push(DebugMessageUi::LayerRequest(request, response));
+        }
+
+        if self.state.heatmap_open {
+            let response = self.heatmap_viewer.draw(ctx, &mut self.state.heatmap_open);
+            let request = self.heatmap_viewer.request_information();
+
+            result.push(DebugMessageUi::HeatmapRequest(request, response));
+        }
+
         result
     }
 }
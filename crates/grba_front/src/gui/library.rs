@@ -0,0 +1,189 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crossbeam::channel::{Receiver, TryRecvError};
+use egui::{Context, TextStyle};
+
+use grba_core::emulator::cartridge::header::{CartridgeHeader, HEADER_SIZE};
+
+/// Lists all GBA ROMs in the configured ROM directory.
+pub struct Library {
+    /// The directory the current `entries` were scanned from.
+    directory: Option<PathBuf>,
+    entries: Vec<LibraryEntry>,
+    /// Receives entries while a scan is in progress, `None` once it has completed.
+    scan: Option<Receiver<LibraryEntry>>,
+}
+
+#[derive(Debug)]
+struct LibraryEntry {
+    path: PathBuf,
+    header: CartridgeHeader,
+}
+
+impl Library {
+    pub fn new() -> Self {
+        Self {
+            directory: None,
+            entries: Vec::new(),
+            scan: None,
+        }
+    }
+
+    /// Draw the library window.
+    ///
+    /// Double-clicking an entry loads it through [crate::State::load_rom_path].
+    pub fn draw(&mut self, ctx: &Context, open: &mut bool, state: &mut crate::State) {
+        if !*open {
+            return;
+        }
+
+        if self.directory != state.settings.rom_directory {
+            self.rescan(state.settings.rom_directory.clone());
+        }
+
+        self.receive_entries(ctx);
+
+        let mut to_load = None;
+        let mut refresh = false;
+
+        egui::containers::Window::new("Library")
+            .resizable(true)
+            .vscroll(true)
+            .open(open)
+            .show(ctx, |ui| {
+                let directory = match &self.directory {
+                    Some(directory) => directory,
+                    None => {
+                        ui.label("No ROM directory has been configured, set one in the settings.");
+                        return;
+                    }
+                };
+
+                ui.horizontal(|ui| {
+                    ui.label(directory.display().to_string());
+
+                    if self.scan.is_some() {
+                        ui.spinner();
+                    } else {
+                        refresh = ui.button("Refresh").clicked();
+                    }
+                });
+
+                ui.separator();
+
+                if self.entries.is_empty() && self.scan.is_none() {
+                    ui.label("No ROMs found");
+                    return;
+                }
+
+                ui.style_mut().override_text_style = Some(TextStyle::Monospace);
+
+                egui::Grid::new("Library Grid").striped(true).show(ui, |ui| {
+                    ui.label("Title");
+                    ui.label("Code");
+                    ui.label("File");
+                    ui.end_row();
+
+                    for entry in &self.entries {
+                        let file_name = entry
+                            .path
+                            .file_name()
+                            .map(|name| name.to_string_lossy())
+                            .unwrap_or_else(|| entry.path.to_string_lossy());
+
+                        if ui
+                            .selectable_label(false, &entry.header.game_title)
+                            .on_hover_text("Double-click to load")
+                            .double_clicked()
+                        {
+                            to_load = Some(entry.path.clone());
+                        }
+                        ui.label(&entry.header.game_code);
+                        ui.label(file_name);
+                        ui.end_row();
+                    }
+                });
+            });
+
+        if refresh {
+            self.rescan(self.directory.clone());
+        }
+
+        if let Some(path) = to_load {
+            state.load_rom_path(path);
+        }
+    }
+
+    /// Start scanning `directory` on a background thread, discarding the previous entries.
+    fn rescan(&mut self, directory: Option<PathBuf>) {
+        self.entries.clear();
+        self.scan = directory.clone().map(|directory| {
+            let (sender, receiver) = crossbeam::channel::unbounded();
+
+            std::thread::spawn(move || {
+                let dir = match std::fs::read_dir(&directory) {
+                    Ok(dir) => dir,
+                    Err(e) => {
+                        log::warn!("Failed to read ROM directory {:?} due to: {}", directory, e);
+                        return;
+                    }
+                };
+
+                for path in dir.flatten().map(|entry| entry.path()) {
+                    if let Some(header) = read_header(&path) {
+                        // A new scan was started in the meantime.
+                        if sender.send(LibraryEntry { path, header }).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+
+            receiver
+        });
+        self.directory = directory;
+    }
+
+    /// Add all entries found since the last frame, without blocking on the scan.
+    fn receive_entries(&mut self, ctx: &Context) {
+        let scan = match &self.scan {
+            Some(scan) => scan,
+            None => return,
+        };
+        let previous_len = self.entries.len();
+
+        let finished = loop {
+            match scan.try_recv() {
+                Ok(entry) => self.entries.push(entry),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        if self.entries.len() != previous_len {
+            self.entries
+                .sort_by(|a, b| a.header.game_title.cmp(&b.header.game_title));
+        }
+
+        if finished {
+            self.scan = None;
+        } else {
+            // Keep polling for new entries, even without user input.
+            ctx.request_repaint();
+        }
+    }
+}
+
+/// Parse the [CartridgeHeader] of the `.gba` file at `path`, without reading the entire ROM.
+fn read_header(path: &Path) -> Option<CartridgeHeader> {
+    if path.extension()?.to_str()? != "gba" {
+        return None;
+    }
+
+    // Reading just the header keeps scanning cheap.
+    let mut header = [0; HEADER_SIZE];
+    std::fs::File::open(path).ok()?.read_exact(&mut header).ok()?;
+
+    Some(CartridgeHeader::new(&header))
+}
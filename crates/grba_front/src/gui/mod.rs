@@ -1,5 +1,6 @@
 use egui::{ClippedPrimitive, Context, Memory, TexturesDelta};
 use egui_wgpu_backend::{BackendError, RenderPass, ScreenDescriptor};
+use grba_core::emulator::cartridge::header::CartBackupId;
 use pixels::{wgpu, PixelsContext};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -12,6 +13,9 @@ pub use debug::DebugViewManager;
 use crate::runner::messages::EmulatorMessage;
 
 mod debug;
+mod library;
+mod overlay;
+mod settings;
 
 /// Manages all state required for rendering egui over `Pixels`.
 pub struct EguiFramework {
@@ -74,12 +78,12 @@ impl EguiFramework {
     /// Prepare egui.
     ///
     /// Returns when the next repaint is expected (if `Duration::zero()` then immediate).
-    pub fn prepare(&mut self, window: &Window, state: &mut crate::State) -> Duration {
+    pub fn prepare(&mut self, window: &Window, state: &mut crate::State, fps: f32) -> Duration {
         // Run the egui frame and create all paint jobs to prepare for rendering.
         let raw_input = self.egui_state.take_egui_input(window);
         let full_output = self.egui_ctx.run(raw_input, |egui_ctx| {
             // Draw the demo application.
-            self.gui.ui(egui_ctx, state);
+            self.gui.ui(egui_ctx, state, fps);
         });
 
         self.textures.append(full_output.textures_delta);
@@ -151,6 +155,11 @@ pub struct AppUiState {
 pub struct Gui {
     /// Only show the egui window when true.
     window_open: bool,
+    /// Whether the settings window is open.
+    settings_open: bool,
+    /// Whether the library window is open.
+    library_open: bool,
+    library: library::Library,
 
     pub debug_view: DebugViewManager,
 }
@@ -160,15 +169,81 @@ impl Gui {
     fn new(ui_state: Option<debug::UiState>) -> Self {
         Self {
             window_open: true,
+            settings_open: false,
+            library_open: false,
+            library: library::Library::new(),
             debug_view: DebugViewManager::new(ui_state),
         }
     }
 
     /// Create the UI using egui.
-    fn ui(&mut self, ctx: &Context, state: &mut crate::State) {
+    fn ui(&mut self, ctx: &Context, state: &mut crate::State, fps: f32) {
         // let now = Instant::now();
         egui::TopBottomPanel::top("menubar_container").show(ctx, |ui| {
             egui::menu::bar(ui, |ui| {
+                ui.menu_button("File", |ui| {
+                    if ui.button("Open ROM...").clicked() {
+                        ui.close_menu();
+
+                        let mut dialog = rfd::FileDialog::new()
+                            .set_title("Open ROM")
+                            .add_filter("GBA ROM", &["gba", "elf", "mb"]);
+
+                        if let Some(dir) = &state.settings.rom_directory {
+                            dialog = dialog.set_directory(dir);
+                        }
+
+                        if let Some(path) = dialog.pick_file() {
+                            state.load_rom_path(path);
+                        }
+                    }
+
+                    ui.menu_button("Open Recent", |ui| {
+                        if state.recent_roms.is_empty() {
+                            ui.label("No recent ROMs");
+                        }
+
+                        let mut to_load = None;
+
+                        for path in &state.recent_roms {
+                            let name = path
+                                .file_name()
+                                .map(|name| name.to_string_lossy())
+                                .unwrap_or_else(|| path.to_string_lossy());
+
+                            if ui.button(name).on_hover_text(path.display().to_string()).clicked() {
+                                to_load = Some(path.clone());
+                            }
+                        }
+
+                        if let Some(path) = to_load {
+                            state.load_rom_path(path);
+                            ui.close_menu();
+                        }
+
+                        if !state.recent_roms.is_empty() {
+                            ui.separator();
+
+                            if ui.button("Clear Recent").clicked() {
+                                state.recent_roms.clear();
+                                ui.close_menu();
+                            }
+                        }
+                    });
+
+                    if ui.button("Library...").clicked() {
+                        self.library_open = true;
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+
+                    if ui.button("Settings...").clicked() {
+                        self.settings_open = true;
+                        ui.close_menu();
+                    }
+                });
+
                 ui.menu_button("Emulation", |ui| {
                     if ui.checkbox(&mut state.paused, "Pause (K)").clicked() {
                         state.pause(state.paused);
@@ -181,12 +256,80 @@ impl Gui {
                         }
                         ui.close_menu()
                     }
+
+                    let mut skip_bios = state.bios.should_skip;
+                    if ui
+                        .checkbox(&mut skip_bios, "Skip BIOS")
+                        .on_hover_text(
+                            "Boot straight into the cartridge instead of playing the BIOS intro, resets the emulator",
+                        )
+                        .clicked()
+                    {
+                        state.set_skip_bios(skip_bios);
+                        ui.close_menu()
+                    }
+
+                    if let Some(detected) = state.current_header.as_ref().map(|header| header.backup_id) {
+                        ui.menu_button("Save Type", |ui| {
+                            let mut backup_override = state.backup_override();
+                            let mut changed = ui
+                                .radio_value(&mut backup_override, None, format!("Detected ({:?})", detected))
+                                .on_hover_text("Use the save type found in the ROM")
+                                .clicked();
+
+                            for backup_id in enum_iterator::all::<CartBackupId>() {
+                                changed |= ui
+                                    .radio_value(&mut backup_override, Some(backup_id), format!("{:?}", backup_id))
+                                    .clicked();
+                            }
+
+                            if changed {
+                                state.set_backup_override(backup_override);
+                                ui.close_menu()
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Force the save type for games where it's detected incorrectly, resets the emulator",
+                        );
+                    }
+
+                    if ui
+                        .add_enabled(state.current_emu.is_some(), egui::Button::new("Copy Frame (F12)"))
+                        .on_hover_text("Copy the current frame to the clipboard")
+                        .clicked()
+                    {
+                        state.copy_frame_requested = true;
+                        ui.close_menu();
+                    }
+
+                    if ui
+                        .button("Dump PPU Memory...")
+                        .on_hover_text("Dump the raw VRAM, OAM, and palette RAM for use in external tools")
+                        .clicked()
+                    {
+                        ui.close_menu();
+
+                        if let Some(emu) = state.current_emu.as_ref() {
+                            if let Some(path) = rfd::FileDialog::new().set_title("Dump PPU Memory").pick_folder() {
+                                emu.request_sender.send(EmulatorMessage::DumpPpuMemory(path)).unwrap();
+                            }
+                        }
+                    }
                 });
 
                 self.debug_view.draw_menu_button(ui);
             });
         });
 
+        if state.settings.show_speed_overlay {
+            overlay::draw_speed_overlay(ctx, fps);
+        }
+
+        settings::draw_settings(ctx, &mut self.settings_open, state);
+        settings::draw_bios_prompt(ctx, state);
+        self.library.draw(ctx, &mut self.library_open, state);
+
         let requests = self.debug_view.draw(ctx);
 
         if let Some(emu) = state.current_emu.as_ref() {
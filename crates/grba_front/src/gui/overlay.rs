@@ -0,0 +1,17 @@
+use egui::{Align2, Context, RichText, TextStyle, Vec2};
+
+use crate::rendering::speed_percentage;
+
+/// Draw the current FPS and emulation speed in the top right corner, on top of the game.
+pub fn draw_speed_overlay(ctx: &Context, fps: f32) {
+    egui::Area::new("Speed Overlay")
+        .anchor(Align2::RIGHT_TOP, Vec2::new(-8.0, 32.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                let text = format!("{:.1} FPS | {:.0}%", fps, speed_percentage(fps));
+
+                ui.label(RichText::new(text).text_style(TextStyle::Monospace));
+            });
+        });
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+
+use egui::{Context, Ui};
+use grba_core::emulator::EmuOptions;
+
+use crate::config::{FramePacing, ScalingMode, SpeedKeyMode};
+
+/// Draw the settings window, where the BIOS, default ROM directory, frame pacing, scaling, fullscreen, speed controls,
+/// overlay, and focus behaviour can be configured.
+pub fn draw_settings(ctx: &Context, open: &mut bool, state: &mut crate::State) {
+    egui::containers::Window::new("Settings")
+        .resizable(false)
+        .collapsible(false)
+        .open(open)
+        .show(ctx, |ui| {
+            egui::Grid::new("Settings Grid").num_columns(2).show(ui, |ui| {
+                ui.label("BIOS:");
+                let bios = state.settings.bios_path.clone();
+                if let Some(bios) = path_picker(ui, bios.as_ref(), "bios", false) {
+                    state.set_bios_path(bios);
+                }
+                ui.end_row();
+
+                ui.label("ROM Directory:");
+                let rom_directory = state.settings.rom_directory.clone();
+                if let Some(directory) = path_picker(ui, rom_directory.as_ref(), "rom_dir", true) {
+                    state.settings.rom_directory = directory;
+                }
+                ui.end_row();
+
+                ui.label("Frame Pacing:");
+                egui::ComboBox::new("FramePacing", "")
+                    .selected_text(format!("{:?}", state.settings.frame_pacing))
+                    .show_ui(ui, |ui| {
+                        for pacing in [FramePacing::Fixed, FramePacing::VSync] {
+                            ui.selectable_value(&mut state.settings.frame_pacing, pacing, format!("{:?}", pacing));
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Fixed runs at the GBA's refresh rate, VSync follows the monitor. Requires a restart",
+                    );
+                ui.end_row();
+
+                ui.label("Scaling:");
+                egui::ComboBox::new("ScalingMode", "")
+                    .selected_text(format!("{:?}", state.settings.scaling_mode))
+                    .show_ui(ui, |ui| {
+                        for mode in [ScalingMode::Integer, ScalingMode::AspectFit, ScalingMode::Stretch] {
+                            ui.selectable_value(&mut state.settings.scaling_mode, mode, format!("{:?}", mode));
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Integer keeps all pixels the same size, AspectFit fills as much as possible while keeping \
+                         the aspect ratio, Stretch fills everything",
+                    );
+                ui.end_row();
+
+                ui.label("Remember Fullscreen (F11):");
+                ui.checkbox(&mut state.settings.remember_fullscreen, "")
+                    .on_hover_text("Start in fullscreen if the window was fullscreen when it was last closed");
+                ui.end_row();
+
+                ui.label("Frame Blending:");
+                ui.checkbox(&mut state.settings.frame_blending, "")
+                    .on_hover_text("Blend every frame with the previous one, reducing the flicker some games rely on");
+                ui.end_row();
+
+                ui.label("CPU Clock Scale:");
+                ui.add(
+                    egui::DragValue::new(&mut state.settings.clock_scale)
+                        .clamp_range(EmuOptions::CLOCK_SCALE_RANGE)
+                        .speed(0.01)
+                        .suffix("x"),
+                )
+                .on_hover_text(
+                    "Run the CPU faster or slower than the rest of the system, for debugging timing issues. \
+                     Anything but 1x results in inaccurate timing! Applies when a ROM is (re)loaded",
+                );
+                ui.end_row();
+
+                ui.label("Fast Forward (LShift):");
+                ui.horizontal(|ui| {
+                    speed_key_mode_picker(ui, "FastForwardMode", &mut state.settings.fast_forward_mode);
+                    ui.add(
+                        egui::DragValue::new(&mut state.settings.fast_forward_multiplier)
+                            .clamp_range(2..=16)
+                            .suffix("x"),
+                    );
+                });
+                ui.end_row();
+
+                ui.label("Unbounded (U):");
+                speed_key_mode_picker(ui, "UnboundedMode", &mut state.settings.unbounded_mode);
+                ui.end_row();
+
+                ui.label("Speed Overlay:");
+                ui.checkbox(&mut state.settings.show_speed_overlay, "")
+                    .on_hover_text("Show the current FPS and emulation speed on top of the game");
+                ui.end_row();
+
+                ui.label("Pause on Focus Loss:");
+                ui.checkbox(&mut state.settings.pause_on_focus_loss, "")
+                    .on_hover_text("Pause while the window isn't focused, resuming once it is again");
+                ui.end_row();
+            });
+
+            if let Some(bios) = state.bios.bios_location.as_ref() {
+                if state.settings.bios_path.as_ref() != Some(bios) {
+                    ui.label(format!("Currently using BIOS: {}", bios.display()));
+                }
+            }
+        });
+}
+
+fn speed_key_mode_picker(ui: &mut Ui, id: &str, mode: &mut SpeedKeyMode) {
+    egui::ComboBox::new(id, "")
+        .selected_text(format!("{:?}", mode))
+        .show_ui(ui, |ui| {
+            for option in [SpeedKeyMode::Held, SpeedKeyMode::Toggle] {
+                ui.selectable_value(mode, option, format!("{:?}", option));
+            }
+        });
+}
+
+/// Draw a prompt asking for a BIOS if a ROM was loaded without one being configured.
+pub fn draw_bios_prompt(ctx: &Context, state: &mut crate::State) {
+    if state.pending_cartridge.is_none() {
+        return;
+    }
+
+    egui::containers::Window::new("No BIOS")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(ctx, |ui| {
+            ui.label("No valid BIOS has been configured.");
+            ui.label("Select a GBA BIOS, or continue without one (the BIOS will be skipped).");
+
+            ui.horizontal(|ui| {
+                if ui.button("Select BIOS...").clicked() {
+                    if let Some(path) = pick_bios() {
+                        state.set_bios_path(Some(path));
+                    }
+                }
+
+                if ui.button("Continue without BIOS").clicked() {
+                    state.load_pending_without_bios();
+                }
+
+                if ui.button("Cancel").clicked() {
+                    state.pending_cartridge = None;
+                    state.current_header = None;
+                }
+            });
+        });
+}
+
+/// Show the current `path` with buttons to change or clear it.
+///
+/// # Returns
+///
+/// `Some` if the path was changed, where the inner [Option] is the new path.
+fn path_picker(ui: &mut Ui, path: Option<&PathBuf>, id: &str, folder: bool) -> Option<Option<PathBuf>> {
+    let mut result = None;
+
+    ui.push_id(id, |ui| {
+        ui.horizontal(|ui| {
+            let text = path
+                .map(|path| path.display().to_string())
+                .unwrap_or_else(|| "Not set".to_string());
+            ui.label(text);
+
+            if ui.button("Browse...").clicked() {
+                let picked = if folder { rfd::FileDialog::new().pick_folder() } else { pick_bios() };
+
+                if let Some(picked) = picked {
+                    result = Some(Some(picked));
+                }
+            }
+
+            if path.is_some() && ui.button("Clear").clicked() {
+                result = Some(None);
+            }
+        });
+    });
+
+    result
+}
+
+fn pick_bios() -> Option<PathBuf> {
+    rfd::FileDialog::new()
+        .set_title("Select BIOS")
+        .add_filter("GBA BIOS", &["bin", "gba"])
+        .pick_file()
+}
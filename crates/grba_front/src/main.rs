@@ -1,16 +1,17 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 use log::LevelFilter;
 use winit::event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
 
-use grba_core::emulator::cartridge::header::CartridgeHeader;
+use grba_core::emulator::cartridge::header::{CartBackupId, CartridgeHeader};
 use grba_core::emulator::cartridge::Cartridge;
+use grba_core::emulator::frame::RgbaFrame;
 
 use crate::gui::EguiFramework;
 use crate::rendering::{Renderer, RendererOptions};
-use crate::runner::messages::EmulatorResponse;
+use crate::runner::messages::{EmulatorMessage, EmulatorResponse};
 use crate::runner::{EmulatorRunner, RunnerHandle};
 use crate::utils::MainArgs;
 
@@ -31,10 +32,14 @@ fn main() {
 
     simplelog::SimpleLogger::init(LevelFilter::Trace, cfg).unwrap();
 
-    #[cfg(feature = "bin-logging")]
-    debug::setup_emulator_logger("./emu.logbin").expect("Failed to setup bin logger");
-
     let cli_options = utils::parse_main_args().expect("Failed to parse arguments");
+
+    #[cfg(feature = "bin-logging")]
+    if let Some(reference) = &cli_options.reference_trace {
+        debug::setup_trace_comparer(reference).expect("Failed to load the reference trace");
+    } else {
+        debug::setup_emulator_logger("./emu.logbin").expect("Failed to setup bin logger");
+    }
     let application = Application::new(cli_options).expect("Failed to create application");
 
     let _ = application.run();
@@ -59,9 +64,19 @@ impl Application {
 
     pub fn new(cli_options: MainArgs) -> anyhow::Result<Application> {
         let gui_state = config::deserialise_state_and_config();
+        let config::Config { recent_roms, settings } = config::load_config();
         let event_loop = EventLoop::new();
         let input = winit_input_helper::WinitInputHelper::new();
-        let renderer = Renderer::new(&event_loop, RendererOptions::default())?;
+        let renderer_options = RendererOptions {
+            present_mode: settings.frame_pacing.present_mode(),
+            ..Default::default()
+        };
+        let renderer = Renderer::new(&event_loop, renderer_options)?;
+
+        if settings.remember_fullscreen && settings.fullscreen {
+            renderer.set_fullscreen(true);
+        }
+
         let gui = EguiFramework::new(
             crate::WIDTH,
             crate::HEIGHT,
@@ -72,7 +87,7 @@ impl Application {
         );
 
         Ok(Application {
-            state: State::new(cli_options),
+            state: State::new(cli_options, recent_roms, settings),
             gui,
             renderer,
             input,
@@ -107,15 +122,12 @@ impl Application {
                     match event {
                         WindowEvent::DroppedFile(path) => {
                             log::debug!("Dropped file: {:?}", path);
-                            let rom = load_gba_cartridge(path);
-
-                            if let Some(rom) = rom {
-                                self.state.load_cartridge(rom);
-                            }
+                            self.state.load_rom_path(path);
                         }
                         WindowEvent::KeyboardInput { input, .. } => {
                             handle_key(input, &mut self.state, &mut self.renderer);
                         }
+                        WindowEvent::Focused(focused) => self.state.handle_focus(focused),
                         _ => {}
                     };
                 }
@@ -171,7 +183,14 @@ impl Application {
                     }
                 }
                 Event::LoopDestroyed => {
-                    config::save_state_and_config(&self.gui).expect("Failed to save state & config");
+                    // Stopping the emulator flushes the save to disk.
+                    if let Some(emu) = self.state.current_emu.take() {
+                        emu.stop();
+                    }
+
+                    config::save_state_and_config(&self.gui, &self.state).expect("Failed to save state & config");
+
+                    std::process::exit(self.state.exit_code);
                 }
                 _ => (),
             }
@@ -184,19 +203,27 @@ impl Application {
         gui: &mut EguiFramework,
         control_flow: &mut ControlFlow,
     ) -> anyhow::Result<()> {
-        let frame = match &mut state.current_emu {
+        let (frame, final_frame) = match &mut state.current_emu {
             Some(emu) => {
                 // Handle emulator responses to our messages
-                Self::handle_debug_messages(gui, control_flow, emu);
+                let final_frame = Self::handle_responses(gui, emu);
 
                 // Try receive a frame to clear up the emulator in case it's waiting for a new frame to come in.
                 // Converting to vec is dreadful, but pulling the emulator out of state is worse atm.
-                emu.frame_receiver.try_recv_or_recent().as_bytes().to_vec()
+                (emu.frame_receiver.try_recv_or_recent().as_bytes().to_vec(), final_frame)
             }
-            None => vec![0; grba_core::FRAMEBUFFER_SIZE * 4],
+            None => (vec![0; grba_core::FRAMEBUFFER_SIZE * 4], None),
         };
 
+        if let Some(final_frame) = final_frame {
+            if state.frame_limit_reached(final_frame.as_bytes()) {
+                *control_flow = ControlFlow::Exit;
+                return Ok(());
+            }
+        }
+
         renderer.render_pixels(&frame, gui, state)?;
+        state.handle_copy_request(&frame);
 
         Ok(())
     }
@@ -210,6 +237,10 @@ impl Application {
     ) -> anyhow::Result<()> {
         // Determine if we need to wait.
         match state.run_state {
+            // Presenting the frame will already block until the next vertical blank.
+            RunningState::FrameLimited if renderer.is_vsync() => {
+                *wait_to = Instant::now();
+            }
             RunningState::FrameLimited | RunningState::FastForward(_) => {
                 let now = Instant::now();
 
@@ -235,27 +266,47 @@ impl Application {
         };
 
         for _ in 0..frames_to_render {
-            let frame = {
-                let emu = state.current_emu.as_mut().unwrap();
-                // Handle emulator responses to our messages
-                Self::handle_debug_messages(gui, control_flow, emu);
+            let emu = state.current_emu.as_mut().unwrap();
 
-                emu.frame_receiver.recv()?.as_bytes().to_vec()
-            };
+            // Handle emulator responses to our messages
+            if let Some(final_frame) = Self::handle_responses(gui, emu) {
+                // The emulator has paused itself, so there won't be another frame to wait for.
+                if state.frame_limit_reached(final_frame.as_bytes()) {
+                    *control_flow = ControlFlow::Exit;
+                }
+                break;
+            }
+
+            let frame = emu.frame_receiver.recv()?.as_bytes().to_vec();
 
             // Render result and send debug requests
             renderer.render_pixels(&frame, gui, state)?;
+            state.handle_copy_request(&frame);
+
+            if state.paused {
+                break;
+            }
         }
 
         Ok(())
     }
 
-    fn handle_debug_messages(gui: &mut EguiFramework, _control_flow: &mut ControlFlow, emu: &mut RunnerHandle) {
+    /// Handle all responses the emulator thread sent in the meantime.
+    ///
+    /// # Returns
+    ///
+    /// The final frame if the emulator reached its frame limit, see [EmulatorResponse::FrameLimitReached].
+    fn handle_responses(gui: &mut EguiFramework, emu: &mut RunnerHandle) -> Option<RgbaFrame> {
+        let mut final_frame = None;
+
         while let Ok(response) = emu.response_receiver.try_recv() {
             match response {
                 EmulatorResponse::Debug(msg) => gui.gui.debug_view.handle_response_message(msg),
+                EmulatorResponse::FrameLimitReached(frame) => final_frame = Some(frame),
             }
         }
+
+        final_frame
     }
 }
 
@@ -281,13 +332,37 @@ pub struct State {
     pub run_state: RunningState,
     /// Whether the emulator is paused
     pub paused: bool,
+    /// Whether the current pause was caused by the window losing focus, rather than by the user.
+    paused_by_focus_loss: bool,
     /// The location of the BIOS file.
     pub bios: BiosState,
+    /// The most recently loaded ROMs, with the most recent one first.
+    pub recent_roms: Vec<PathBuf>,
+    pub settings: config::Settings,
+    /// A cartridge which is waiting for the user to select a BIOS (or to continue without one) before it's started.
+    pub pending_cartridge: Option<Cartridge>,
+    /// Set when the emulator should only run for a fixed amount of frames.
+    pub frame_limit: Option<FrameLimit>,
+    /// The exit code the application should exit with.
+    pub exit_code: i32,
+    /// Set to copy the next drawn frame to the clipboard.
+    pub copy_frame_requested: bool,
+    /// Kept alive as some platforms clear the clipboard contents once it's dropped.
+    clipboard: Option<arboard::Clipboard>,
+}
+
+pub struct FrameLimit {
+    /// The amount of frames to emulate, as counted by the emulator itself.
+    pub frames: u32,
+    /// Whether the application should exit once all frames have been emulated, otherwise the emulator is paused.
+    pub then_exit: bool,
+    /// Where to save a screenshot of the last emulated frame.
+    pub screenshot_path: Option<PathBuf>,
 }
 
 pub struct BiosState {
-    /// The location of the BIOS file.
-    pub bios_location: PathBuf,
+    /// The location of the BIOS file, if one is known.
+    pub bios_location: Option<PathBuf>,
     /// Whether the bios should be skipped or not.
     pub should_skip: bool,
 }
@@ -312,24 +387,81 @@ impl State {
     pub fn run_default(&mut self) {
         self.run_state = RunningState::FrameLimited;
     }
+
+    /// Handle a press (or release) of a speed control key which runs the emulator at `target` speed.
+    ///
+    /// With [config::SpeedKeyMode::Held] the `target` only applies until the key is released, whereas
+    /// [config::SpeedKeyMode::Toggle] switches between `target` and the default on every release.
+    pub fn handle_speed_key(&mut self, target: RunningState, mode: config::SpeedKeyMode, pressed: bool) {
+        // Compare variants only, the fast forward multiplier could've been changed in the meantime.
+        let is_active = std::mem::discriminant(&self.run_state) == std::mem::discriminant(&target);
+
+        match mode {
+            config::SpeedKeyMode::Held if pressed => self.run_state = target,
+            config::SpeedKeyMode::Held if is_active => self.run_default(),
+            config::SpeedKeyMode::Toggle if !pressed => {
+                if is_active {
+                    self.run_default();
+                } else {
+                    self.run_state = target;
+                }
+            }
+            _ => {}
+        }
+
+        log::debug!("Run State: {:?}", self.run_state);
+    }
 }
 
 impl State {
-    pub fn new(cli_options: MainArgs) -> Self {
+    /// The maximum amount of ROMs to remember in [Self::recent_roms].
+    pub const MAX_RECENT_ROMS: usize = 10;
+
+    /// The BIOS to use if neither `--bios` nor the settings provide one.
+    const DEFAULT_BIOS_PATH: &'static str = "roms/gba_bios.bin";
+
+    pub fn new(cli_options: MainArgs, recent_roms: Vec<PathBuf>, settings: config::Settings) -> Self {
+        let bios_location = cli_options
+            .bios
+            .or_else(|| settings.bios_path.clone())
+            .or_else(|| Some(PathBuf::from(Self::DEFAULT_BIOS_PATH)).filter(|path| path.exists()));
+
         let mut result = Self {
             current_emu: None,
             current_header: None,
             run_state: RunningState::FrameLimited,
             paused: false,
+            paused_by_focus_loss: false,
             bios: BiosState {
-                bios_location: cli_options.bios,
+                bios_location,
                 should_skip: !cli_options.start_bios,
             },
+            recent_roms,
+            settings,
+            pending_cartridge: None,
+            frame_limit: None,
+            exit_code: 0,
+            copy_frame_requested: false,
+            clipboard: None,
         };
 
+        if let Some(frames) = cli_options.frames {
+            result.frame_limit = Some(FrameLimit {
+                frames,
+                then_exit: cli_options.then_exit,
+                screenshot_path: cli_options.screenshot_on_exit,
+            });
+
+            // Only a completed run counts as a success.
+            if cli_options.then_exit {
+                result.exit_code = 1;
+            }
+        }
+
         // Set the initial state according to our CLI parameters
         if let Some(initial_rom) = cli_options.execute_path {
-            let cartridge = load_gba_cartridge(initial_rom).expect("Initial ROM was an invalid GBA cartridge");
+            let cartridge = load_gba_cartridge(&initial_rom).expect("Initial ROM was an invalid GBA cartridge");
+            result.add_recent_rom(initial_rom);
             result.load_cartridge(cartridge);
             result.pause(cli_options.start_paused)
         }
@@ -337,17 +469,184 @@ impl State {
         result
     }
 
+    /// Copy the given `frame` to the clipboard if [Self::copy_frame_requested] is set.
+    pub fn handle_copy_request(&mut self, frame: &[u8]) {
+        if !std::mem::take(&mut self.copy_frame_requested) {
+            return;
+        }
+
+        let clipboard = match &mut self.clipboard {
+            Some(clipboard) => clipboard,
+            None => match arboard::Clipboard::new() {
+                Ok(clipboard) => self.clipboard.insert(clipboard),
+                Err(e) => {
+                    log::error!("Failed to open the clipboard due to: {}", e);
+                    return;
+                }
+            },
+        };
+
+        match utils::copy_frame_to_clipboard(clipboard, frame) {
+            Ok(_) => log::info!("Copied frame to the clipboard"),
+            Err(e) => log::error!("Failed to copy frame to the clipboard due to: {:#}", e),
+        }
+    }
+
+    /// Should be called once the emulator has reached the [FrameLimit], with the last `frame` it emulated.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the application should exit.
+    pub fn frame_limit_reached(&mut self, frame: &[u8]) -> bool {
+        let limit = match self.frame_limit.take() {
+            Some(limit) => limit,
+            None => return false,
+        };
+
+        self.exit_code = 0;
+
+        if let Some(path) = limit.screenshot_path {
+            match utils::save_rgba_frame(frame, &path) {
+                Ok(_) => log::info!("Saved screenshot to: {:?}", path),
+                Err(e) => {
+                    log::error!("Failed to save screenshot to {:?} due to: {:#}", path, e);
+                    self.exit_code = 1;
+                }
+            }
+        }
+
+        if limit.then_exit {
+            true
+        } else {
+            // The emulator already paused itself.
+            self.paused = true;
+            self.paused_by_focus_loss = false;
+            false
+        }
+    }
+
+    /// Load the GBA ROM located at `path` and add it to the recently loaded ROMs.
+    ///
+    /// If the file is not a valid GBA ROM the current emulator (if any) keeps running.
+    pub fn load_rom_path(&mut self, path: PathBuf) {
+        match load_gba_cartridge(&path) {
+            Some(cartridge) => {
+                self.add_recent_rom(path);
+                self.load_cartridge(cartridge);
+            }
+            None => log::warn!("Failed to load ROM: {:?}", path),
+        }
+    }
+
+    /// Move `path` to the front of the recent ROMs list, evicting the oldest entry if the list is full.
+    pub fn add_recent_rom(&mut self, path: PathBuf) {
+        let path = path.canonicalize().unwrap_or(path);
+
+        self.recent_roms.retain(|recent| *recent != path);
+        self.recent_roms.insert(0, path);
+        self.recent_roms.truncate(Self::MAX_RECENT_ROMS);
+    }
+
     pub fn load_cartridge(&mut self, cartridge: Cartridge) {
+        // Ensure the previous emulator thread is shut down before we start a new one.
+        if let Some(emu) = self.current_emu.take() {
+            emu.stop();
+        }
+
         self.current_header = Some(cartridge.header().clone());
-        let bios = std::fs::read(&self.bios.bios_location).unwrap();
 
-        let runner = EmulatorRunner::new(cartridge, Some(bios));
-        self.current_emu = Some(runner.run(self.paused, self.bios.should_skip));
+        let bios = match self.bios.bios_location.as_ref().map(std::fs::read) {
+            Some(Ok(bios)) => bios,
+            Some(Err(e)) => {
+                log::warn!("Failed to read BIOS at {:?} due to: {}", self.bios.bios_location, e);
+                self.pending_cartridge = Some(cartridge);
+                return;
+            }
+            None => {
+                self.pending_cartridge = Some(cartridge);
+                return;
+            }
+        };
+
+        self.start_emulator(cartridge, Some(bios));
+    }
+
+    /// Start the cartridge waiting in [Self::pending_cartridge] without a BIOS, forcing the BIOS to be skipped.
+    pub fn load_pending_without_bios(&mut self) {
+        if let Some(cartridge) = self.pending_cartridge.take() {
+            self.start_emulator(cartridge, None);
+        }
+    }
+
+    /// Set the BIOS to use, and persist it in the settings.
+    ///
+    /// If a cartridge was waiting for a BIOS it will be started.
+    pub fn set_bios_path(&mut self, path: Option<PathBuf>) {
+        self.settings.bios_path = path.clone();
+        self.bios.bios_location = path;
+
+        if let Some(cartridge) = self.pending_cartridge.take() {
+            self.load_cartridge(cartridge);
+        }
+    }
+
+    fn start_emulator(&mut self, cartridge: Cartridge, bios: Option<Vec<u8>>) {
+        let backup_override = self
+            .settings
+            .backup_overrides
+            .get(&cartridge.header().game_code)
+            .copied();
+        let runner = EmulatorRunner::new(cartridge, bios);
+        self.current_emu = Some(runner.run(
+            self.paused,
+            self.bios.should_skip,
+            backup_override,
+            self.settings.clock_scale,
+            self.frame_limit.as_ref().map(|limit| u64::from(limit.frames)),
+        ));
+    }
+
+    /// The backup type the user forced for the current game, if any.
+    pub fn backup_override(&self) -> Option<CartBackupId> {
+        let header = self.current_header.as_ref()?;
+
+        self.settings.backup_overrides.get(&header.game_code).copied()
+    }
+
+    /// Force the backup type of the current game, persisting it in the settings, and reset the emulator to apply it.
+    ///
+    /// `None` goes back to the autodetected type.
+    pub fn set_backup_override(&mut self, backup_id: Option<CartBackupId>) {
+        let game_code = if let Some(header) = &self.current_header {
+            header.game_code.clone()
+        } else {
+            return;
+        };
+
+        match backup_id {
+            Some(backup_id) => self.settings.backup_overrides.insert(game_code, backup_id),
+            None => self.settings.backup_overrides.remove(&game_code),
+        };
+
+        if let Some(emu) = &self.current_emu {
+            let _ = emu.request_sender.send(EmulatorMessage::SetBackupOverride(backup_id));
+        }
+    }
+
+    /// Choose whether to boot straight into the cartridge or run the BIOS intro, resetting the current emulator.
+    pub fn set_skip_bios(&mut self, skip_bios: bool) {
+        self.bios.should_skip = skip_bios;
+
+        if let Some(emu) = &self.current_emu {
+            let _ = emu.request_sender.send(EmulatorMessage::SetSkipBios(skip_bios));
+        }
     }
 
     pub fn pause(&mut self, pause: bool) {
         log::debug!("Pausing: {}", pause);
         self.paused = pause;
+        // Any explicit (un)pause takes precedence over the automatic one.
+        self.paused_by_focus_loss = false;
 
         // Send a message to the emulator thread to pause/unpause
         if let Some(emu) = &self.current_emu {
@@ -358,13 +657,25 @@ impl State {
             }
         }
     }
+
+    /// Pause the emulator when the window loses focus if [config::Settings::pause_on_focus_loss] is set.
+    ///
+    /// Regaining focus only resumes emulation if it was paused by the focus loss, a manual pause is left as is.
+    pub fn handle_focus(&mut self, focused: bool) {
+        if !focused && self.settings.pause_on_focus_loss && !self.paused {
+            self.pause(true);
+            self.paused_by_focus_loss = true;
+        } else if focused && self.paused_by_focus_loss {
+            self.pause(false);
+        }
+    }
 }
 
-fn load_gba_cartridge(path: PathBuf) -> Option<Cartridge> {
+fn load_gba_cartridge(path: &Path) -> Option<Cartridge> {
     let extension = path.extension()?.to_str()?;
 
-    if extension == "gba" {
-        let contents = std::fs::read(&path).ok()?;
+    if extension == "gba" || extension == "elf" || extension == "mb" {
+        let contents = std::fs::read(path).ok()?;
         let parent_dir = path.parent()?;
         let file_name = path.file_name()?.to_string_lossy();
 
@@ -380,9 +691,19 @@ fn load_gba_cartridge(path: PathBuf) -> Option<Cartridge> {
         let mut mm = memmap2::MmapOptions::new();
         let map = unsafe { mm.populate().map_mut(&file).ok()? };
 
-        let cart = Cartridge::new(contents, Box::new(map));
+        let cartridge = match extension {
+            "elf" => Cartridge::from_elf(&contents, Box::new(map)),
+            "mb" => Cartridge::from_multiboot(&contents, Box::new(map)),
+            _ => Cartridge::new(contents, Box::new(map)),
+        };
 
-        Some(cart)
+        match cartridge {
+            Ok(cart) => Some(cart),
+            Err(e) => {
+                log::error!("Failed to load {:?} due to: {}", path, e);
+                None
+            }
+        }
     } else {
         None
     }
@@ -400,26 +721,25 @@ fn handle_key(input: KeyboardInput, state: &mut State, renderer: &mut Renderer)
         return;
     };
 
+    let pressed = input.state == ElementState::Pressed;
+
     match key {
-        VirtualKeyCode::U if input.state == ElementState::Released => {
-            log::debug!("Run State: {:?}", state.run_state);
-            if state.run_state == RunningState::Unbounded {
-                state.run_default();
-            } else {
-                state.run_unbounded();
-            }
+        VirtualKeyCode::U => {
+            state.handle_speed_key(RunningState::Unbounded, state.settings.unbounded_mode, pressed);
         }
         VirtualKeyCode::LShift => {
-            if input.state == ElementState::Released {
-                state.run_default();
-            } else {
-                state.run_fast_forward(4);
-            }
+            let target = RunningState::FastForward(state.settings.fast_forward_multiplier);
+            state.handle_speed_key(target, state.settings.fast_forward_mode, pressed);
         }
         VirtualKeyCode::K if input.state == ElementState::Released => {
             state.pause(!state.paused);
         }
-        VirtualKeyCode::F11 if input.state == ElementState::Released => renderer.toggle_fullscreen(),
+        VirtualKeyCode::F11 if input.state == ElementState::Released => {
+            state.settings.fullscreen = renderer.toggle_fullscreen();
+        }
+        VirtualKeyCode::F12 if input.state == ElementState::Released && state.current_emu.is_some() => {
+            state.copy_frame_requested = true;
+        }
         _ => {}
     }
 }
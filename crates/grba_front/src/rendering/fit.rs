@@ -0,0 +1,153 @@
+use pixels::{wgpu, PixelsContext};
+
+use crate::config::ScalingMode;
+
+/// Draws the emulator frame with non-integer scaling, used for [ScalingMode::AspectFit] and [ScalingMode::Stretch].
+///
+/// [ScalingMode::Integer] is handled by the [pixels::ScalingRenderer] instead.
+pub struct FitRenderer {
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+}
+
+impl FitRenderer {
+    pub fn new(context: &PixelsContext, render_texture_format: wgpu::TextureFormat) -> Self {
+        let device = &context.device;
+        let shader = device.create_shader_module(wgpu::include_wgsl!("fit.wgsl"));
+
+        let texture_view = context.texture.create_view(&wgpu::TextureViewDescriptor::default());
+        // Nearest filtering keeps the pixels sharp, at the cost of some being slightly wider than others.
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("fit_renderer_sampler"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("fit_renderer_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("fit_renderer_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("fit_renderer_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("fit_renderer_pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: render_texture_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            multiview: None,
+        });
+
+        Self {
+            bind_group,
+            render_pipeline,
+        }
+    }
+
+    /// Draw the frame onto a `surface_size` sized `render_target`, clearing the remainder to black.
+    pub fn render(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        render_target: &wgpu::TextureView,
+        surface_size: (u32, u32),
+        mode: ScalingMode,
+    ) {
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("fit_renderer_render_pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: render_target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        let (x, y, width, height) = fit_viewport(mode, surface_size.0 as f32, surface_size.1 as f32);
+
+        // Happens while the window is minimised, an empty viewport isn't valid.
+        if width < 1.0 || height < 1.0 {
+            return;
+        }
+
+        rpass.set_pipeline(&self.render_pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_viewport(x, y, width, height, 0.0, 1.0);
+        rpass.draw(0..3, 0..1);
+    }
+}
+
+/// The area of the surface the frame should be drawn in, as `(x, y, width, height)`.
+///
+/// Everything but [ScalingMode::Stretch] keeps the GBA's `3:2` aspect ratio, centring the frame between black bars.
+fn fit_viewport(mode: ScalingMode, surface_width: f32, surface_height: f32) -> (f32, f32, f32, f32) {
+    match mode {
+        ScalingMode::Stretch => (0.0, 0.0, surface_width, surface_height),
+        ScalingMode::Integer | ScalingMode::AspectFit => {
+            let (frame_width, frame_height) = (grba_core::DISPLAY_WIDTH as f32, grba_core::DISPLAY_HEIGHT as f32);
+            let scale = (surface_width / frame_width).min(surface_height / frame_height);
+            let (width, height) = (frame_width * scale, frame_height * scale);
+
+            (
+                (surface_width - width) / 2.0,
+                (surface_height - height) / 2.0,
+                width,
+                height,
+            )
+        }
+    }
+}
@@ -1,4 +1,6 @@
+use crate::config::ScalingMode;
 use crate::gui::EguiFramework;
+use crate::rendering::fit::FitRenderer;
 use crate::rendering::framerate::FrameRate;
 
 use crate::State;
@@ -9,15 +11,22 @@ use std::time::{Duration, Instant};
 use winit::event_loop::EventLoop;
 use winit::window::{Fullscreen, Window, WindowId};
 
+mod fit;
 mod framerate;
 
 pub const SCALE_FACTOR_MULTIPLIER: f32 = 1.2;
 
+/// The emulation speed relative to the GBA's native frame rate, for the given `fps`.
+pub fn speed_percentage(fps: f32) -> f32 {
+    fps / 60.0 * 100.0
+}
+
 #[derive(Debug, Clone)]
 pub struct RendererOptions {
     pub title: String,
     pub width: u32,
     pub height: u32,
+    pub present_mode: wgpu::PresentMode,
 }
 
 impl Default for RendererOptions {
@@ -26,15 +35,25 @@ impl Default for RendererOptions {
             title: "GRBA".to_string(),
             width: crate::WIDTH,
             height: crate::HEIGHT,
+            present_mode: wgpu::PresentMode::Immediate,
         }
     }
 }
 
 pub struct Renderer {
     pub pixels: Pixels,
+    /// Used instead of the `pixels` scaling renderer for anything but [ScalingMode::Integer].
+    fit_renderer: FitRenderer,
+    /// The physical size of the window's surface.
+    surface_size: (u32, u32),
     primary_window: Window,
     framerate: framerate::FrameRate,
     last_title_update: Instant,
+    present_mode: wgpu::PresentMode,
+    /// The most recent distinct frame, only kept up to date while frame blending is enabled.
+    last_frame: Vec<u8>,
+    /// The distinct frame before [Self::last_frame].
+    previous_frame: Vec<u8>,
 }
 
 impl Renderer {
@@ -58,15 +77,23 @@ impl Renderer {
                     force_fallback_adapter: false,
                     compatible_surface: None,
                 })
-                .present_mode(wgpu::PresentMode::Immediate)
+                .present_mode(options.present_mode)
                 .build()?
         };
 
+        let fit_renderer = FitRenderer::new(pixels.context(), pixels.render_texture_format());
+        let window_size = window.inner_size();
+
         Ok(Self {
             pixels,
+            fit_renderer,
+            surface_size: (window_size.width, window_size.height),
             primary_window: window,
             framerate: FrameRate::new(),
             last_title_update: Instant::now(),
+            present_mode: options.present_mode,
+            last_frame: vec![0; grba_core::FRAMEBUFFER_SIZE * 4],
+            previous_frame: vec![0; grba_core::FRAMEBUFFER_SIZE * 4],
         })
     }
 
@@ -81,16 +108,35 @@ impl Renderer {
     ) -> anyhow::Result<Duration> {
         let frame = self.pixels.frame_mut();
 
-        frame.copy_from_slice(framebuffer);
+        if state.settings.frame_blending {
+            // While paused the same frame is rendered repeatedly, which shouldn't push the previous frame out.
+            if self.last_frame != framebuffer {
+                std::mem::swap(&mut self.previous_frame, &mut self.last_frame);
+                self.last_frame.copy_from_slice(framebuffer);
+            }
+
+            blend_frames(frame, &self.last_frame, &self.previous_frame);
+        } else {
+            frame.copy_from_slice(framebuffer);
+        }
+
+        if gui.gui.debug_view.state.tile_grid_overlay {
+            draw_tile_grid(frame);
+        }
 
-        let next_repaint = gui.prepare(&self.primary_window, state);
+        let next_repaint = gui.prepare(&self.primary_window, state, self.framerate.fps());
+        let scaling_mode = state.settings.scaling_mode;
+        let (fit_renderer, surface_size) = (&self.fit_renderer, self.surface_size);
 
         // Render everything together
         let result = self
             .pixels
             .render_with(|encoder, render_target, context| {
                 // Render the world texture
-                context.scaling_renderer.render(encoder, render_target);
+                match scaling_mode {
+                    ScalingMode::Integer => context.scaling_renderer.render(encoder, render_target),
+                    _ => fit_renderer.render(encoder, render_target, surface_size, scaling_mode),
+                }
 
                 // Render egui
                 gui.render(encoder, render_target, context)?;
@@ -122,6 +168,7 @@ impl Renderer {
         // Resize the window
         if let Some(size) = input.window_resized() {
             let _ = self.pixels.resize_surface(size.width, size.height);
+            self.surface_size = (size.width, size.height);
             gui.resize(size.width, size.height);
         }
 
@@ -129,7 +176,7 @@ impl Renderer {
         if self.last_title_update.elapsed().as_secs() >= 1 {
             let fps = self.framerate.fps();
             self.primary_window
-                .set_title(&format!("GRBA - [{:.1} FPS | {:.0}%]", fps, fps / 60.0 * 100.0));
+                .set_title(&format!("GRBA - [{:.1} FPS | {:.0}%]", fps, speed_percentage(fps)));
             self.last_title_update = Instant::now();
         }
 
@@ -145,6 +192,11 @@ impl Renderer {
         self.framerate.fps()
     }
 
+    /// Whether presenting a frame blocks until the monitor's next vertical blank.
+    pub fn is_vsync(&self) -> bool {
+        self.present_mode == wgpu::PresentMode::Fifo
+    }
+
     pub fn request_redraw(&self) {
         self.primary_window.request_redraw();
     }
@@ -153,13 +205,53 @@ impl Renderer {
         self.primary_window.id()
     }
 
-    pub fn toggle_fullscreen(&self) {
-        match self.primary_window.fullscreen() {
-            None => {
-                self.primary_window
-                    .set_fullscreen(Some(Fullscreen::Borderless(self.primary_window.current_monitor())));
+    pub fn is_fullscreen(&self) -> bool {
+        self.primary_window.fullscreen().is_some()
+    }
+
+    /// Switch between borderless fullscreen and windowed mode.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the window is now fullscreen.
+    pub fn toggle_fullscreen(&self) -> bool {
+        let fullscreen = !self.is_fullscreen();
+        self.set_fullscreen(fullscreen);
+
+        fullscreen
+    }
+
+    pub fn set_fullscreen(&self, fullscreen: bool) {
+        if fullscreen {
+            self.primary_window
+                .set_fullscreen(Some(Fullscreen::Borderless(self.primary_window.current_monitor())));
+        } else {
+            self.primary_window.set_fullscreen(None);
+        }
+    }
+}
+
+/// Write the average of the `current` and `previous` RGBA frames to `output`.
+fn blend_frames(output: &mut [u8], current: &[u8], previous: &[u8]) {
+    for ((out, &current), &previous) in output.iter_mut().zip(current).zip(previous) {
+        *out = ((current as u16 + previous as u16) / 2) as u8;
+    }
+}
+
+/// Tint every pixel on the edge of an 8x8 tile in the RGBA `frame`, without touching the emulator's own framebuffer.
+fn draw_tile_grid(frame: &mut [u8]) {
+    const GRID_COLOUR: [u8; 3] = [0xFF, 0x00, 0xFF];
+
+    for (i, pixel) in frame.chunks_exact_mut(4).enumerate() {
+        let (x, y) = (
+            i % grba_core::DISPLAY_WIDTH as usize,
+            i / grba_core::DISPLAY_WIDTH as usize,
+        );
+
+        if x % 8 == 0 || y % 8 == 0 {
+            for (channel, grid) in pixel.iter_mut().zip(GRID_COLOUR) {
+                *channel = ((*channel as u16 + grid as u16) / 2) as u8;
             }
-            Some(_) => self.primary_window.set_fullscreen(None),
         }
     }
 }
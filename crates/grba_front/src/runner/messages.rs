@@ -1,5 +1,8 @@
 use crate::gui::{DebugMessageResponse, DebugMessageUi};
+use grba_core::emulator::cartridge::header::CartBackupId;
+use grba_core::emulator::frame::RgbaFrame;
 use grba_core::InputKeys;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum EmulatorMessage {
@@ -9,6 +12,12 @@ pub enum EmulatorMessage {
     KeyDown(InputKeys),
     KeyUp(InputKeys),
     Reset,
+    /// Set [grba_core::emulator::EmuOptions::skip_bios], and reset the emulator to apply it.
+    SetSkipBios(bool),
+    /// Set [grba_core::emulator::EmuOptions::backup_override], and reset the emulator to apply it.
+    SetBackupOverride(Option<CartBackupId>),
+    /// Dump the raw VRAM, OAM, and palette RAM to the given directory.
+    DumpPpuMemory(PathBuf),
     Pause,
     Unpause,
 }
@@ -16,4 +25,8 @@ pub enum EmulatorMessage {
 #[derive(Debug)]
 pub enum EmulatorResponse {
     Debug(DebugMessageResponse),
+    /// The emulator completed the amount of frames it was limited to, and paused itself.
+    ///
+    /// Contains the final frame, which is still sent as usual right after this response.
+    FrameLimitReached(RgbaFrame),
 }
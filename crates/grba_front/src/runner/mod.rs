@@ -3,20 +3,26 @@ use crossbeam::channel::{unbounded, Receiver, Sender};
 
 use crate::gui::DebugViewManager;
 use crate::runner::frame_exchanger::{ExchangerReceiver, ExchangerSender};
+use grba_core::emulator::cartridge::header::CartBackupId;
 use grba_core::emulator::cartridge::Cartridge;
 use grba_core::emulator::debug::DebugEmulator;
 use grba_core::emulator::frame::RgbaFrame;
 
-use grba_core::emulator::EmuOptions;
 use grba_core::emulator::GBAEmulator;
+use grba_core::emulator::{BiosSkipState, EmuOptions};
+use grba_core::error::CoreError;
 use grba_core::InputKeys;
+use std::path::PathBuf;
 use std::thread::JoinHandle;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use winit::event::{ElementState, KeyboardInput, VirtualKeyCode};
 
 pub mod frame_exchanger;
 pub mod messages;
 
+/// How often writes to the cartridge's save are flushed to disk while the emulator is running.
+const SAVE_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+
 pub struct EmulatorRunner {
     rom: Cartridge,
     bios: Option<Vec<u8>>,
@@ -27,7 +33,18 @@ impl EmulatorRunner {
         Self { rom, bios }
     }
 
-    pub fn run(self, start_paused: bool, skip_bios: bool) -> RunnerHandle {
+    /// Start the emulator on its own thread.
+    ///
+    /// If a `frame_limit` is given the emulator pauses itself once it has completed that many frames, see
+    /// [EmulatorResponse::FrameLimitReached].
+    pub fn run(
+        self,
+        start_paused: bool,
+        skip_bios: bool,
+        backup_override: Option<CartBackupId>,
+        clock_scale: f32,
+        frame_limit: Option<u64>,
+    ) -> RunnerHandle {
         let (request_sender, request_receiver) = unbounded::<EmulatorMessage>();
         let (response_sender, response_receiver) = unbounded::<EmulatorResponse>();
         let (frame_sender, frame_receiver) = frame_exchanger::exchangers(RgbaFrame::default());
@@ -39,14 +56,36 @@ impl EmulatorRunner {
         let emu_thread = std::thread::spawn(move || {
             profiling::register_thread!("Emulator Thread");
 
-            let emu_options = EmuOptions {
+            let mut emu_options = EmuOptions {
                 bios: self.bios,
                 skip_bios,
+                backup_override,
+                clock_scale,
                 ..Default::default()
             };
 
-            let mut emulator = create_emulator(self.rom, emu_options);
-            run_emulator(&mut emulator, frame_sender, response_sender, request_receiver);
+            // Breaking on a divergence requires debugging, and the reference traces (like `grba_log`) expect the
+            // minimal post-BIOS state.
+            if crate::debug::trace_comparer().is_some() {
+                emu_options.debugging = true;
+                emu_options.skip_bios_state = BiosSkipState::Minimal;
+            }
+
+            let mut emulator = match create_emulator(self.rom, emu_options) {
+                Ok(emulator) => emulator,
+                Err(e) => {
+                    log::error!("Failed to create emulator due to: {}", e);
+                    return;
+                }
+            };
+            run_emulator(
+                &mut emulator,
+                frame_sender,
+                response_sender,
+                request_receiver,
+                frame_limit,
+            );
+            flush_save(&mut emulator);
         });
 
         RunnerHandle {
@@ -110,7 +149,10 @@ fn run_emulator(
     frame_sender: ExchangerSender<RgbaFrame>,
     response_sender: Sender<EmulatorResponse>,
     request_receiver: Receiver<EmulatorMessage>,
+    mut frame_limit: Option<u64>,
 ) {
+    let mut last_save_flush = Instant::now();
+
     'mainloop: loop {
         profiling::scope!("Emulator Loop");
 
@@ -135,10 +177,16 @@ fn run_emulator(
                 EmulatorMessage::Unpause => {
                     log::info!("Tried to unpause when not paused");
                 }
-                EmulatorMessage::Reset => {
-                    log::trace!("Resetting Emulator");
-                    emu.reset();
+                EmulatorMessage::Reset => reset_emulator(emu),
+                EmulatorMessage::SetSkipBios(skip_bios) => {
+                    emu.options.skip_bios = skip_bios;
+                    reset_emulator(emu)
+                }
+                EmulatorMessage::SetBackupOverride(backup_id) => {
+                    emu.options.backup_override = backup_id;
+                    reset_emulator(emu)
                 }
+                EmulatorMessage::DumpPpuMemory(path) => dump_ppu_memory(emu, path),
             }
         }
 
@@ -154,10 +202,29 @@ fn run_emulator(
             emu.run_to_vblank();
         }
 
+        if last_save_flush.elapsed() >= SAVE_FLUSH_INTERVAL {
+            flush_save(emu);
+            last_save_flush = Instant::now();
+        }
+
+        let limit_reached = frame_limit.map_or(false, |limit| emu.frame_count() >= limit);
+
+        // Sent before the frame itself, so that the UI never waits on a frame which won't come while we're paused.
+        if limit_reached {
+            frame_limit = None;
+            response_sender
+                .send(EmulatorResponse::FrameLimitReached(emu.frame_buffer().clone()))
+                .expect("Failed to send response");
+        }
+
         if let Err(e) = frame_sender.send(emu.frame_buffer()) {
             log::error!("Failed to transfer framebuffer due to: {:#}", e);
             break;
         }
+
+        if limit_reached && pause_loop(emu, &response_sender, &request_receiver, &frame_sender) {
+            break 'mainloop;
+        }
     }
 }
 
@@ -201,10 +268,16 @@ fn pause_loop(
                 }
                 EmulatorMessage::Pause => log::info!("Tried to pause when already paused"),
                 EmulatorMessage::Unpause => break 'pause_loop false,
-                EmulatorMessage::Reset => {
-                    log::trace!("Resetting Emulator");
-                    emu.reset();
+                EmulatorMessage::Reset => reset_emulator(emu),
+                EmulatorMessage::SetSkipBios(skip_bios) => {
+                    emu.options.skip_bios = skip_bios;
+                    reset_emulator(emu)
+                }
+                EmulatorMessage::SetBackupOverride(backup_id) => {
+                    emu.options.backup_override = backup_id;
+                    reset_emulator(emu)
                 }
+                EmulatorMessage::DumpPpuMemory(path) => dump_ppu_memory(emu, path),
             }
         }
 
@@ -212,7 +285,32 @@ fn pause_loop(
     }
 }
 
-fn create_emulator(rom: Cartridge, options: EmuOptions) -> GBAEmulator {
+fn flush_save(emu: &mut GBAEmulator) {
+    if let Err(e) = emu.flush_save() {
+        log::error!("Failed to flush save due to: {}", e);
+    }
+}
+
+fn reset_emulator(emu: &mut GBAEmulator) {
+    log::trace!("Resetting Emulator");
+    if let Err(e) = emu.reset() {
+        log::error!("Failed to reset emulator due to: {}", e);
+        return;
+    }
+
+    if let Some(comparer) = crate::debug::trace_comparer() {
+        comparer.reset();
+    }
+}
+
+fn dump_ppu_memory(emu: &GBAEmulator, path: PathBuf) {
+    match emu.dump_ppu_memory(&path) {
+        Ok(_) => log::info!("Dumped PPU memory to: {:?}", path),
+        Err(e) => log::error!("Failed to dump PPU memory to {:?} due to: {:#}", path, e),
+    }
+}
+
+fn create_emulator(rom: Cartridge, options: EmuOptions) -> Result<GBAEmulator, CoreError> {
     log::info!("Created emulator for ROM: {:#?}", rom.header());
     GBAEmulator::new(rom, options)
 }
@@ -2,13 +2,24 @@ use grba_core::emulator::ppu::RGBA;
 use grba_core::FRAMEBUFFER_SIZE;
 use image::imageops::FilterType;
 use image::ImageBuffer;
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 
 pub struct MainArgs {
     pub execute_path: Option<PathBuf>,
     pub start_paused: bool,
-    pub bios: PathBuf,
+    /// Overrides the BIOS set in the [crate::config::Settings] for this session.
+    pub bios: Option<PathBuf>,
     pub start_bios: bool,
+    /// The amount of frames to emulate before pausing (or exiting if `then_exit` is set).
+    pub frames: Option<u32>,
+    /// Whether to exit the application once `frames` have been emulated.
+    pub then_exit: bool,
+    /// Where to save a screenshot of the final frame once `frames` have been emulated.
+    pub screenshot_on_exit: Option<PathBuf>,
+    /// A reference trace to compare against while running, halting at the first divergence.
+    #[cfg(feature = "bin-logging")]
+    pub reference_trace: Option<PathBuf>,
 }
 
 pub fn parse_main_args() -> Option<MainArgs> {
@@ -17,10 +28,12 @@ pub fn parse_main_args() -> Option<MainArgs> {
     Some(MainArgs {
         start_paused: parser.contains(["-p", "--paused"]),
         start_bios: parser.contains(["-s", "--start-bios"]),
-        bios: parser
-            .opt_value_from_str("--bios")
-            .ok()?
-            .unwrap_or("roms/gba_bios.bin".into()),
+        bios: parser.opt_value_from_str("--bios").ok()?,
+        frames: parser.opt_value_from_str("--frames").ok()?,
+        then_exit: parser.contains("--then-exit"),
+        screenshot_on_exit: parser.opt_value_from_str("--screenshot-on-exit").ok()?,
+        #[cfg(feature = "bin-logging")]
+        reference_trace: parser.opt_value_from_str("--reference-trace").ok()?,
         execute_path: parser.opt_free_from_str().ok()?,
     })
 }
@@ -44,3 +57,36 @@ pub fn save_rgba_image(framebuffer: &[RGBA; FRAMEBUFFER_SIZE], path: impl AsRef<
     let temp_buffer = image::imageops::resize(&temp_buffer, 320, 288, FilterType::Nearest);
     temp_buffer.save(path).unwrap();
 }
+
+/// Save the given RGBA `frame`, as received from the emulator thread, to `path` at the native GBA resolution.
+pub fn save_rgba_frame(frame: &[u8], path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let buffer: ImageBuffer<image::Rgba<u8>, &[u8]> =
+        image::ImageBuffer::from_raw(grba_core::DISPLAY_WIDTH, grba_core::DISPLAY_HEIGHT, frame)
+            .ok_or_else(|| anyhow::anyhow!("Frame has an invalid size: {}", frame.len()))?;
+
+    buffer.save(path)?;
+
+    Ok(())
+}
+
+/// Copy the given RGBA `frame`, as received from the emulator thread, to the system clipboard as an image.
+///
+/// If the platform doesn't support images on the clipboard the frame is saved as a temporary PNG instead, and its path
+/// is copied.
+pub fn copy_frame_to_clipboard(clipboard: &mut arboard::Clipboard, frame: &[u8]) -> anyhow::Result<()> {
+    let image = arboard::ImageData {
+        width: grba_core::DISPLAY_WIDTH as usize,
+        height: grba_core::DISPLAY_HEIGHT as usize,
+        bytes: Cow::Borrowed(frame),
+    };
+
+    if let Err(e) = clipboard.set_image(image) {
+        log::debug!("Couldn't copy frame as an image, falling back to a file: {}", e);
+
+        let path = std::env::temp_dir().join("grba_frame.png");
+        save_rgba_frame(frame, &path)?;
+        clipboard.set_text(path.to_string_lossy())?;
+    }
+
+    Ok(())
+}
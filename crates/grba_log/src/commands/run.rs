@@ -2,7 +2,7 @@ use crate::bin_logger::InstructionLogger;
 use crate::format::{DiffItem, DiffItemWithInstr};
 use crate::InstructionSnapshot;
 use anyhow::Context;
-use grba_core::emulator::{EmuOptions, GBAEmulator};
+use grba_core::emulator::{BiosSkipState, EmuOptions, GBAEmulator};
 use std::fs::read;
 use std::panic;
 use std::path::{Path, PathBuf};
@@ -129,14 +129,14 @@ impl RunExecutor {
 
 fn create_emulator(rom: &Path, bios: Option<&Path>) -> anyhow::Result<GBAEmulator> {
     let rom_data = std::fs::read(rom)?;
-    let ram_data = vec![0u8; grba_core::emulator::cartridge::CARTRIDGE_RAM_SIZE];
-    let cartridge = grba_core::emulator::cartridge::Cartridge::new(rom_data, Box::new(ram_data));
-
-    let mut emu_opts = EmuOptions::default();
-    if let Some(bios) = bios {
-        let bios_data = std::fs::read(bios)?;
-        emu_opts.bios = Some(bios_data);
-    }
-
-    Ok(grba_core::emulator::GBAEmulator::new(cartridge, emu_opts))
+    let bios_data = bios.map(std::fs::read).transpose()?;
+
+    Ok(grba_core::headless::headless_emulator(
+        rom_data,
+        bios_data,
+        EmuOptions {
+            skip_bios_state: BiosSkipState::Minimal,
+            ..Default::default()
+        },
+    )?)
 }
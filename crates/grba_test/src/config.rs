@@ -12,6 +12,11 @@ pub struct TestConfig {
     pub output_path: PathBuf,
     pub snapshot_path: PathBuf,
     pub bios_path: PathBuf,
+    /// The maximum amount of instructions a single test may execute before it's aborted as timed out.
+    ///
+    /// Guards against ROMs which hang, `None` lets every test run to completion.
+    #[serde(default)]
+    pub max_instructions: Option<u64>,
     pub custom_configs: HashMap<RomName, CustomRomTest>,
 }
 
@@ -22,6 +27,21 @@ pub struct CustomRomTest {
     /// The sequence of instructions to run *after* the initial `num_frames`
     #[serde(default)]
     pub sequences: HashMap<String, Vec<TestSequenceInstructions>>,
+    /// Tags/categories for this ROM, used to run a subset of all tests with `--filter`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// The expected result of all tests for this ROM.
+    #[serde(default)]
+    pub expected: TestExpectation,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TestExpectation {
+    /// The test is expected to match its snapshot, any failure is a regression.
+    #[default]
+    Pass,
+    /// The test is known to fail, its result is reported separately and won't fail the test run.
+    KnownFail,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
@@ -51,6 +71,7 @@ impl Default for TestConfig {
             output_path: PathBuf::from("./grba_test_output"),
             snapshot_path: PathBuf::from("./test_roms/expected"),
             bios_path: PathBuf::from("./roms/gba_bios.bin"),
+            max_instructions: None,
             custom_configs: Default::default(),
         }
     }
@@ -92,6 +113,25 @@ pub struct ClapArgs {
     /// The amount of frames to emulate
     #[clap(short, default_value = "5")]
     pub frames: u32,
+    /// The maximum amount of instructions a single test may execute, if not provided the config's value will be used
+    #[clap(long)]
+    pub max_instructions: Option<u64>,
     /// The amount of threads to use, by default will use as many threads as the system has.
     pub num_threads: Option<NonZeroUsize>,
+    /// Only run the tests for ROMs with the given tag in their custom config.
+    #[clap(long)]
+    pub filter: Option<String>,
+    /// Only run the test with the given ID, the ROM's file name without extension, or `{name}_{sequence}` for sequences.
+    #[clap(long)]
+    pub rom: Option<String>,
+    /// Write every emulated frame of the `--rom` test to the output directory, and print the CPU state at the end.
+    ///
+    /// Runs the test directly instead of comparing it against its snapshots.
+    #[clap(long, requires = "rom")]
+    pub dump_each_frame: bool,
+    /// Skip the image comparison for tests whose frames have the same hash as in the last fully passing run.
+    ///
+    /// Tests with a changed hash are still compared against their snapshots, producing comparison images on failure.
+    #[clap(long)]
+    pub hash_compare: bool,
 }
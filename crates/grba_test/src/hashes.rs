@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::CapturedFrame;
+
+const HASHES_FILE_NAME: &str = "frame_hashes.json";
+
+/// A cache of the frame hashes of all tests which matched their snapshots in a previous run.
+///
+/// Used to skip the (comparatively slow) image comparison for tests whose output didn't change.
+/// The cache is stored next to the snapshots, and is only updated after a fully passing test run.
+#[derive(Debug, Default)]
+pub struct FrameHashes {
+    path: PathBuf,
+    hashes: HashMap<String, u64>,
+}
+
+impl FrameHashes {
+    /// Load the cache from the given `snapshot_path`, if no cache exists yet an empty one is returned.
+    pub fn load(snapshot_path: &Path) -> anyhow::Result<Self> {
+        let path = snapshot_path.join(HASHES_FILE_NAME);
+        let hashes = if path.exists() { serde_json::from_reader(std::fs::File::open(&path)?)? } else { HashMap::new() };
+
+        Ok(Self { path, hashes })
+    }
+
+    pub fn save(&self) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(std::fs::File::create(&self.path)?, &self.hashes)?;
+
+        Ok(())
+    }
+
+    /// Check whether all `frames` of the given test match their previously recorded hashes.
+    ///
+    /// If a frame has never been recorded this will return `false`.
+    pub fn matches(&self, test_id: &str, frames: &[CapturedFrame]) -> bool {
+        !frames.is_empty()
+            && frames
+                .iter()
+                .all(|frame| self.hashes.get(&frame_key(test_id, frame)) == Some(&frame.frame.hash()))
+    }
+
+    /// Record the hashes of all `frames` of the given test, overwriting any previous values.
+    pub fn record(&mut self, test_id: &str, frames: &[CapturedFrame]) {
+        for frame in frames {
+            self.hashes.insert(frame_key(test_id, frame), frame.frame.hash());
+        }
+    }
+}
+
+fn frame_key(test_id: &str, frame: &CapturedFrame) -> String {
+    match &frame.tag {
+        Some(tag) => format!("{test_id}_{tag}"),
+        None => test_id.to_string(),
+    }
+}
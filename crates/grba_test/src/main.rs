@@ -1,15 +1,23 @@
 use anyhow::Context;
 use clap::Parser;
 use emu_test_runner::formatters::simple::SimpleConsoleFormatter;
+use emu_test_runner::inputs::TestCandidate;
 use emu_test_runner::options::EmuRunnerOptions;
 use emu_test_runner::outputs::FrameOutput;
 use emu_test_runner::EmuTestRunner;
+use grba_core::emulator::debug::DebugEmulator;
+use grba_core::emulator::frame::RgbaFrame;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use std::time::Duration;
 
-use crate::config::{ClapArgs, TestSequenceInstructions};
-use crate::utils::MemoryRam;
+use crate::config::{ClapArgs, TestExpectation, TestSequenceInstructions};
+use crate::hashes::FrameHashes;
 
 mod config;
+mod hashes;
 mod utils;
 
 fn main() -> anyhow::Result<()> {
@@ -29,123 +37,349 @@ fn main() -> anyhow::Result<()> {
     let output_path = clap_args.output_path.unwrap_or(config.output_path.clone());
     let snapshots = config.snapshot_path.clone();
 
-    let (tests, test_id_sequence_map) = utils::find_all_tests(&test_roms, &config)?;
-
-    let formatter = Box::new(SimpleConsoleFormatter::new().with_progress(tests.len() as u64));
-    let options = EmuRunnerOptions {
-        output_path,
-        snapshot_path: snapshots,
-        num_threads: clap_args.num_threads.unwrap_or(config.num_threads),
-        expected_frame_width: grba_core::DISPLAY_WIDTH as usize,
-        expected_frame_height: grba_core::DISPLAY_HEIGHT as usize,
-        put_sequence_tests_in_subfolder: true,
-        copy_comparison_image: true,
-        timeout: Some(Duration::from_secs(5)),
-    };
-    let runner = EmuTestRunner::new(formatter, options)?;
+    let (mut tests, test_id_sequence_map) = utils::find_all_tests(&test_roms, &config, clap_args.filter.as_deref())?;
+
+    if let Some(rom) = clap_args.rom.as_deref() {
+        tests.retain(|test| test.rom_id == rom);
+        anyhow::ensure!(!tests.is_empty(), "No test found with the ID `{}`", rom);
+    }
+
+    // Known failures are run separately, to ensure they can't be confused with actual regressions.
+    let (known_failures, tests): (Vec<_>, Vec<_>) = tests.into_iter().partition(|test| {
+        test_id_sequence_map
+            .get(&test.rom_id)
+            .map_or(false, |cfg| cfg.expected == TestExpectation::KnownFail)
+    });
 
     let bios = std::fs::read(bios_path)?;
+    let max_instructions = clap_args.max_instructions.or(config.max_instructions);
 
-    let output = runner.run_tests(tests.into_iter(), |test, rom_data| {
-        if let Some(custom_conf) = test_id_sequence_map.get(&test.rom_id) {
+    let run_test_on = |rom_id: &str, emu: &mut TestEmulator| -> Result<Vec<CapturedFrame>, TimedOut> {
+        if let Some(custom_conf) = test_id_sequence_map.get(rom_id) {
             let frames_to_run = custom_conf.num_frames;
 
             if let Some(sequence) = custom_conf.sequence {
-                run_sequence_test(sequence, rom_data, frames_to_run, &bios)
+                run_sequence_test(emu, sequence, frames_to_run)
             } else {
-                vec![run_normal_test(rom_data, frames_to_run, &bios)]
+                run_normal_test(emu, frames_to_run).map(|frame| vec![frame])
             }
         } else {
-            vec![run_normal_test(rom_data, clap_args.frames, &bios)]
+            run_normal_test(emu, clap_args.frames).map(|frame| vec![frame])
         }
-    });
+    };
+
+    let run_test = |rom_id: &str, rom_data: Vec<u8>| -> Result<Vec<CapturedFrame>, TimedOut> {
+        run_test_on(rom_id, &mut TestEmulator::new(rom_data, &bios, max_instructions))
+    };
+    let print_timed_out = |rom_id: &str| {
+        println!(
+            "TIMED OUT: `{}` didn't finish within {} instructions",
+            rom_id,
+            max_instructions.unwrap_or(u64::MAX)
+        );
+    };
+
+    if clap_args.dump_each_frame {
+        let test = tests
+            .iter()
+            .chain(&known_failures)
+            .next()
+            .expect("Checked for a test above");
+        let dump_dir = output_path.join(&test.rom_id);
+        let mut emu = TestEmulator::new(std::fs::read(&test.rom_path)?, &bios, max_instructions);
+        emu.dump_frames_to = Some(dump_dir.clone());
+
+        let frames = run_test_on(&test.rom_id, &mut emu).unwrap_or_else(|TimedOut(frames)| {
+            print_timed_out(&test.rom_id);
+            frames
+        });
+
+        for frame in frames {
+            let name = frame.tag.as_deref().unwrap_or("final");
+            save_frame(&frame.frame, &dump_dir.join(format!("{}.png", name)))?;
+        }
+
+        println!("Dumped {} frames to: `{}`", emu.frames_run, dump_dir.display());
+        println!("Final CPU state: {:#X?}", DebugEmulator(&mut emu.emu).cpu().registers);
+
+        return Ok(());
+    }
+
+    let frame_hashes = FrameHashes::load(&snapshots)?;
+
+    // The frames of tests which were already emulated to compare their hashes, so they don't have to be run twice.
+    let mut first_pass_frames = HashMap::new();
+
+    let tests = if clap_args.hash_compare {
+        // Tests whose frames are identical to the last passing run can skip the image comparison entirely.
+        let results: Vec<_> = tests
+            .into_par_iter()
+            .map(|test| {
+                let result = std::fs::read(&test.rom_path)
+                    .ok()
+                    .map(|rom| run_test(&test.rom_id, rom));
+                // A timed out test always has to be reported, even if its partial frames happen to match.
+                let unchanged = matches!(&result, Some(Ok(frames)) if frame_hashes.matches(&test.rom_id, frames));
 
-    if output.is_err() {
-        println!("ERROR: {:#?}", output);
+                (test, unchanged, result.filter(|_| !unchanged))
+            })
+            .collect();
+        let (unchanged, changed): (Vec<_>, Vec<_>) = results.into_iter().partition(|(_, unchanged, _)| *unchanged);
+
+        println!(
+            "{} tests matched their recorded frame hashes, running the image comparison for the remaining {}",
+            unchanged.len(),
+            changed.len()
+        );
+
+        changed
+            .into_iter()
+            .map(|(test, _, result)| {
+                if let Some(result) = result {
+                    first_pass_frames.insert(test.rom_id.clone(), result);
+                }
+
+                test
+            })
+            .collect()
+    } else {
+        tests
+    };
+
+    let frame_hashes = Mutex::new(frame_hashes);
+    let first_pass_frames = Mutex::new(first_pass_frames);
+
+    let run_test_group = |tests: Vec<TestCandidate>, record_hashes: bool| -> anyhow::Result<bool> {
+        let formatter = Box::new(SimpleConsoleFormatter::new().with_progress(tests.len() as u64));
+        let options = EmuRunnerOptions {
+            output_path: output_path.clone(),
+            snapshot_path: snapshots.clone(),
+            num_threads: clap_args.num_threads.unwrap_or(config.num_threads),
+            expected_frame_width: grba_core::DISPLAY_WIDTH as usize,
+            expected_frame_height: grba_core::DISPLAY_HEIGHT as usize,
+            put_sequence_tests_in_subfolder: true,
+            copy_comparison_image: true,
+            timeout: Some(Duration::from_secs(5)),
+        };
+        let runner = EmuTestRunner::new(formatter, options)?;
+        // The partial frames of these are still compared, but could match the snapshot by accident.
+        let timed_out = Mutex::new(Vec::new());
+
+        let output = runner.run_tests(tests.into_iter(), |test, rom_data| {
+            let result = first_pass_frames.lock().unwrap().remove(&test.rom_id);
+            let frames = match result.unwrap_or_else(|| run_test(&test.rom_id, rom_data)) {
+                Ok(frames) => {
+                    if record_hashes {
+                        frame_hashes.lock().unwrap().record(&test.rom_id, &frames);
+                    }
+
+                    frames
+                }
+                Err(TimedOut(frames)) => {
+                    print_timed_out(&test.rom_id);
+                    timed_out.lock().unwrap().push(test.rom_id.clone());
+                    frames
+                }
+            };
+
+            frames.into_iter().map(FrameOutput::from).collect()
+        });
+
+        let timed_out = timed_out.into_inner().unwrap();
+
+        if !timed_out.is_empty() {
+            println!("FAILED: {} test(s) timed out: {:?}", timed_out.len(), timed_out);
+        }
+
+        if output.is_err() {
+            println!("ERROR: {:#?}", output);
+            Ok(false)
+        } else {
+            Ok(timed_out.is_empty())
+        }
+    };
+
+    let passed = run_test_group(tests, true)?;
+
+    // Only a fully passing run is guaranteed to have frames identical to the snapshots.
+    if passed {
+        frame_hashes.lock().unwrap().save()?;
+    }
+
+    if !known_failures.is_empty() {
+        println!(
+            "\nKnown failures ({}), these don't count as regressions:",
+            known_failures.len()
+        );
+        let _ = run_test_group(known_failures, false)?;
+    }
+
+    if !passed {
         std::process::exit(1);
     } else {
         Ok(())
     }
 }
 
-pub fn run_normal_test(rom: Vec<u8>, frames_to_run: u32, bios: &[u8]) -> FrameOutput {
-    let mut emu = construct_emu(rom, bios);
-
-    for _ in 0..frames_to_run {
-        emu.run_to_vblank();
-    }
+pub fn run_normal_test(emu: &mut TestEmulator, frames_to_run: u32) -> Result<CapturedFrame, TimedOut> {
+    emu.run_frames(frames_to_run)
+        .map_err(|_| TimedOut(vec![emu.capture_frame(None)]))?;
 
-    capture_emulator_frame(None, &mut emu)
+    Ok(emu.capture_frame(None))
 }
 
 pub fn run_sequence_test(
+    emu: &mut TestEmulator,
     sequence: &[TestSequenceInstructions],
-    rom: Vec<u8>,
     frames_to_run: u32,
-    bios: &[u8],
-) -> Vec<FrameOutput> {
-    let mut emu = construct_emu(rom, bios);
+) -> Result<Vec<CapturedFrame>, TimedOut> {
     let mut output_frames = Vec::with_capacity(sequence.len());
 
-    for _ in 0..frames_to_run {
-        emu.run_to_vblank();
+    if emu.run_frames(frames_to_run).is_err() {
+        return Err(TimedOut(output_frames));
     }
 
     for instruction in sequence {
-        handle_instruction(instruction, &mut emu, &mut output_frames);
+        if handle_instruction(instruction, emu, &mut output_frames).is_err() {
+            return Err(TimedOut(output_frames));
+        }
     }
 
-    output_frames
+    Ok(output_frames)
 }
 
 fn handle_instruction(
     instruction: &TestSequenceInstructions,
-    emu: &mut grba_core::emulator::GBAEmulator,
-    frame_buffer: &mut Vec<FrameOutput>,
-) {
+    emu: &mut TestEmulator,
+    frame_buffer: &mut Vec<CapturedFrame>,
+) -> Result<(), InstructionLimitReached> {
     match instruction {
         TestSequenceInstructions::DumpFrame(name) => {
-            frame_buffer.push(capture_emulator_frame(Some(name.clone()), emu));
-        }
-        TestSequenceInstructions::AdvanceFrames(to_advance) => {
-            for _ in 0..*to_advance {
-                emu.run_to_vblank();
-            }
+            frame_buffer.push(emu.capture_frame(Some(name.clone())));
         }
+        TestSequenceInstructions::AdvanceFrames(to_advance) => emu.run_frames(*to_advance)?,
         TestSequenceInstructions::Input(key) => {
-            handle_instruction(&TestSequenceInstructions::HoldInputFor(*key, 1), emu, frame_buffer)
+            handle_instruction(&TestSequenceInstructions::HoldInputFor(*key, 1), emu, frame_buffer)?
         }
         TestSequenceInstructions::HoldInputFor(key, to_advance) => handle_instruction(
             &TestSequenceInstructions::HoldInputForTimes(*key, *to_advance, 1),
             emu,
             frame_buffer,
-        ),
+        )?,
         TestSequenceInstructions::HoldInputForTimes(key, to_advance, times) => {
             for _ in 0..*times {
-                emu.key_down(*key);
-                handle_instruction(&TestSequenceInstructions::AdvanceFrames(*to_advance), emu, frame_buffer);
-                emu.key_up(*key);
-                emu.run_to_vblank();
+                emu.emu.set_input_state((*key).into());
+                handle_instruction(&TestSequenceInstructions::AdvanceFrames(*to_advance), emu, frame_buffer)?;
+                emu.emu.set_input_state(grba_core::InputState::empty());
+                emu.run_frames(1)?;
             }
         }
     }
+
+    Ok(())
 }
 
-fn construct_emu(rom: Vec<u8>, bios: &[u8]) -> grba_core::emulator::GBAEmulator {
-    let emu_options = grba_core::emulator::EmuOptions {
-        skip_bios: true,
-        bios: Some(bios.to_owned()),
-        debugging: false,
-    };
-    let cartridge = grba_core::emulator::cartridge::Cartridge::new(rom, Box::new(MemoryRam::default()));
-    let emu = grba_core::emulator::GBAEmulator::new(cartridge, emu_options);
+/// Returned once a test has executed its maximum amount of instructions, see [TestEmulator].
+#[derive(Debug)]
+pub struct InstructionLimitReached;
 
-    emu
+/// A test which was aborted after reaching its instruction limit, with all frames captured up to that point.
+pub struct TimedOut(pub Vec<CapturedFrame>);
+
+/// An emulator which can only execute a limited amount of instructions over its entire lifetime.
+pub struct TestEmulator {
+    emu: grba_core::emulator::GBAEmulator,
+    instructions_left: u64,
+    /// The amount of frames emulated so far.
+    frames_run: u32,
+    /// If set, every emulated frame is written to this directory as `frame_{number}.png`.
+    dump_frames_to: Option<PathBuf>,
 }
 
-fn capture_emulator_frame(suffix: Option<String>, emu: &mut grba_core::emulator::GBAEmulator) -> FrameOutput {
-    FrameOutput {
+impl TestEmulator {
+    /// Create an emulator for the given `rom` which skips the BIOS, with an optional limit on the total amount of
+    /// instructions it may execute.
+    pub fn new(rom: Vec<u8>, bios: &[u8], max_instructions: Option<u64>) -> Self {
+        let emu_options = grba_core::emulator::EmuOptions {
+            skip_bios: true,
+            debugging: false,
+            ..Default::default()
+        };
+
+        Self {
+            emu: grba_core::headless::headless_emulator(rom, Some(bios.to_owned()), emu_options)
+                .expect("Invalid ROM or BIOS"),
+            instructions_left: max_instructions.unwrap_or(u64::MAX),
+            frames_run: 0,
+            dump_frames_to: None,
+        }
+    }
+
+    /// Run the given amount of frames, stopping early if the instruction limit is reached.
+    pub fn run_frames(&mut self, frames: u32) -> Result<(), InstructionLimitReached> {
+        for _ in 0..frames {
+            match self.emu.run_to_vblank_limited(self.instructions_left) {
+                Some(executed) => self.instructions_left -= executed,
+                None => {
+                    self.instructions_left = 0;
+                    return Err(InstructionLimitReached);
+                }
+            }
+
+            self.frames_run += 1;
+
+            if let Some(dir) = &self.dump_frames_to {
+                let path = dir.join(format!("frame_{:05}.png", self.frames_run));
+
+                if let Err(e) = save_frame(self.emu.frame_buffer(), &path) {
+                    println!("Failed to dump frame to `{}`: {:#}", path.display(), e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn capture_frame(&mut self, suffix: Option<String>) -> CapturedFrame {
+        capture_emulator_frame(suffix, &mut self.emu)
+    }
+}
+
+fn capture_emulator_frame(suffix: Option<String>, emu: &mut grba_core::emulator::GBAEmulator) -> CapturedFrame {
+    CapturedFrame {
         tag: suffix,
-        frame: emu_test_runner::outputs::RgbaFrame(emu.frame_buffer().as_bytes().to_vec()),
+        frame: emu.frame_buffer().clone(),
+    }
+}
+
+/// Save the given `frame` as an image at `path`, creating any missing directories.
+fn save_frame(frame: &RgbaFrame, path: &Path) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    image::save_buffer(
+        path,
+        frame.as_bytes(),
+        grba_core::DISPLAY_WIDTH,
+        grba_core::DISPLAY_HEIGHT,
+        image::ColorType::Rgba8,
+    )?;
+
+    Ok(())
+}
+
+/// A frame captured from the emulator, before it's handed to the test runner.
+pub struct CapturedFrame {
+    pub tag: Option<String>,
+    pub frame: RgbaFrame,
+}
+
+impl From<CapturedFrame> for FrameOutput {
+    fn from(value: CapturedFrame) -> Self {
+        FrameOutput {
+            tag: value.tag,
+            frame: emu_test_runner::outputs::RgbaFrame(value.frame.as_bytes().to_vec()),
+        }
     }
 }
@@ -1,19 +1,23 @@
 use std::collections::HashMap;
-use std::ops::{Deref, DerefMut};
 use std::path::Path;
 
 use emu_test_runner::inputs::{get_rom_fs_id, TestCandidate};
 
-use crate::config::{TestConfig, TestSequenceInstructions};
+use crate::config::{TestConfig, TestExpectation, TestSequenceInstructions};
 
 pub struct CustomRomTestSequence<'a> {
     pub num_frames: u32,
     pub sequence: Option<&'a Vec<TestSequenceInstructions>>,
+    pub expected: TestExpectation,
 }
 
+/// Find all test ROMs in the given `path`.
+///
+/// If a `tag_filter` is provided only the ROMs which have that tag in their custom config are returned.
 pub fn find_all_tests<'a>(
     path: &Path,
     config: &'a TestConfig,
+    tag_filter: Option<&str>,
 ) -> anyhow::Result<(Vec<TestCandidate>, HashMap<String, CustomRomTestSequence<'a>>)> {
     let files = emu_test_runner::inputs::list_files_with_extensions(path, ".gba")?;
     let mut sequences = HashMap::new();
@@ -28,7 +32,15 @@ pub fn find_all_tests<'a>(
                 return vec![];
             }
 
-            if let Some(cfg) = config.custom_configs.get(basic_rom_id.as_ref()) {
+            let custom_config = config.custom_configs.get(basic_rom_id.as_ref());
+
+            if let Some(tag) = tag_filter {
+                if !custom_config.map_or(false, |cfg| cfg.tags.iter().any(|t| t == tag)) {
+                    return vec![];
+                }
+            }
+
+            if let Some(cfg) = custom_config {
                 if !cfg.sequences.is_empty() {
                     return cfg
                         .sequences
@@ -39,6 +51,7 @@ pub fn find_all_tests<'a>(
                                 CustomRomTestSequence {
                                     num_frames: cfg.num_frames,
                                     sequence: Some(sequence),
+                                    expected: cfg.expected,
                                 },
                             );
                         })
@@ -50,6 +63,7 @@ pub fn find_all_tests<'a>(
                         CustomRomTestSequence {
                             num_frames: cfg.num_frames,
                             sequence: None,
+                            expected: cfg.expected,
                         },
                     );
                 }
@@ -61,29 +75,3 @@ pub fn find_all_tests<'a>(
 
     Ok((out, sequences))
 }
-
-pub struct MemoryRam {
-    data: Box<[u8; grba_core::emulator::cartridge::CARTRIDGE_RAM_SIZE]>,
-}
-
-impl Default for MemoryRam {
-    fn default() -> Self {
-        Self {
-            data: grba_core::box_array![0; grba_core::emulator::cartridge::CARTRIDGE_RAM_SIZE],
-        }
-    }
-}
-
-impl Deref for MemoryRam {
-    type Target = [u8];
-
-    fn deref(&self) -> &Self::Target {
-        &*self.data
-    }
-}
-
-impl DerefMut for MemoryRam {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut *self.data
-    }
-}